@@ -0,0 +1,92 @@
+use std::fmt;
+
+use crate::attributes::registry::AttributeTarget;
+use crate::parser::ast::Position;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AttributeError {
+    UnknownAttribute(String),
+    InvalidTarget {
+        name: String,
+        target: AttributeTarget,
+    },
+    ArgumentCount {
+        name: String,
+        min: usize,
+        max: Option<usize>,
+        found: usize,
+    },
+    Duplicate(String),
+}
+
+impl AttributeError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            AttributeError::UnknownAttribute(_) => "A0001",
+            AttributeError::InvalidTarget { .. } => "A0002",
+            AttributeError::ArgumentCount { .. } => "A0003",
+            AttributeError::Duplicate(_) => "A0004",
+        }
+    }
+}
+
+impl fmt::Display for AttributeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AttributeError::UnknownAttribute(name) => write!(f, "Unknown attribute @{}", name),
+            AttributeError::InvalidTarget { name, target } => {
+                write!(f, "Attribute @{} cannot be applied to a {}", name, target)
+            }
+            AttributeError::ArgumentCount {
+                name,
+                min,
+                max,
+                found,
+            } => match max {
+                Some(max) if max == min => write!(
+                    f,
+                    "Attribute @{} expects {} arguments, found {}",
+                    name, min, found
+                ),
+                Some(max) => write!(
+                    f,
+                    "Attribute @{} expects {} to {} arguments, found {}",
+                    name, min, max, found
+                ),
+                None => write!(
+                    f,
+                    "Attribute @{} expects at least {} arguments, found {}",
+                    name, min, found
+                ),
+            },
+            AttributeError::Duplicate(name) => write!(f, "Attribute @{} is repeated", name),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttributeDiagnostic {
+    pub error: AttributeError,
+    pub position: Position,
+}
+
+impl AttributeDiagnostic {
+    pub fn code(&self) -> &'static str {
+        self.error.code()
+    }
+}
+
+impl fmt::Display for AttributeDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "error[{}] {}:{}: {}",
+            self.code(),
+            self.position.line,
+            self.position.column,
+            self.error
+        )
+    }
+}
+
+impl std::error::Error for AttributeDiagnostic {}
@@ -0,0 +1,8 @@
+#![allow(dead_code, unused, unused_imports)]
+
+pub mod error;
+pub mod registry;
+
+// Re-export main types for easier access
+pub use error::{AttributeDiagnostic, AttributeError};
+pub use registry::{Annotated, AttributeRegistry, AttributeSpec, AttributeTarget};
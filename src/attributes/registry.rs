@@ -0,0 +1,383 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::attributes::error::{AttributeDiagnostic, AttributeError};
+use crate::parser::ast::{
+    Attribute, Block, EnumDecl, Expr, ExprKind, Field, FunctionDecl, Item, Program, Stmt, StmtKind,
+    StringPart, StructDecl, Variant, VariantKind,
+};
+
+// Kinds of node an attribute can be written on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AttributeTarget {
+    Function,
+    Struct,
+    Enum,
+    Variant,
+    // Fields of structs and of struct variants
+    Field,
+    Statement,
+}
+
+impl fmt::Display for AttributeTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            AttributeTarget::Function => "function",
+            AttributeTarget::Struct => "struct",
+            AttributeTarget::Enum => "enum",
+            AttributeTarget::Variant => "variant",
+            AttributeTarget::Field => "field",
+            AttributeTarget::Statement => "statement",
+        };
+        f.write_str(name)
+    }
+}
+
+// A node of the program that carries attributes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Annotated<'a> {
+    Function(&'a FunctionDecl),
+    Struct(&'a StructDecl),
+    Enum(&'a EnumDecl),
+    Variant(&'a Variant),
+    Field(&'a Field),
+    Statement(&'a Stmt),
+}
+
+impl<'a> Annotated<'a> {
+    pub fn target(&self) -> AttributeTarget {
+        match self {
+            Annotated::Function(_) => AttributeTarget::Function,
+            Annotated::Struct(_) => AttributeTarget::Struct,
+            Annotated::Enum(_) => AttributeTarget::Enum,
+            Annotated::Variant(_) => AttributeTarget::Variant,
+            Annotated::Field(_) => AttributeTarget::Field,
+            Annotated::Statement(_) => AttributeTarget::Statement,
+        }
+    }
+
+    pub fn attributes(&self) -> &'a [Attribute] {
+        match self {
+            Annotated::Function(function) => &function.attributes,
+            Annotated::Struct(decl) => &decl.attributes,
+            Annotated::Enum(decl) => &decl.attributes,
+            Annotated::Variant(variant) => &variant.attributes,
+            Annotated::Field(field) => &field.attributes,
+            Annotated::Statement(stmt) => &stmt.attributes,
+        }
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<&'a Attribute> {
+        self.attributes()
+            .iter()
+            .find(|attribute| attribute.name == name)
+    }
+
+    pub fn has(&self, name: &str) -> bool {
+        self.attribute(name).is_some()
+    }
+
+    // Every node of `program` that can carry attributes, in source order
+    pub fn collect(program: &'a Program) -> Vec<Annotated<'a>> {
+        let mut nodes = Vec::new();
+        for item in &program.items {
+            match item {
+                Item::Function(function) => function_nodes(function, &mut nodes),
+                Item::Struct(decl) => {
+                    nodes.push(Annotated::Struct(decl));
+                    nodes.extend(decl.fields.iter().map(Annotated::Field));
+                    for method in &decl.methods {
+                        function_nodes(method, &mut nodes);
+                    }
+                }
+                Item::Impl(decl) => {
+                    for method in &decl.methods {
+                        function_nodes(method, &mut nodes);
+                    }
+                }
+                Item::Trait(decl) => {
+                    for method in &decl.methods {
+                        function_nodes(method, &mut nodes);
+                    }
+                }
+                Item::Enum(decl) => {
+                    nodes.push(Annotated::Enum(decl));
+                    for variant in &decl.variants {
+                        nodes.push(Annotated::Variant(variant));
+                        if let VariantKind::Struct(fields) = &variant.kind {
+                            nodes.extend(fields.iter().map(Annotated::Field));
+                        }
+                    }
+                }
+                Item::Statement(stmt) => statement_nodes(stmt, &mut nodes),
+                Item::Use(_) | Item::Error(_) => {}
+            }
+        }
+        nodes
+    }
+}
+
+fn function_nodes<'a>(function: &'a FunctionDecl, nodes: &mut Vec<Annotated<'a>>) {
+    nodes.push(Annotated::Function(function));
+    if let Some(body) = &function.body {
+        block_nodes(body, nodes);
+    }
+}
+
+fn block_nodes<'a>(block: &'a Block, nodes: &mut Vec<Annotated<'a>>) {
+    for stmt in &block.statements {
+        statement_nodes(stmt, nodes);
+    }
+}
+
+fn statement_nodes<'a>(stmt: &'a Stmt, nodes: &mut Vec<Annotated<'a>>) {
+    nodes.push(Annotated::Statement(stmt));
+    match &stmt.kind {
+        StmtKind::Let { value, .. } | StmtKind::Return(value) => {
+            if let Some(value) = value {
+                expr_nodes(value, nodes);
+            }
+        }
+        StmtKind::Expr(expr) | StmtKind::Print(expr) => expr_nodes(expr, nodes),
+        StmtKind::If {
+            condition,
+            then_branch,
+            else_branch,
+        } => {
+            expr_nodes(condition, nodes);
+            block_nodes(then_branch, nodes);
+            if let Some(else_branch) = else_branch {
+                statement_nodes(else_branch, nodes);
+            }
+        }
+        StmtKind::While { condition, body } => {
+            expr_nodes(condition, nodes);
+            block_nodes(body, nodes);
+        }
+        StmtKind::For { iterable, body, .. } => {
+            expr_nodes(iterable, nodes);
+            block_nodes(body, nodes);
+        }
+        StmtKind::Block(block) => block_nodes(block, nodes),
+        StmtKind::Break | StmtKind::Continue | StmtKind::Error => {}
+    }
+}
+
+// Statements inside expressions, such as the blocks of match arms
+fn expr_nodes<'a>(expr: &'a Expr, nodes: &mut Vec<Annotated<'a>>) {
+    match &expr.kind {
+        ExprKind::Block(block) => block_nodes(block, nodes),
+        ExprKind::Match { scrutinee, arms } => {
+            expr_nodes(scrutinee, nodes);
+            for arm in arms {
+                if let Some(guard) = &arm.guard {
+                    expr_nodes(guard, nodes);
+                }
+                expr_nodes(&arm.body, nodes);
+            }
+        }
+        ExprKind::Interpolation(parts) => {
+            for part in parts {
+                if let StringPart::Expr(expr) = part {
+                    expr_nodes(expr, nodes);
+                }
+            }
+        }
+        ExprKind::Unary { operand, .. } | ExprKind::Postfix { operand, .. } => {
+            expr_nodes(operand, nodes)
+        }
+        ExprKind::Binary { left, right, .. } => {
+            expr_nodes(left, nodes);
+            expr_nodes(right, nodes);
+        }
+        ExprKind::Assign { target, value } | ExprKind::CompoundAssign { target, value, .. } => {
+            expr_nodes(target, nodes);
+            expr_nodes(value, nodes);
+        }
+        ExprKind::Ternary {
+            condition,
+            then_branch,
+            else_branch,
+        } => {
+            expr_nodes(condition, nodes);
+            expr_nodes(then_branch, nodes);
+            expr_nodes(else_branch, nodes);
+        }
+        ExprKind::Call { callee, args } => {
+            expr_nodes(callee, nodes);
+            for arg in args {
+                expr_nodes(arg, nodes);
+            }
+        }
+        ExprKind::Index { object, index } => {
+            expr_nodes(object, nodes);
+            expr_nodes(index, nodes);
+        }
+        ExprKind::Member { object, .. } => expr_nodes(object, nodes),
+        ExprKind::StructLiteral { fields, .. } => {
+            for (_, value) in fields {
+                expr_nodes(value, nodes);
+            }
+        }
+        ExprKind::Literal(_) | ExprKind::Identifier(_) | ExprKind::Path(_) => {}
+    }
+}
+
+// What the registry accepts for one attribute name
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttributeSpec {
+    pub name: String,
+    pub targets: Vec<AttributeTarget>,
+    pub min_args: usize,
+    // `None` when any number of arguments is allowed
+    pub max_args: Option<usize>,
+    // Whether the attribute may be written more than once on the same node
+    pub repeatable: bool,
+}
+
+impl AttributeSpec {
+    // An attribute without arguments
+    pub fn new(name: &str, targets: &[AttributeTarget]) -> Self {
+        Self {
+            name: name.to_string(),
+            targets: targets.to_vec(),
+            min_args: 0,
+            max_args: Some(0),
+            repeatable: false,
+        }
+    }
+
+    pub fn args(mut self, min: usize, max: Option<usize>) -> Self {
+        self.min_args = min;
+        self.max_args = max;
+        self
+    }
+
+    pub fn repeatable(mut self) -> Self {
+        self.repeatable = true;
+        self
+    }
+}
+
+// Attributes known to the compiler. Passes register the attributes they act on,
+// `check` validates every use in a program and `find` returns the nodes a
+// pass has to handle.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttributeRegistry {
+    specs: HashMap<String, AttributeSpec>,
+}
+
+impl Default for AttributeRegistry {
+    // The built-in attributes
+    fn default() -> Self {
+        use AttributeTarget::*;
+
+        Self::empty()
+            .register(AttributeSpec::new("inline", &[Function]))
+            .register(AttributeSpec::new("test", &[Function]))
+            .register(
+                AttributeSpec::new("deprecated", &[Function, Struct, Enum, Variant, Field])
+                    .args(0, Some(1)),
+            )
+            .register(
+                AttributeSpec::new("derive", &[Struct, Enum])
+                    .args(1, None)
+                    .repeatable(),
+            )
+    }
+}
+
+impl AttributeRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // A registry without the built-in attributes
+    pub fn empty() -> Self {
+        Self {
+            specs: HashMap::new(),
+        }
+    }
+
+    // Registering a name again replaces its spec
+    pub fn register(mut self, spec: AttributeSpec) -> Self {
+        self.specs.insert(spec.name.clone(), spec);
+        self
+    }
+
+    pub fn spec(&self, name: &str) -> Option<&AttributeSpec> {
+        self.specs.get(name)
+    }
+
+    pub fn is_registered(&self, name: &str) -> bool {
+        self.specs.contains_key(name)
+    }
+
+    pub fn check(&self, program: &Program) -> Vec<AttributeDiagnostic> {
+        let mut diagnostics = Vec::new();
+        for node in Annotated::collect(program) {
+            let attributes = node.attributes();
+            for (index, attribute) in attributes.iter().enumerate() {
+                if let Some(error) =
+                    self.check_attribute(node.target(), attribute, &attributes[..index])
+                {
+                    diagnostics.push(AttributeDiagnostic {
+                        error,
+                        position: attribute.position,
+                    });
+                }
+            }
+        }
+        diagnostics
+    }
+
+    // `earlier` are the attributes written before this one on the same node
+    fn check_attribute(
+        &self,
+        target: AttributeTarget,
+        attribute: &Attribute,
+        earlier: &[Attribute],
+    ) -> Option<AttributeError> {
+        let name = attribute.name.clone();
+        let Some(spec) = self.spec(&attribute.name) else {
+            return Some(AttributeError::UnknownAttribute(name));
+        };
+
+        if !spec.targets.contains(&target) {
+            return Some(AttributeError::InvalidTarget { name, target });
+        }
+
+        let found = attribute.args.len();
+        if found < spec.min_args || spec.max_args.is_some_and(|max| found > max) {
+            return Some(AttributeError::ArgumentCount {
+                name,
+                min: spec.min_args,
+                max: spec.max_args,
+                found,
+            });
+        }
+
+        if !spec.repeatable && earlier.iter().any(|other| other.name == attribute.name) {
+            return Some(AttributeError::Duplicate(name));
+        }
+
+        None
+    }
+
+    // Nodes annotated with `name`, each with the matching attribute
+    pub fn find<'a>(
+        &self,
+        program: &'a Program,
+        name: &str,
+    ) -> Vec<(Annotated<'a>, &'a Attribute)> {
+        Annotated::collect(program)
+            .into_iter()
+            .flat_map(|node| {
+                node.attributes()
+                    .iter()
+                    .filter(move |attribute| attribute.name == name)
+                    .map(move |attribute| (node, attribute))
+            })
+            .collect()
+    }
+}
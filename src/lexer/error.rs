@@ -0,0 +1,168 @@
+use std::fmt;
+
+use crate::lexer::token::{NumberBase, Token};
+use crate::source::span::{FileId, Span};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenError {
+    UnexpectedToken(Token),
+    UnexpectedCharacter(char),
+    // A run of several characters that cannot start a token
+    UnexpectedCharacters(String),
+    UnexpectedEndOfFile,
+    UnterminatedString(char),
+    UnterminatedComment,
+    InvalidNumber(String),
+    InvalidDigit { digit: char, base: NumberBase },
+    InvalidSuffix(String),
+    IntegerOverflow(String),
+    UnterminatedChar,
+    EmptyCharLiteral,
+    MultiCharLiteral(String),
+    InvalidEscape(String),
+    InvalidRawDelimiter(String),
+    // Lints, reported as warnings
+    MixedScriptIdentifier(String),
+    ConfusableIdentifier { name: String, similar_to: String },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl TokenError {
+    // Stable identifier that tools can match on instead of the message text
+    pub fn code(&self) -> &'static str {
+        match self {
+            TokenError::UnexpectedToken(_) => "L0001",
+            TokenError::UnexpectedCharacter(_) => "L0002",
+            TokenError::UnexpectedEndOfFile => "L0003",
+            TokenError::UnterminatedString(_) => "L0004",
+            TokenError::UnterminatedComment => "L0005",
+            TokenError::InvalidNumber(_) => "L0006",
+            TokenError::InvalidDigit { .. } => "L0007",
+            TokenError::InvalidSuffix(_) => "L0008",
+            TokenError::IntegerOverflow(_) => "L0009",
+            TokenError::MixedScriptIdentifier(_) => "L0010",
+            TokenError::ConfusableIdentifier { .. } => "L0011",
+            TokenError::UnterminatedChar => "L0012",
+            TokenError::EmptyCharLiteral => "L0013",
+            TokenError::MultiCharLiteral(_) => "L0014",
+            TokenError::InvalidEscape(_) => "L0015",
+            TokenError::InvalidRawDelimiter(_) => "L0016",
+            TokenError::UnexpectedCharacters(_) => "L0017",
+        }
+    }
+
+    pub fn severity(&self) -> Severity {
+        match self {
+            TokenError::MixedScriptIdentifier(_) | TokenError::ConfusableIdentifier { .. } => {
+                Severity::Warning
+            }
+            _ => Severity::Error,
+        }
+    }
+}
+
+impl fmt::Display for TokenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenError::UnexpectedToken(token) => write!(f, "Unexpected token {:?}", token),
+            TokenError::UnexpectedCharacter(c) => write!(f, "Unexpected character {:?}", c),
+            TokenError::UnexpectedCharacters(text) => {
+                write!(f, "Unexpected characters {:?}", text)
+            }
+            TokenError::UnexpectedEndOfFile => write!(f, "Unexpected end of file"),
+            TokenError::UnterminatedString(quote) => {
+                write!(f, "Unterminated string starting with {}", quote)
+            }
+            TokenError::UnterminatedComment => write!(f, "Unterminated block comment"),
+            TokenError::InvalidNumber(text) => write!(f, "Invalid number: {}", text),
+            TokenError::InvalidDigit { digit, base } => {
+                write!(
+                    f,
+                    "Invalid digit {:?} in base {} literal",
+                    digit,
+                    base.radix()
+                )
+            }
+            TokenError::InvalidSuffix(suffix) => write!(f, "Invalid numeric suffix: {}", suffix),
+            TokenError::IntegerOverflow(text) => {
+                write!(f, "Integer literal is too large: {}", text)
+            }
+            TokenError::UnterminatedChar => write!(f, "Unterminated character literal"),
+            TokenError::EmptyCharLiteral => write!(f, "Empty character literal"),
+            TokenError::MultiCharLiteral(text) => {
+                write!(
+                    f,
+                    "Character literal must contain exactly one character: {}",
+                    text
+                )
+            }
+            TokenError::InvalidEscape(escape) => write!(f, "Invalid escape sequence: {}", escape),
+            TokenError::InvalidRawDelimiter(delimiter) => {
+                write!(f, "Invalid raw string delimiter: {:?}", delimiter)
+            }
+            TokenError::MixedScriptIdentifier(name) => {
+                write!(f, "Identifier mixes scripts: {}", name)
+            }
+            TokenError::ConfusableIdentifier { name, similar_to } => {
+                write!(f, "Identifier {} is confusable with {}", name, similar_to)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+    // UTF-8 byte offset into the input
+    pub offset: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LexDiagnostic {
+    pub error: TokenError,
+    pub file_id: FileId,
+    pub start: Location,
+    pub end: Location,
+    // Where the lexer resumed scanning after reporting the error
+    pub recovery: Location,
+}
+
+impl LexDiagnostic {
+    pub fn code(&self) -> &'static str {
+        self.error.code()
+    }
+
+    pub fn severity(&self) -> Severity {
+        self.error.severity()
+    }
+
+    pub fn span(&self) -> Span {
+        Span::new(self.file_id, self.start.offset, self.end.offset)
+    }
+}
+
+impl fmt::Display for LexDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self.severity() {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(
+            f,
+            "{}[{}] {}:{}: {}",
+            label,
+            self.code(),
+            self.start.line,
+            self.start.column,
+            self.error
+        )
+    }
+}
+
+impl std::error::Error for LexDiagnostic {}
@@ -0,0 +1,262 @@
+use std::ops::Range;
+
+use crate::lexer::error::{LexDiagnostic, Location, TokenError};
+use crate::lexer::lexer::{LexState, Lexer};
+use crate::lexer::token::{Token, TokenInfo};
+use crate::source::span::Span;
+
+// Replaces the bytes in `range` with `replacement`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit {
+    pub range: Range<usize>,
+    pub replacement: String,
+}
+
+impl TextEdit {
+    pub fn new(range: Range<usize>, replacement: &str) -> Self {
+        Self {
+            range,
+            replacement: replacement.to_string(),
+        }
+    }
+
+    pub fn insert(offset: usize, text: &str) -> Self {
+        Self::new(offset..offset, text)
+    }
+
+    pub fn delete(range: Range<usize>) -> Self {
+        Self::new(range, "")
+    }
+
+    pub fn apply(&self, text: &str) -> String {
+        let mut edited = text.to_string();
+        edited.replace_range(self.range.clone(), &self.replacement);
+        edited
+    }
+
+    // End of the replacement in the edited text
+    fn replacement_end(&self) -> usize {
+        self.range.start + self.replacement.len()
+    }
+}
+
+// Result of a lexer run that `Lexer::relex` can update after an edit
+#[derive(Debug, Clone)]
+pub struct Lexed<'src> {
+    pub tokens: Vec<TokenInfo<'src>>,
+    pub diagnostics: Vec<LexDiagnostic>,
+    // State the lexer was in before the trivia leading up to each token
+    pub states: Vec<LexState>,
+    // Tokens that were lexed again instead of being carried over
+    pub relexed: Range<usize>,
+}
+
+// Moves locations at or after `from` by the same amount as `from` moves to `to`
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Shift {
+    from: Location,
+    to: Location,
+}
+
+impl Shift {
+    pub(crate) fn location(&self, location: Location) -> Location {
+        if location.offset < self.from.offset {
+            return location;
+        }
+        // Only the rest of the line `from` is on changes columns
+        let column = if location.line == self.from.line {
+            location.column - self.from.column + self.to.column
+        } else {
+            location.column
+        };
+
+        Location {
+            line: location.line - self.from.line + self.to.line,
+            column,
+            offset: location.offset - self.from.offset + self.to.offset,
+        }
+    }
+
+    fn token<'src>(&self, info: &TokenInfo, text: &'src str) -> TokenInfo<'src> {
+        let start = self.location(Location {
+            line: info.line,
+            column: info.column,
+            offset: info.span.start_byte,
+        });
+        let end = start.offset + info.span.len();
+        let span = Span::new(info.span.file_id, start.offset, end);
+
+        TokenInfo::new(
+            info.token.clone(),
+            &text[start.offset..end],
+            start.line,
+            start.column,
+            span,
+        )
+    }
+
+    fn diagnostic(&self, diagnostic: &LexDiagnostic) -> LexDiagnostic {
+        LexDiagnostic {
+            start: self.location(diagnostic.start),
+            end: self.location(diagnostic.end),
+            recovery: self.location(diagnostic.recovery),
+            ..diagnostic.clone()
+        }
+    }
+}
+
+impl<'src> Lexer<'src> {
+    // Lexes the whole input, keeping the states `relex` restarts from
+    pub fn lex(mut self) -> Lexed<'src> {
+        let mut tokens = Vec::new();
+        let mut states = Vec::new();
+        while let Some((state, token)) = self.next_with_state() {
+            states.push(state);
+            tokens.push(token);
+        }
+
+        Lexed {
+            relexed: 0..tokens.len(),
+            tokens,
+            diagnostics: self.diagnostics().to_vec(),
+            states,
+        }
+    }
+
+    // Lexes the edited text given the result for the text before `edit`. Lexing
+    // restarts at the line the edit begins on and stops as soon as a token
+    // starts where an old one did, in the same state; the old tokens from there
+    // on are moved by the edit. `self` must be configured like the lexer that
+    // produced `previous`.
+    pub fn relex(self, previous: &Lexed, edit: &TextEdit) -> Lexed<'src> {
+        let text = self.input();
+        let old = &previous.tokens;
+        let old_states = &previous.states;
+
+        // Everything before the edit is unchanged, including its line breaks
+        let line_start = text[..edit.range.start]
+            .rfind('\n')
+            .map_or(0, |index| index + 1);
+        let mut restart = restart_index(old_states, line_start);
+
+        // Unterminated strings end with their opener's line, but raw and
+        // multi-line ones searched the rest of the input for a terminator, which
+        // the edit may have added
+        while let Some(index) = previous
+            .diagnostics
+            .iter()
+            .filter(|d| matches!(d.error, TokenError::UnterminatedString(_)))
+            .filter(|d| {
+                old.iter()
+                    .find(|info| info.span.start_byte == d.start.offset)
+                    .is_some_and(|info| searches_past_line(info.lexeme))
+            })
+            .filter(|d| d.start.offset < old_states[restart].location().offset)
+            .map(|d| restart_index(old_states, d.start.offset))
+            .min()
+            .filter(|&index| index < restart)
+        {
+            restart = index;
+        }
+
+        let restart_offset = old_states[restart].location().offset;
+        let mut lexer = self.resume(&old_states[restart]);
+        for info in &old[..restart] {
+            if let Token::Identifier(name) = &info.token {
+                lexer.remember_identifier(name.as_str());
+            }
+        }
+
+        // Tokens before the restart point are unchanged and stay where they were
+        let unchanged = Shift {
+            from: old_states[restart].location(),
+            to: old_states[restart].location(),
+        };
+        let mut tokens: Vec<_> = old[..restart]
+            .iter()
+            .map(|info| unchanged.token(info, text))
+            .collect();
+        let mut states = old_states[..restart].to_vec();
+
+        let mut candidate = restart;
+        let mut sync = None;
+        while let Some((state, token)) = lexer.next_with_state() {
+            let offset = state.location().offset;
+            if offset >= edit.replacement_end() {
+                let old_offset = offset - edit.replacement_end() + edit.range.end;
+                while candidate < old.len() && old_states[candidate].location().offset < old_offset
+                {
+                    candidate += 1;
+                }
+
+                if let Some(old_state) = old_states.get(candidate)
+                    && old_state.location().offset == old_offset
+                {
+                    let shift = Shift {
+                        from: old_state.location(),
+                        to: state.location(),
+                    };
+                    if old_state.shifted(&shift) == state {
+                        sync = Some((candidate, shift));
+                        break;
+                    }
+                }
+            }
+
+            states.push(state);
+            tokens.push(token);
+        }
+
+        let relexed = restart..tokens.len();
+        let mut diagnostics: Vec<_> = previous
+            .diagnostics
+            .iter()
+            .filter(|d| d.recovery.offset <= restart_offset)
+            .cloned()
+            .collect();
+
+        match sync {
+            Some((candidate, shift)) => {
+                let sync_offset = shift.to.offset;
+                diagnostics.extend(
+                    lexer
+                        .diagnostics()
+                        .iter()
+                        .filter(|d| d.recovery.offset <= sync_offset)
+                        .cloned(),
+                );
+                diagnostics.extend(
+                    previous
+                        .diagnostics
+                        .iter()
+                        .filter(|d| d.recovery.offset > shift.from.offset)
+                        .map(|d| shift.diagnostic(d)),
+                );
+
+                tokens.extend(old[candidate..].iter().map(|info| shift.token(info, text)));
+                states.extend(old_states[candidate..].iter().map(|s| s.shifted(&shift)));
+            }
+            None => diagnostics.extend(lexer.diagnostics().iter().cloned()),
+        }
+
+        Lexed {
+            tokens,
+            diagnostics,
+            states,
+            relexed,
+        }
+    }
+}
+
+// Raw and multi-line strings, the literals whose terminator may be on a later line
+fn searches_past_line(lexeme: &str) -> bool {
+    lexeme.starts_with("\"\"\"") || lexeme.starts_with(['r', 'R'])
+}
+
+// Last token whose leading trivia starts at or before `offset`
+fn restart_index(states: &[LexState], offset: usize) -> usize {
+    states
+        .iter()
+        .rposition(|state| state.location().offset <= offset)
+        .unwrap_or(0)
+}
@@ -0,0 +1,127 @@
+use phf::phf_map;
+
+use crate::lexer::token::Reserved;
+
+// Every reserved word, keyed by its lowercase spelling. This is the only place
+// that maps source text to `Reserved`.
+static KEYWORDS: phf::Map<&'static str, Reserved> = phf_map! {
+    "null" => Reserved::Null,
+    "void" => Reserved::Void,
+    "let" => Reserved::Let,
+    "fn" => Reserved::Fn,
+    "if" => Reserved::If,
+    "else" => Reserved::Else,
+    "while" => Reserved::While,
+    "for" => Reserved::For,
+    "in" => Reserved::In,
+    "match" => Reserved::Match,
+    "continue" => Reserved::Continue,
+    "break" => Reserved::Break,
+    "return" => Reserved::Return,
+    "public" => Reserved::Public,
+    "private" => Reserved::Private,
+    "static" => Reserved::Static,
+    "print" => Reserved::Print,
+    "true" => Reserved::True,
+    "false" => Reserved::False,
+    "define" => Reserved::Define,
+    "macro" => Reserved::Macro,
+    "struct" => Reserved::Struct,
+    "enum" => Reserved::Enum,
+    "union" => Reserved::Union,
+    "type" => Reserved::Type,
+    "trait" => Reserved::Trait,
+    "impl" => Reserved::Impl,
+    "module" => Reserved::Module,
+    "use" => Reserved::Use,
+    "import" => Reserved::Import,
+    "export" => Reserved::Export,
+    "alias" => Reserved::TypeAlias,
+    "def" => Reserved::TypeDef,
+};
+
+// Capitalized spellings that have always been keywords too. Other keywords
+// are only reserved in lowercase, leaving `Let` or `Module` free as names.
+const CAPITALIZED: &[&str] = &[
+    "Null", "Void", "Return", "Print", "True", "False", "Type", "Alias", "Def", "Struct", "Enum",
+    "Impl", "Trait", "Use", "Union", "Define",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum KeywordMode {
+    // The spellings in `CAPITALIZED` are keywords as well (`Return`, `Print`)
+    #[default]
+    Relaxed,
+    // Only the exact lowercase spelling is a keyword
+    Strict,
+}
+
+// Keyword configuration shared by the lexer and the parser
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keywords {
+    mode: KeywordMode,
+    // Lexed as identifiers and only treated as keywords where the parser expects them
+    contextual: Vec<Reserved>,
+}
+
+impl Default for Keywords {
+    fn default() -> Self {
+        Self {
+            mode: KeywordMode::default(),
+            contextual: vec![Reserved::In, Reserved::Match],
+        }
+    }
+}
+
+impl Keywords {
+    pub fn new(mode: KeywordMode) -> Self {
+        Self {
+            mode,
+            ..Self::default()
+        }
+    }
+
+    pub fn strict() -> Self {
+        Self::new(KeywordMode::Strict)
+    }
+
+    pub fn mode(&self) -> KeywordMode {
+        self.mode
+    }
+
+    // Registers `keyword` as contextual, freeing its spelling for use as a name
+    pub fn contextual(mut self, keyword: Reserved) -> Self {
+        if !self.contextual.contains(&keyword) {
+            self.contextual.push(keyword);
+        }
+        self
+    }
+
+    pub fn is_contextual(&self, keyword: Reserved) -> bool {
+        self.contextual.contains(&keyword)
+    }
+
+    // Keyword spelled by `text` under the current mode, contextual or not
+    pub fn lookup(&self, text: &str) -> Option<Reserved> {
+        if let Some(&keyword) = KEYWORDS.get(text) {
+            return Some(keyword);
+        }
+
+        if self.mode == KeywordMode::Relaxed && CAPITALIZED.contains(&text) {
+            return KEYWORDS.get(text.to_ascii_lowercase().as_str()).copied();
+        }
+        None
+    }
+
+    // Keyword the lexer emits for `text`; contextual keywords stay identifiers
+    pub fn reserved(&self, text: &str) -> Option<Reserved> {
+        self.lookup(text)
+            .filter(|&keyword| !self.is_contextual(keyword))
+    }
+
+    // Contextual keyword spelled by an identifier, for the parser
+    pub fn contextual_keyword(&self, name: &str) -> Option<Reserved> {
+        self.lookup(name)
+            .filter(|&keyword| self.is_contextual(keyword))
+    }
+}
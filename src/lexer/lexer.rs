@@ -1,51 +1,298 @@
-use crate::lexer::token::{Operation, Punctuation, Reserved, Token, TokenInfo};
+use std::collections::HashMap;
 
-pub struct Lexer {
-    input: Vec<char>,
+use unicode_normalization::UnicodeNormalization;
+use unicode_security::{MixedScript, skeleton};
+use unicode_xid::UnicodeXID;
+
+use crate::lexer::error::{LexDiagnostic, Location, TokenError};
+use crate::lexer::incremental::Shift;
+use crate::lexer::keywords::Keywords;
+use crate::lexer::symbol::Symbol;
+use crate::lexer::token::{
+    CommentKind, NumberBase, NumericSuffix, OPERATORS, Operation, Punctuation, Reserved,
+    StringKind, Token, TokenInfo,
+};
+use crate::source::span::{FileId, Span};
+
+// Byte length of a multi-line string body up to its closing quotes, if they exist
+fn multi_line_body_length(body: &str) -> Option<usize> {
+    let mut chars = body.char_indices();
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '"' if body[index..].starts_with("\"\"\"") => return Some(index),
+            _ => {}
+        }
+    }
+    None
+}
+
+// Characters no token can start with; a run of them is reported once
+fn is_invalid_start(c: char) -> bool {
+    !(matches!(c, ' ' | '\r' | '\t' | '\n')
+        || c == '_'
+        || c.is_ascii_digit()
+        || c.is_xid_start()
+        || "()[]{},;.:?#@$\"'".contains(c)
+        || OPERATORS.iter().any(|(text, _)| text.starts_with(c)))
+}
+
+// What the lexer is scanning; the innermost mode is last on the stack
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum LexMode {
+    // Literal text of an interpolated string that opened at `start`
+    String { start: Location },
+    // Expression inside `${...}`, with the number of unclosed `{` it contains
+    Interpolation { depth: usize },
+}
+
+// Everything the lexer needs besides the input to resume at a token boundary,
+// including the open interpolated strings around it
+#[derive(Debug, Clone, PartialEq)]
+pub struct LexState {
+    location: Location,
+    modes: Vec<LexMode>,
+    dot_binds_left: bool,
+}
+
+impl LexState {
+    pub fn location(&self) -> Location {
+        self.location
+    }
+
+    pub(crate) fn shifted(&self, shift: &Shift) -> LexState {
+        let modes = self
+            .modes
+            .iter()
+            .map(|mode| match mode {
+                LexMode::String { start } => LexMode::String {
+                    start: shift.location(*start),
+                },
+                interpolation => *interpolation,
+            })
+            .collect();
+
+        LexState {
+            location: shift.location(self.location),
+            modes,
+            dot_binds_left: self.dot_binds_left,
+        }
+    }
+}
+
+// Outcome of reading one escape sequence
+enum Escaped {
+    Char(char),
+    Continuation,
+    Invalid,
+}
+
+// Scans the bytes of the borrowed source; token lexemes are slices of it
+pub struct Lexer<'src> {
+    input: &'src str,
+    // Byte offset of the next character
     current: usize,
+    file_id: FileId,
     line: usize,
     column: usize,
+    keep_comments: bool,
+    keep_whitespace: bool,
+    lint_identifiers: bool,
+    keywords: Keywords,
+    emitted_eof: bool,
+    // Confusable skeleton of each identifier seen so far, mapped to its first spelling
+    skeletons: HashMap<String, String>,
+    // Set right after tokens that an adjacent `.` continues (member access or `..`),
+    // in which case `.5` is not read as a float
+    dot_binds_left: bool,
+    // Empty outside interpolated strings
+    modes: Vec<LexMode>,
+    // Offset from which each string terminator is known not to occur, so a file
+    // full of unterminated literals is not searched to its end for every one
+    missing_terminators: HashMap<String, usize>,
+    token_start: Location,
+    diagnostics: Vec<LexDiagnostic>,
 }
 
-impl Lexer {
-    pub fn new(input: &str) -> Self {
+impl<'src> Lexer<'src> {
+    pub fn new(input: &'src str) -> Self {
         Self {
-            input: input.chars().collect(),
+            input,
             current: 0,
+            file_id: FileId::default(),
             line: 1,
             column: 1,
+            keep_comments: false,
+            keep_whitespace: false,
+            lint_identifiers: false,
+            keywords: Keywords::default(),
+            emitted_eof: false,
+            skeletons: HashMap::new(),
+            dot_binds_left: false,
+            modes: Vec::new(),
+            missing_terminators: HashMap::new(),
+            token_start: Location {
+                line: 1,
+                column: 1,
+                offset: 0,
+            },
+            diagnostics: Vec::new(),
         }
     }
 
-    pub fn tokenize(&mut self) -> Vec<TokenInfo> {
-        let mut tokens = Vec::new();
+    // Keep `Token::Comment` trivia in the output of `tokenize` instead of dropping it
+    pub fn keep_comments(mut self, keep: bool) -> Self {
+        self.keep_comments = keep;
+        self
+    }
 
+    // Keep `Token::Whitespace` in the output, so the tokens cover every byte of
+    // the input
+    pub fn keep_whitespace(mut self, keep: bool) -> Self {
+        self.keep_whitespace = keep;
+        self
+    }
+
+    // Warn about mixed-script identifiers and identifiers that look like another one
+    pub fn lint_identifiers(mut self, lint: bool) -> Self {
+        self.lint_identifiers = lint;
+        self
+    }
+
+    // Keyword spellings and contextual keywords to recognise
+    pub fn keywords(mut self, keywords: Keywords) -> Self {
+        self.keywords = keywords;
+        self
+    }
+
+    // File the spans of produced tokens refer to
+    pub fn in_file(mut self, file_id: FileId) -> Self {
+        self.file_id = file_id;
+        self
+    }
+
+    // Continue lexing from a state captured on the same text, or on text that
+    // only differs after it
+    pub fn resume(mut self, state: &LexState) -> Self {
+        self.current = state.location.offset;
+        self.line = state.location.line;
+        self.column = state.location.column;
+        self.modes = state.modes.clone();
+        self.dot_binds_left = state.dot_binds_left;
+        self.emitted_eof = false;
+        self
+    }
+
+    pub(crate) fn input(&self) -> &'src str {
+        self.input
+    }
+
+    pub fn state(&self) -> LexState {
+        LexState {
+            location: self.location(),
+            modes: self.modes.clone(),
+            dot_binds_left: self.dot_binds_left,
+        }
+    }
+
+    pub fn tokenize(&mut self) -> Vec<TokenInfo<'src>> {
+        self.by_ref().collect()
+    }
+
+    // Like `tokenize`, but also returns every lexical error found along the way.
+    // Erroneous input still produces `Token::Invalid` so the stream stays complete.
+    pub fn tokenize_with_diagnostics(&mut self) -> (Vec<TokenInfo<'src>>, Vec<LexDiagnostic>) {
+        let tokens = self.tokenize();
+        (tokens, std::mem::take(&mut self.diagnostics))
+    }
+
+    pub fn diagnostics(&self) -> &[LexDiagnostic] {
+        &self.diagnostics
+    }
+
+    // Next token together with the state before the trivia that precedes it.
+    // Resuming from that state lexes the trivia and then the same token.
+    pub(crate) fn next_with_state(&mut self) -> Option<(LexState, TokenInfo<'src>)> {
+        let state = self.state();
         while !self.is_at_end() {
             let token_info = self.next_token();
             // Skip whitespace tokens for cleaner output
-            if !matches!(token_info.token, Token::Whitespace) {
-                tokens.push(token_info);
+            match token_info.token {
+                Token::Whitespace if !self.keep_whitespace => {}
+                Token::Comment { .. } if !self.keep_comments => {}
+                _ => return Some((state, token_info)),
             }
         }
 
-        tokens.push(TokenInfo::new(
+        if self.emitted_eof {
+            return None;
+        }
+        self.emitted_eof = true;
+
+        // Interpolated strings still open when the input ran out
+        self.close_strings();
+
+        let eof = TokenInfo::new(
             Token::Eof,
-            String::new(),
+            "",
             self.line,
             self.column,
-        ));
-
-        tokens
+            Span::new(self.file_id, self.current, self.current),
+        );
+        Some((state, eof))
     }
 
-    fn next_token(&mut self) -> TokenInfo {
+    fn next_token(&mut self) -> TokenInfo<'src> {
+        self.token_start = self.location();
         let start_line = self.line;
         let start_column = self.column;
         let start_pos = self.current;
 
+        let token = match self.modes.last() {
+            Some(LexMode::String { .. }) => self.string_segment(),
+            _ => self.scan_token(),
+        };
+
+        self.dot_binds_left = matches!(
+            token,
+            Token::Identifier(_)
+                | Token::Integer { .. }
+                | Token::Float { .. }
+                | Token::String { .. }
+                | Token::StringEnd
+                | Token::Char(_)
+                | Token::Punctuation(
+                    Punctuation::CloseParen | Punctuation::CloseBracket | Punctuation::Dot
+                )
+        );
+
+        let lexeme = &self.input[start_pos..self.current];
+        let span = Span::new(self.file_id, start_pos, self.current);
+
+        TokenInfo::new(token, lexeme, start_line, start_column, span)
+    }
+
+    fn scan_token(&mut self) -> Token {
+        // Comments share their first character with `/` and `/=`
+        if self.input[self.current..].starts_with("//") {
+            self.advance();
+            self.advance();
+            return self.line_comment();
+        }
+        if self.input[self.current..].starts_with("/*") {
+            self.advance();
+            self.advance();
+            return self.block_comment();
+        }
+        if let Some(op) = self.operator() {
+            return Token::Operation(op);
+        }
+
         let ch = self.advance();
 
-        let token = match ch {
+        match ch {
             // Whitespace
             ' ' | '\r' | '\t' => {
                 while self.peek().is_ascii_whitespace() && self.peek() != '\n' {
@@ -54,212 +301,826 @@ impl Lexer {
                 Token::Whitespace
             }
             '\n' => {
-                self.line += 1;
-                self.column = 1;
+                // Strings cannot span lines, so this also ends an interpolation
+                self.close_strings();
                 Token::Newline
             }
 
-            // Multi-character operators (must come before single-char)
-            '=' => {
-                if self.peek() == '=' {
-                    self.advance();
-                    Token::Operation(Operation::IfEqual)
-                } else {
-                    Token::Operation(Operation::Assign)
-                }
-            }
-            '!' => {
-                if self.peek() == '=' {
-                    self.advance();
-                    Token::Operation(Operation::NotEqual)
-                } else {
-                    Token::Operation(Operation::Not)
-                }
-            }
-            '>' => {
-                if self.peek() == '=' {
-                    self.advance();
-                    Token::Operation(Operation::GreaterEqual)
-                } else {
-                    Token::Operation(Operation::Greater)
-                }
-            }
-            '<' => {
-                if self.peek() == '=' {
-                    self.advance();
-                    Token::Operation(Operation::LessEqual)
-                } else {
-                    Token::Operation(Operation::Less)
+            // Punctuation
+            '(' => Token::Punctuation(Punctuation::OpenParen),
+            ')' => Token::Punctuation(Punctuation::CloseParen),
+            '{' => {
+                if let Some(LexMode::Interpolation { depth }) = self.modes.last_mut() {
+                    *depth += 1;
                 }
+                Token::Punctuation(Punctuation::OpenBrace)
             }
-
-            // Single character operators
-            '+' => Token::Operation(Operation::Add),
-            '-' => Token::Operation(Operation::Subtract),
-            '*' => Token::Operation(Operation::Multiply),
-            '%' => {
-                if self.peek() == '%' {
-                    self.advance();
-                    Token::Operation(Operation::Remainder)
-                } else {
-                    Token::Operation(Operation::Modulo)
+            '}' => match self.modes.last_mut() {
+                // Closes the `${` and resumes the enclosing string
+                Some(LexMode::Interpolation { depth: 0 }) => {
+                    self.modes.pop();
+                    Token::InterpolationEnd
                 }
-            }
-
-            // Handle division and comments
-            '/' => {
-                if self.peek() == '/' {
-                    self.advance();
-                    Token::Punctuation(Punctuation::Comment)
-                } else if (self.peek() == '*') {
-                    self.advance();
-                    Token::Punctuation(Punctuation::CommentBlkStr)
-                } else {
-                    Token::Operation(Operation::Divide)
+                Some(LexMode::Interpolation { depth }) => {
+                    *depth -= 1;
+                    Token::Punctuation(Punctuation::CloseBrace)
                 }
-            }
-
-            // Punctuation
-            '(' => Token::Punctuation(Punctuation::OpenParen),
-            ')' => Token::Punctuation(Punctuation::CloseParen),
-            '{' => Token::Punctuation(Punctuation::OpenBrace),
-            '}' => Token::Punctuation(Punctuation::CloseBrace),
+                _ => Token::Punctuation(Punctuation::CloseBrace),
+            },
             '[' => Token::Punctuation(Punctuation::OpenBracket),
             ']' => Token::Punctuation(Punctuation::CloseBracket),
             ',' => Token::Punctuation(Punctuation::Comma),
             ';' => Token::Punctuation(Punctuation::Semicolon),
+            '.' if self.peek().is_ascii_digit() && !self.dot_binds_left => self.number(ch),
             '.' => Token::Punctuation(Punctuation::Dot),
             ':' => Token::Punctuation(Punctuation::Colon),
             '?' => Token::Punctuation(Punctuation::QuestionMark),
             '#' => Token::Punctuation(Punctuation::Hashtag),
             '@' => Token::Punctuation(Punctuation::At),
+            '$' => Token::Punctuation(Punctuation::Dollar),
 
-            // String literals
-            '"' | '\'' => self.string(ch),
+            // String and character literals
+            '"' if self.peek() == '"' && self.peek_ahead(1) == '"' => self.multi_line_string(),
+            '"' if self.interpolation_ahead() => {
+                self.modes.push(LexMode::String {
+                    start: self.token_start,
+                });
+                Token::StringStart
+            }
+            '"' => self.string(ch),
+            'r' if self.raw_string_ahead() => self.raw_string(),
+            'R' if self.peek() == '"' => self.raw_delimited_string(),
+            '\'' => self.char_literal(),
 
             // Numbers
-            c if c.is_ascii_digit() => self.number(),
+            c if c.is_ascii_digit() => self.number(c),
 
             // Identifiers and keywords
-            c if c.is_ascii_alphabetic() || c == '_' => self.identifier(),
+            c if c == '_' || c.is_xid_start() => self.identifier(),
 
-            // Invalid character
-            c => Token::Invalid(c.to_string()),
-        };
+            // Invalid characters
+            _ if is_invalid_start(self.peek()) && !self.is_at_end() => {
+                while is_invalid_start(self.peek()) && !self.is_at_end() {
+                    self.advance();
+                }
+                let text = &self.input[self.token_start.offset..self.current];
+                self.error(TokenError::UnexpectedCharacters(text.to_string()))
+            }
+            c => self.error(TokenError::UnexpectedCharacter(c)),
+        }
+    }
+
+    // Consumes the longest operator at the current position, if any
+    fn operator(&mut self) -> Option<Operation> {
+        let rest = &self.input[self.current..];
+        let &(text, op) = OPERATORS
+            .iter()
+            .filter(|(text, _)| rest.starts_with(text))
+            .max_by_key(|(text, _)| text.len())?;
+
+        // Operators are ASCII, so bytes and characters agree
+        for _ in 0..text.len() {
+            self.advance();
+        }
+        Some(op)
+    }
+
+    fn line_comment(&mut self) -> Token {
+        let start = self.current;
+
+        while self.peek() != '\n' && !self.is_at_end() {
+            self.advance();
+        }
+
+        Token::Comment {
+            kind: CommentKind::Line,
+            text: self.input[start..self.current].to_string(),
+        }
+    }
+
+    fn block_comment(&mut self) -> Token {
+        let start = self.current;
+        // Block comments nest, so `/* a /* b */ c */` is a single comment
+        let mut depth = 1;
 
-        let lexeme = self.input[start_pos..self.current].iter().collect();
+        while !self.is_at_end() {
+            if self.peek() == '/' && self.peek_ahead(1) == '*' {
+                self.advance();
+                self.advance();
+                depth += 1;
+            } else if self.peek() == '*' && self.peek_ahead(1) == '/' {
+                self.advance();
+                self.advance();
+                depth -= 1;
+                if depth == 0 {
+                    return Token::Comment {
+                        kind: CommentKind::Block,
+                        text: self.input[start..self.current - 2].to_string(),
+                    };
+                }
+            } else {
+                self.advance();
+            }
+        }
 
-        TokenInfo::new(token, lexeme, start_line, start_column)
+        self.error(TokenError::UnterminatedComment)
     }
 
     fn string(&mut self, quote_char: char) -> Token {
         let mut value = String::new();
+        let mut valid = true;
 
-        while self.peek() != quote_char && !self.is_at_end() {
-            if self.peek() == '\n' {
-                self.line += 1;
-                self.column = 1;
+        while self.peek() != quote_char && self.peek() != '\n' && !self.is_at_end() {
+            match self.advance() {
+                '\\' => match self.escape(true) {
+                    Escaped::Char(c) => value.push(c),
+                    Escaped::Continuation => {}
+                    Escaped::Invalid => valid = false,
+                },
+                c => value.push(c),
             }
-            // Basic escape sequence handling
-            if self.peek() == '\\' {
-                self.advance(); // consume backslash
-                match self.peek() {
-                    'n' => value.push('\n'),
-                    't' => value.push('\t'),
-                    'r' => value.push('\r'),
-                    '\\' => value.push('\\'),
-                    '"' => value.push('"'),
-                    '\'' => value.push('\''),
-                    c => {
-                        value.push('\\');
-                        value.push(c);
-                    }
+        }
+
+        if self.peek() != quote_char {
+            return self.error(TokenError::UnterminatedString(quote_char));
+        }
+
+        // Consume closing quote
+        self.advance();
+
+        if !valid {
+            return self.invalid_literal();
+        }
+        Token::String {
+            value,
+            kind: StringKind::Plain,
+        }
+    }
+
+    // True when the string whose opening quote was just consumed contains `${`
+    fn interpolation_ahead(&self) -> bool {
+        let mut chars = self.input[self.current..].chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => {
+                    chars.next();
                 }
-                self.advance();
-            } else {
-                value.push(self.advance());
+                '"' | '\n' => return false,
+                '$' if chars.clone().next() == Some('{') => return true,
+                _ => {}
             }
         }
+        false
+    }
 
-        if self.is_at_end() {
-            return Token::Invalid(format!("Unterminated string starting with {}", quote_char));
+    // Next piece of an interpolated string: a run of literal text, the `${` that
+    // starts an expression, or the closing quote
+    fn string_segment(&mut self) -> Token {
+        if self.peek() == '\n' {
+            self.close_strings();
+            return self.scan_token();
+        }
+        if self.peek() == '"' {
+            self.advance();
+            self.modes.pop();
+            return Token::StringEnd;
+        }
+        if self.at_interpolation() {
+            self.advance();
+            self.advance();
+            self.modes.push(LexMode::Interpolation { depth: 0 });
+            return Token::InterpolationStart;
         }
 
-        // Consume closing quote
+        let mut value = String::new();
+        let mut valid = true;
+        while !matches!(self.peek(), '"' | '\n') && !self.at_interpolation() && !self.is_at_end() {
+            match self.advance() {
+                '\\' => match self.escape(true) {
+                    Escaped::Char(c) => value.push(c),
+                    Escaped::Continuation => {}
+                    Escaped::Invalid => valid = false,
+                },
+                c => value.push(c),
+            }
+        }
+
+        if !valid {
+            return self.invalid_literal();
+        }
+        Token::StringFragment(value)
+    }
+
+    fn at_interpolation(&self) -> bool {
+        self.peek() == '$' && self.peek_ahead(1) == '{'
+    }
+
+    // True when the `r` just consumed opens `r"` or `r#...#"`
+    fn raw_string_ahead(&self) -> bool {
+        let mut hashes = 0;
+        while self.peek_ahead(hashes) == '#' {
+            hashes += 1;
+        }
+        self.peek_ahead(hashes) == '"'
+    }
+
+    // r#"..."#: no escapes, closed by a quote followed by as many hashes as opened it
+    fn raw_string(&mut self) -> Token {
+        let mut hashes = 0;
+        while self.peek() == '#' {
+            self.advance();
+            hashes += 1;
+        }
+        self.advance(); // opening quote
+
+        let start = self.current;
+        let closing = format!("\"{}", "#".repeat(hashes));
+        let Some(length) = self.find_terminator(&closing, |rest| rest.find(&closing)) else {
+            return self.unterminated_string();
+        };
+        self.advance_to(start + length);
+
+        let value = self.input[start..self.current].to_string();
+        for _ in 0..closing.len() {
+            self.advance();
+        }
+
+        Token::String {
+            value,
+            kind: StringKind::Raw { hashes },
+        }
+    }
+
+    // C++ style R"delim(...)delim", closed by `)delim"`
+    fn raw_delimited_string(&mut self) -> Token {
+        self.advance(); // opening quote
+
+        let start = self.current;
+        while !matches!(self.peek(), '(' | '"' | '\n') && !self.is_at_end() {
+            self.advance();
+        }
+        let delimiter = &self.input[start..self.current];
+
+        if self.peek() != '(' {
+            return self.unterminated_string();
+        }
+        // Same restrictions as C++: at most 16 characters, no spaces, parentheses or
+        // backslashes. A bad delimiter is still matched so the whole literal is skipped.
+        let valid = delimiter.chars().count() <= 16
+            && !delimiter
+                .chars()
+                .any(|c| c.is_whitespace() || matches!(c, ')' | '\\'));
+        self.advance(); // opening paren
+
+        let body_start = self.current;
+        let closing = format!("){}\"", delimiter);
+        let Some(length) = self.find_terminator(&closing, |rest| rest.find(&closing)) else {
+            return self.unterminated_string();
+        };
+        self.advance_to(body_start + length);
+
+        let value = self.input[body_start..self.current].to_string();
+        let delimiter = delimiter.to_string();
+        for _ in 0..closing.chars().count() {
+            self.advance();
+        }
+
+        if !valid {
+            return self.error(TokenError::InvalidRawDelimiter(delimiter));
+        }
+
+        Token::String {
+            value,
+            kind: StringKind::RawDelimited { delimiter },
+        }
+    }
+
+    // """...""": escapes are processed and the common indentation of the content
+    // lines is removed. A line break right after the opening quotes and a
+    // whitespace-only line before the closing quotes are dropped.
+    fn multi_line_string(&mut self) -> Token {
         self.advance();
-        Token::String(value)
+        self.advance();
+
+        let Some(length) = self.find_terminator("\"\"\"", multi_line_body_length) else {
+            return self.unterminated_string();
+        };
+        let body_end = self.current + length;
+        let body = &self.input[self.current..body_end];
+
+        let mut lines: Vec<&str> = body.split('\n').collect();
+        let skip_first = lines.len() > 1 && lines[0].trim().is_empty();
+        let drop_last = lines.len() > 1 && lines[lines.len() - 1].trim().is_empty();
+        // Content stops before the line break that precedes the closing line
+        let content_end = if drop_last {
+            body_end - lines.pop().map_or(0, str::len) - 1
+        } else {
+            body_end
+        };
+        if skip_first {
+            lines.remove(0);
+        }
+
+        // A body on a single line keeps its leading whitespace
+        let indent = if body.contains('\n') {
+            lines
+                .iter()
+                .filter(|line| !line.trim().is_empty())
+                .map(|line| line.chars().take_while(|c| matches!(c, ' ' | '\t')).count())
+                .min()
+                .unwrap_or(0)
+        } else {
+            0
+        };
+
+        if skip_first {
+            while self.advance() != '\n' {}
+        }
+
+        let mut value = String::new();
+        let mut valid = true;
+        let mut line_start = true;
+        while self.current < content_end {
+            if line_start {
+                line_start = false;
+                let mut stripped = 0;
+                while stripped < indent && matches!(self.peek(), ' ' | '\t') {
+                    self.advance();
+                    stripped += 1;
+                }
+                continue;
+            }
+
+            match self.advance() {
+                '\\' => match self.escape(true) {
+                    Escaped::Char(c) => value.push(c),
+                    Escaped::Continuation => {}
+                    Escaped::Invalid => valid = false,
+                },
+                '\r' if self.peek() == '\n' => {}
+                '\n' => {
+                    value.push('\n');
+                    line_start = true;
+                }
+                c => value.push(c),
+            }
+        }
+
+        // Skip the closing line's indentation and the closing quotes
+        while self.current < body_end + 3 {
+            self.advance();
+        }
+
+        if !valid {
+            return self.invalid_literal();
+        }
+        Token::String {
+            value,
+            kind: StringKind::MultiLine,
+        }
+    }
+
+    fn char_literal(&mut self) -> Token {
+        let mut value = None;
+        let mut count = 0;
+        let mut valid = true;
+
+        // Scan to the closing quote even after a problem so the whole literal is consumed
+        while self.peek() != '\'' && self.peek() != '\n' && !self.is_at_end() {
+            let c = match self.advance() {
+                '\\' => match self.escape(false) {
+                    Escaped::Char(c) => c,
+                    _ => {
+                        valid = false;
+                        continue;
+                    }
+                },
+                c => c,
+            };
+
+            value.get_or_insert(c);
+            count += 1;
+        }
+
+        if self.peek() != '\'' {
+            return self.error(TokenError::UnterminatedChar);
+        }
+        self.advance(); // consume closing quote
+
+        if !valid {
+            return self.invalid_literal();
+        }
+
+        match (value, count) {
+            (Some(c), 1) => Token::Char(c),
+            (None, _) => self.error(TokenError::EmptyCharLiteral),
+            _ => {
+                let text = self.input[self.token_start.offset..self.current].to_string();
+                self.error(TokenError::MultiCharLiteral(text))
+            }
+        }
     }
 
-    fn number(&mut self) -> Token {
+    // Handles the escape following a consumed backslash. Invalid escapes are
+    // reported at the backslash rather than at the start of the literal.
+    fn escape(&mut self, allow_continuation: bool) -> Escaped {
+        let start = Location {
+            line: self.line,
+            column: self.column - 1,
+            offset: self.current - 1,
+        };
+
+        // Left for the caller to report as an unterminated literal
+        if self.is_at_end() {
+            return Escaped::Invalid;
+        }
+
+        // A backslash before a line break joins the lines, dropping the
+        // break and the next line's indentation
+        if allow_continuation && matches!(self.peek(), '\n' | '\r') {
+            while matches!(self.peek(), ' ' | '\t' | '\n' | '\r') {
+                self.advance();
+            }
+            return Escaped::Continuation;
+        }
+
+        match self.decode_escape() {
+            Ok(c) => Escaped::Char(c),
+            Err(error) => {
+                self.report_at(start, error);
+                Escaped::Invalid
+            }
+        }
+    }
+
+    fn decode_escape(&mut self) -> Result<char, TokenError> {
         let start = self.current - 1;
 
-        while self.peek().is_ascii_digit() {
-            self.advance();
+        // The line break is left in place so the literal ends with its line
+        if matches!(self.peek(), '\n' | '\r') {
+            return Err(self.invalid_escape(start));
+        }
+
+        let c = match self.advance() {
+            'n' => '\n',
+            't' => '\t',
+            'r' => '\r',
+            '0' => '\0',
+            '\\' => '\\',
+            '"' => '"',
+            '\'' => '\'',
+            // Lets a string contain a literal `${`
+            '$' => '$',
+            // `\xHH`: exactly two hex digits
+            'x' => {
+                let digits = (self.peek(), self.peek_ahead(1));
+                if !(digits.0.is_ascii_hexdigit() && digits.1.is_ascii_hexdigit()) {
+                    return Err(self.invalid_escape(start));
+                }
+                self.advance();
+                self.advance();
+
+                let value = u8::from_str_radix(&self.input[start + 2..self.current], 16)
+                    .expect("two hex digits fit in a byte");
+                char::from(value)
+            }
+            // `\u{H..}`: one to six hex digits naming a Unicode scalar value
+            'u' => {
+                if self.peek() != '{' {
+                    return Err(self.invalid_escape(start));
+                }
+                self.advance();
+
+                let digits_start = self.current;
+                while self.peek().is_ascii_hexdigit() {
+                    self.advance();
+                }
+                let digits = &self.input[digits_start..self.current];
+
+                if self.peek() != '}' || digits.is_empty() || digits.len() > 6 {
+                    return Err(self.invalid_escape(start));
+                }
+                self.advance();
+
+                match u32::from_str_radix(digits, 16)
+                    .ok()
+                    .and_then(char::from_u32)
+                {
+                    Some(c) => c,
+                    None => return Err(self.invalid_escape(start)),
+                }
+            }
+            _ => return Err(self.invalid_escape(start)),
+        };
+
+        Ok(c)
+    }
+
+    fn invalid_escape(&self, start: usize) -> TokenError {
+        TokenError::InvalidEscape(self.input[start..self.current].to_string())
+    }
+
+    fn number(&mut self, first: char) -> Token {
+        let start = self.token_start.offset;
+        // `.5` style literals start directly in the fraction
+        let mut is_float = first == '.';
+
+        if first == '0' {
+            let base = match self.peek() {
+                'x' | 'X' => Some(NumberBase::Hexadecimal),
+                'o' | 'O' => Some(NumberBase::Octal),
+                'b' | 'B' => Some(NumberBase::Binary),
+                _ => None,
+            };
+            if let Some(base) = base {
+                self.advance(); // consume prefix letter
+                return self.prefixed_integer(start, base);
+            }
         }
 
-        // Handle decimal point
-        if self.peek() == '.' && self.peek_ahead(1).is_ascii_digit() {
+        self.digits(|c| c.is_ascii_digit());
+
+        // Handle decimal point. A dot not followed by a digit is left for member
+        // access or ranges, so `1.foo` and `1..2` keep their integer.
+        if !is_float && self.peek() == '.' && self.peek_ahead(1).is_ascii_digit() {
             self.advance(); // consume '.'
-            while self.peek().is_ascii_digit() {
+            self.digits(|c| c.is_ascii_digit());
+            is_float = true;
+        }
+
+        // Handle exponent, e.g. `1.23e-4` or `5E10`
+        if matches!(self.peek(), 'e' | 'E') {
+            let sign = matches!(self.peek_ahead(1), '+' | '-');
+            let digit_offset = if sign { 2 } else { 1 };
+
+            if self.peek_ahead(digit_offset).is_ascii_digit() {
+                for _ in 0..digit_offset {
+                    self.advance();
+                }
+                self.digits(|c| c.is_ascii_digit());
+                is_float = true;
+            } else if sign {
+                self.advance();
                 self.advance();
+                let text = self.input[start..self.current].to_string();
+                return self.error(TokenError::InvalidNumber(text));
             }
         }
 
-        let number_str: String = self.input[start..self.current].iter().collect();
-        match number_str.parse::<f64>() {
-            Ok(num) => Token::Number(num),
-            Err(_) => Token::Invalid(format!("Invalid number: {}", number_str)),
+        let digits = self.collect_digits(start, self.current);
+        let suffix = match self.numeric_suffix() {
+            Ok(suffix) => suffix,
+            Err(token) => return token,
+        };
+
+        if is_float && suffix.is_some_and(|suffix| !suffix.is_float()) {
+            let text = self.input[start..self.current].to_string();
+            return self.error(TokenError::InvalidSuffix(text));
         }
+
+        if is_float || suffix.is_some_and(|suffix| suffix.is_float()) {
+            return match digits.parse::<f64>() {
+                Ok(value) => Token::Float { value, suffix },
+                Err(_) => self.error(TokenError::InvalidNumber(digits)),
+            };
+        }
+
+        // C-style octal: a leading zero followed by more digits
+        if first == '0' && digits.len() > 1 {
+            return self.integer(start, &digits[1..], NumberBase::Octal, suffix);
+        }
+
+        self.integer(start, &digits, NumberBase::Decimal, suffix)
     }
 
-    fn identifier(&mut self) -> Token {
-        let start = self.current - 1;
+    fn prefixed_integer(&mut self, start: usize, base: NumberBase) -> Token {
+        let digits_start = self.current;
+
+        // Consume every digit-like character so bad digits are reported, not split off
+        if base == NumberBase::Hexadecimal {
+            self.digits(|c| c.is_ascii_hexdigit());
+        } else {
+            self.digits(|c| c.is_ascii_digit());
+        }
+
+        let digits = self.collect_digits(digits_start, self.current);
+        if digits.is_empty() {
+            let text = self.input[start..self.current].to_string();
+            return self.error(TokenError::InvalidNumber(text));
+        }
+
+        match self.numeric_suffix() {
+            Ok(Some(suffix)) if suffix.is_float() => {
+                let text = self.input[start..self.current].to_string();
+                self.error(TokenError::InvalidSuffix(text))
+            }
+            Ok(suffix) => self.integer(start, &digits, base, suffix),
+            Err(token) => token,
+        }
+    }
+
+    fn integer(
+        &mut self,
+        start: usize,
+        digits: &str,
+        base: NumberBase,
+        suffix: Option<NumericSuffix>,
+    ) -> Token {
+        if let Some(digit) = digits.chars().find(|c| !c.is_digit(base.radix())) {
+            return self.error(TokenError::InvalidDigit { digit, base });
+        }
+
+        match u128::from_str_radix(digits, base.radix()) {
+            Ok(value) => Token::Integer {
+                value,
+                base,
+                suffix,
+            },
+            Err(_) => {
+                let text = self.input[start..self.current].to_string();
+                self.error(TokenError::IntegerOverflow(text))
+            }
+        }
+    }
 
+    // Consumes digits accepted by `is_digit` along with `_` separators
+    fn digits(&mut self, is_digit: impl Fn(char) -> bool) {
+        while is_digit(self.peek()) || self.peek() == '_' {
+            self.advance();
+        }
+    }
+
+    fn collect_digits(&self, start: usize, end: usize) -> String {
+        self.input[start..end]
+            .chars()
+            .filter(|&c| c != '_')
+            .collect()
+    }
+
+    fn numeric_suffix(&mut self) -> Result<Option<NumericSuffix>, Token> {
+        if !self.peek().is_ascii_alphabetic() {
+            return Ok(None);
+        }
+
+        let start = self.current;
         while self.peek().is_ascii_alphanumeric() || self.peek() == '_' {
             self.advance();
         }
 
-        let text: String = self.input[start..self.current].iter().collect();
-
-        // Check for reserved keywords
-        let reserved = match text.as_str() {
-            "Null" | "null" => Some(Reserved::Null),
-            "Void" | "void" => Some(Reserved::Void),
-            "let" => Some(Reserved::Let),
-            "fn" => Some(Reserved::Fn),
-            "if" => Some(Reserved::If),
-            "else" => Some(Reserved::Else),
-            "while" => Some(Reserved::While),
-            "for" => Some(Reserved::For),
-            "continue" => Some(Reserved::Continue),
-            "break" => Some(Reserved::Break),
-            "Return" | "return" => Some(Reserved::Return),
-            "public" => Some(Reserved::Public),
-            "private" => Some(Reserved::Private),
-            "static" => Some(Reserved::Static),
-            "Print" | "print" => Some(Reserved::Print),
-            "True" | "true" => Some(Reserved::True),
-            "False" | "false" => Some(Reserved::False),
-            "Type" | "type" => Some(Reserved::Type),
-            "Alias" | "alias" => Some(Reserved::TypeAlias),
-            "Def" | "def" => Some(Reserved::TypeDef),
-            "Struct" | "struct" => Some(Reserved::Struct),
-            "Enum" | "enum" => Some(Reserved::Enum),
-            "Impl" | "impl" => Some(Reserved::Impl),
-            "Trait" | "trait" => Some(Reserved::Trait),
-            "Use" | "use" => Some(Reserved::Use),
-            "Union" | "union" => Some(Reserved::Union),
-            "Impl" | "impl" => Some(Reserved::Impl),
-            "import" => Some(Reserved::Import),
-            "export" => Some(Reserved::Export),
-            "define" | "Define" => Some(Reserved::Define),
-
-            _ => None,
+        let text = &self.input[start..self.current];
+        match NumericSuffix::from_text(text) {
+            Some(suffix) => Ok(Some(suffix)),
+            None => Err(self.error(TokenError::InvalidSuffix(text.to_string()))),
+        }
+    }
+
+    fn identifier(&mut self) -> Token {
+        let start = self.token_start.offset;
+
+        while self.peek().is_xid_continue() {
+            self.advance();
+        }
+
+        let raw = &self.input[start..self.current];
+
+        // Normalize so that visually identical spellings compare equal
+        let normalized: String;
+        let text = if raw.is_ascii() {
+            raw
+        } else {
+            normalized = raw.nfc().collect();
+            &normalized
         };
 
-        match reserved {
+        match self.keywords.reserved(text) {
             Some(keyword) => Token::Reserved(keyword),
-            None => Token::Identifier(text),
+            None => {
+                if self.lint_identifiers {
+                    self.lint_identifier(text);
+                }
+                Token::Identifier(Symbol::intern(text))
+            }
+        }
+    }
+
+    // Makes an identifier lexed earlier known to the confusable lint
+    pub(crate) fn remember_identifier(&mut self, name: &str) {
+        if !self.lint_identifiers {
+            return;
+        }
+        let key: String = skeleton(name).collect();
+        self.skeletons
+            .entry(key)
+            .or_insert_with(|| name.to_string());
+    }
+
+    fn lint_identifier(&mut self, name: &str) {
+        if !name.is_single_script() {
+            self.report(TokenError::MixedScriptIdentifier(name.to_string()));
+        }
+
+        let key: String = skeleton(name).collect();
+        match self.skeletons.get(&key) {
+            // Pairs of plain ASCII names such as `rn`/`m` are left alone
+            Some(existing) if existing != name && !(existing.is_ascii() && name.is_ascii()) => {
+                let similar_to = existing.clone();
+                self.report(TokenError::ConfusableIdentifier {
+                    name: name.to_string(),
+                    similar_to,
+                });
+            }
+            Some(_) => {}
+            None => {
+                self.skeletons.insert(key, name.to_string());
+            }
+        }
+    }
+
+    // Length of the body before `closing`, as found by `search` in the rest of
+    // the input. Once a terminator is missing from some offset on, later literals
+    // that use it fail without searching again.
+    fn find_terminator(
+        &mut self,
+        closing: &str,
+        search: impl Fn(&str) -> Option<usize>,
+    ) -> Option<usize> {
+        if self
+            .missing_terminators
+            .get(closing)
+            .is_some_and(|&from| from <= self.current)
+        {
+            return None;
+        }
+        let length = search(&self.input[self.current..]);
+        if length.is_none() {
+            self.missing_terminators
+                .insert(closing.to_string(), self.current);
+        }
+        length
+    }
+
+    fn advance_to(&mut self, offset: usize) {
+        while self.current < offset {
+            self.advance();
+        }
+    }
+
+    // Ends a string literal that is never closed at the end of the line it
+    // started on, so the lines after it are lexed normally. The scan has not
+    // left that line yet.
+    fn unterminated_string(&mut self) -> Token {
+        let start = self.token_start.offset;
+        let line_end = self.input[start..]
+            .find('\n')
+            .map_or(self.input.len(), |index| start + index);
+        self.advance_to(line_end);
+
+        self.error(TokenError::UnterminatedString('"'))
+    }
+
+    // Reports and leaves the interpolated strings that are still open
+    fn close_strings(&mut self) {
+        for mode in std::mem::take(&mut self.modes) {
+            if let LexMode::String { start } = mode {
+                self.report_at(start, TokenError::UnterminatedString('"'));
+            }
+        }
+    }
+
+    // Records a diagnostic spanning the current token and yields its `Invalid` stand-in
+    fn error(&mut self, error: TokenError) -> Token {
+        let message = error.to_string();
+        self.report(error);
+        Token::Invalid(message)
+    }
+
+    fn report(&mut self, error: TokenError) {
+        self.report_at(self.token_start, error);
+    }
+
+    // Records a diagnostic spanning `start` up to the current position
+    fn report_at(&mut self, start: Location, error: TokenError) {
+        let location = self.location();
+
+        self.diagnostics.push(LexDiagnostic {
+            error,
+            file_id: self.file_id,
+            start,
+            end: location,
+            recovery: location,
+        });
+    }
+
+    // Stand-in for a literal whose problems have already been reported
+    fn invalid_literal(&self) -> Token {
+        Token::Invalid(self.input[self.token_start.offset..self.current].to_string())
+    }
+
+    fn location(&self) -> Location {
+        Location {
+            line: self.line,
+            column: self.column,
+            offset: self.current,
         }
     }
 
@@ -268,22 +1129,33 @@ impl Lexer {
         self.peek_ahead(0)
     }
 
+    // `offset` counts characters, not bytes
     fn peek_ahead(&self, offset: usize) -> char {
-        let pos = self.current + offset;
-        if pos >= self.input.len() {
-            '\0'
-        } else {
-            self.input[pos]
+        let rest = &self.input.as_bytes()[self.current.min(self.input.len())..];
+
+        // Fast path: everything up to the requested character is ASCII
+        if rest.len() > offset && rest[..=offset].is_ascii() {
+            return rest[offset] as char;
         }
+
+        self.input[self.current..]
+            .chars()
+            .nth(offset)
+            .unwrap_or('\0')
     }
 
     fn advance(&mut self) -> char {
         if self.is_at_end() {
             return '\0';
         }
-        let ch = self.input[self.current];
-        self.current += 1;
-        self.column += 1;
+        let ch = self.peek();
+        self.current += ch.len_utf8();
+        if ch == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
         ch
     }
 
@@ -291,3 +1163,12 @@ impl Lexer {
         self.current >= self.input.len()
     }
 }
+
+// Yields the same tokens as `tokenize`, one at a time, ending with `Token::Eof`
+impl<'src> Iterator for Lexer<'src> {
+    type Item = TokenInfo<'src>;
+
+    fn next(&mut self) -> Option<TokenInfo<'src>> {
+        self.next_with_state().map(|(_, token_info)| token_info)
+    }
+}
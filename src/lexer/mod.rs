@@ -1,8 +1,22 @@
 #![allow(dead_code, unused, unused_imports)]
 
+pub mod error;
+pub mod incremental;
+pub mod keywords;
+#[allow(clippy::module_inception)]
 pub mod lexer;
+pub mod symbol;
 pub mod token;
+pub mod token_stream;
 
 // Re-export main types for easier access
-pub use lexer::Lexer;
-pub use token::{Operation, Punctuation, Reserved, Token, TokenInfo};
+pub use error::{LexDiagnostic, Location, Severity, TokenError};
+pub use incremental::{Lexed, TextEdit};
+pub use keywords::{KeywordMode, Keywords};
+pub use lexer::{LexState, Lexer};
+pub use symbol::Symbol;
+pub use token::{
+    CommentKind, NumberBase, NumericSuffix, OPERATORS, Operation, Punctuation, Reserved,
+    StringKind, Token, TokenInfo,
+};
+pub use token_stream::TokenStream;
@@ -1,43 +1,120 @@
+use std::rc::Rc;
+
+use crate::lexer::symbol::Symbol;
+use crate::source::expansion::Expansion;
+use crate::source::span::Span;
+
 #[allow(dead_code, unused_variables)]
 #[derive(Debug, Clone, PartialEq)]
 pub enum Punctuation {
-    OpenParen = 0,      // (
-    CloseParen = 1,     // )
-    OpenBrace = 2,      // {
-    CloseBrace = 3,     // }
-    OpenBracket = 4,    // [
-    CloseBracket = 5,   // ]
-    Comma = 6,          // ,
-    Semicolon = 7,      // ;
-    Dot = 8,            // .
-    Colon = 9,          // :
-    QuestionMark = 10,  // ?
-    Comment = 11,       // //
-    Hashtag = 12,       // #
-    CommentBlkStr = 13, // /*
-    CommentBlkEnd = 14, // *\
-    At = 15,            // @
+    OpenParen = 0,     // (
+    CloseParen = 1,    // )
+    OpenBrace = 2,     // {
+    CloseBrace = 3,    // }
+    OpenBracket = 4,   // [
+    CloseBracket = 5,  // ]
+    Comma = 6,         // ,
+    Semicolon = 7,     // ;
+    Dot = 8,           // .
+    Colon = 9,         // :
+    QuestionMark = 10, // ?
+    Hashtag = 12,      // #
+    At = 15,           // @
+    Dollar = 16,       // $
 }
 
 #[derive(Debug, Clone, PartialEq)]
-pub enum Operation {
-    Add,          // +
-    Subtract,     // -
-    Multiply,     // *
-    Divide,       // /
-    Assign,       // =
-    IfEqual,      // ==
-    NotEqual,     // !=
-    Greater,      // >
-    Less,         // <
-    GreaterEqual, // >=
-    LessEqual,    // <=
-    Not,          // !
-    Modulo,       // %
-    Remainder,    // %%
+pub enum CommentKind {
+    Line,  // // ...
+    Block, // /* ... */
 }
 
+// Spelling of a string literal, kept so a formatter can reproduce it
 #[derive(Debug, Clone, PartialEq)]
+pub enum StringKind {
+    Plain,                              // "..."
+    Raw { hashes: usize },              // r"...", r#"..."#
+    RawDelimited { delimiter: String }, // R"delim(...)delim"
+    MultiLine,                          // """..."""
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Operation {
+    Add,           // +
+    Subtract,      // -
+    Multiply,      // *
+    Divide,        // /
+    Assign,        // =
+    IfEqual,       // ==
+    NotEqual,      // !=
+    Greater,       // >
+    Less,          // <
+    GreaterEqual,  // >=
+    LessEqual,     // <=
+    Not,           // !
+    Modulo,        // %
+    Remainder,     // %%
+    And,           // &&
+    Or,            // ||
+    BitAnd,        // &
+    BitOr,         // |
+    BitXor,        // ^
+    BitNot,        // ~
+    ShiftLeft,     // <<
+    ShiftRight,    // >>
+    AddAssign,     // +=
+    SubAssign,     // -=
+    MulAssign,     // *=
+    DivAssign,     // /=
+    OrAssign,      // |=
+    AndAssign,     // &=
+    Increment,     // ++
+    Decrement,     // --
+    Arrow,         // ->
+    FatArrow,      // =>
+    PathSeparator, // ::
+}
+
+// Spelling of every operator. The lexer takes the longest entry that matches,
+// so `<<` wins over `<` and `<=` regardless of table order.
+pub const OPERATORS: &[(&str, Operation)] = &[
+    ("+", Operation::Add),
+    ("-", Operation::Subtract),
+    ("*", Operation::Multiply),
+    ("/", Operation::Divide),
+    ("=", Operation::Assign),
+    ("==", Operation::IfEqual),
+    ("!=", Operation::NotEqual),
+    (">", Operation::Greater),
+    ("<", Operation::Less),
+    (">=", Operation::GreaterEqual),
+    ("<=", Operation::LessEqual),
+    ("!", Operation::Not),
+    ("%", Operation::Modulo),
+    ("%%", Operation::Remainder),
+    ("&&", Operation::And),
+    ("||", Operation::Or),
+    ("&", Operation::BitAnd),
+    ("|", Operation::BitOr),
+    ("^", Operation::BitXor),
+    ("~", Operation::BitNot),
+    ("<<", Operation::ShiftLeft),
+    (">>", Operation::ShiftRight),
+    ("+=", Operation::AddAssign),
+    ("-=", Operation::SubAssign),
+    ("*=", Operation::MulAssign),
+    ("/=", Operation::DivAssign),
+    ("|=", Operation::OrAssign),
+    ("&=", Operation::AndAssign),
+    ("++", Operation::Increment),
+    ("--", Operation::Decrement),
+    ("->", Operation::Arrow),
+    ("=>", Operation::FatArrow),
+    ("::", Operation::PathSeparator),
+];
+
+// Spellings live in `keywords::KEYWORDS`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Reserved {
     Null,
     Void,
@@ -47,6 +124,8 @@ pub enum Reserved {
     Else,
     While,
     For,
+    In,
+    Match,
     Continue,
     Break,
     Return,
@@ -68,20 +147,116 @@ pub enum Reserved {
     Use,
     Import,
     Export,
-    EnumVariant,
-    StructField,
     TypeAlias,
     TypeDef,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NumberBase {
+    Binary,      // 0b
+    Octal,       // 0o or a leading 0
+    Decimal,     //
+    Hexadecimal, // 0x
+}
+
+impl NumberBase {
+    pub fn radix(&self) -> u32 {
+        match self {
+            NumberBase::Binary => 2,
+            NumberBase::Octal => 8,
+            NumberBase::Decimal => 10,
+            NumberBase::Hexadecimal => 16,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NumericSuffix {
+    I8,
+    I16,
+    I32,
+    I64,
+    I128,
+    U8,
+    U16,
+    U32,
+    U64,
+    U128,
+    F32,
+    F64,
+    // C-style suffixes
+    Unsigned,         // u
+    Long,             // l
+    UnsignedLong,     // ul
+    LongLong,         // ll
+    UnsignedLongLong, // ull
+}
+
+impl NumericSuffix {
+    pub fn from_text(text: &str) -> Option<Self> {
+        let suffix = match text {
+            "i8" => NumericSuffix::I8,
+            "i16" => NumericSuffix::I16,
+            "i32" => NumericSuffix::I32,
+            "i64" => NumericSuffix::I64,
+            "i128" => NumericSuffix::I128,
+            "u8" => NumericSuffix::U8,
+            "u16" => NumericSuffix::U16,
+            "u32" => NumericSuffix::U32,
+            "u64" => NumericSuffix::U64,
+            "u128" => NumericSuffix::U128,
+            "f32" | "f" | "F" => NumericSuffix::F32,
+            "f64" => NumericSuffix::F64,
+            // C suffixes are case-insensitive and `u` may come before or after `l`
+            _ => match text.to_ascii_lowercase().as_str() {
+                "u" => NumericSuffix::Unsigned,
+                "l" => NumericSuffix::Long,
+                "ul" | "lu" => NumericSuffix::UnsignedLong,
+                "ll" => NumericSuffix::LongLong,
+                "ull" | "llu" => NumericSuffix::UnsignedLongLong,
+                _ => return None,
+            },
+        };
+        Some(suffix)
+    }
+
+    pub fn is_float(&self) -> bool {
+        matches!(self, NumericSuffix::F32 | NumericSuffix::F64)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum Token {
-    Identifier(String),
-    Number(f64),
-    String(String),
+    Identifier(Symbol),
+    Integer {
+        value: u128,
+        base: NumberBase,
+        suffix: Option<NumericSuffix>,
+    },
+    Float {
+        value: f64,
+        suffix: Option<NumericSuffix>,
+    },
+    String {
+        value: String,
+        kind: StringKind,
+    },
+    // An interpolated string such as `"a ${x} b"` lexes as StringStart,
+    // StringFragment("a "), InterpolationStart, x, InterpolationEnd,
+    // StringFragment(" b"), StringEnd
+    StringStart,
+    StringFragment(String),
+    InterpolationStart,
+    InterpolationEnd,
+    StringEnd,
+    Char(char),
     Reserved(Reserved),
     Operation(Operation),
     Punctuation(Punctuation),
+    Comment {
+        kind: CommentKind,
+        text: String,
+    },
     Whitespace,
     Newline,
     Eof,
@@ -89,30 +264,29 @@ pub enum Token {
 }
 
 #[derive(Debug, Clone)]
-pub struct TokenInfo {
+pub struct TokenInfo<'src> {
     pub token: Token,
+    // Exact source text of the token, borrowed from the lexer input
     #[allow(dead_code)]
-    pub lexeme: String,
+    pub lexeme: &'src str,
     #[allow(dead_code)]
     pub line: usize,
     #[allow(dead_code)]
     pub column: usize,
+    pub span: Span,
+    // Set on tokens produced by macro expansion
+    pub expansion: Option<Rc<Expansion>>,
 }
 
-#[derive(Debug, Clone)]
-pub enum TokenError {
-    UnexpectedToken(Token),
-    UnexpectedCharacter(char),
-    UnexpectedEndOfFile,
-}
-
-impl TokenInfo {
-    pub fn new(token: Token, lexeme: String, line: usize, column: usize) -> Self {
+impl<'src> TokenInfo<'src> {
+    pub fn new(token: Token, lexeme: &'src str, line: usize, column: usize, span: Span) -> Self {
         Self {
             token,
             lexeme,
             line,
             column,
+            span,
+            expansion: None,
         }
     }
 }
@@ -0,0 +1,71 @@
+use std::collections::VecDeque;
+
+use crate::lexer::token::{Token, TokenInfo};
+use crate::source::span::Span;
+
+// Buffered view over a token iterator. Tokens are pulled from the source only as
+// far as lookahead requires, so a consumer that stops early never lexes the rest.
+pub struct TokenStream<'src> {
+    source: Box<dyn Iterator<Item = TokenInfo<'src>> + 'src>,
+    buffer: VecDeque<TokenInfo<'src>>,
+    // Returned for lookahead past the end of the source
+    eof: TokenInfo<'src>,
+}
+
+impl<'src> TokenStream<'src> {
+    pub fn new(source: impl Iterator<Item = TokenInfo<'src>> + 'src) -> Self {
+        Self {
+            source: Box::new(source),
+            buffer: VecDeque::new(),
+            eof: TokenInfo::new(Token::Eof, "", 1, 1, Span::default()),
+        }
+    }
+
+    pub fn peek(&mut self) -> &TokenInfo<'src> {
+        self.peek_nth(0)
+    }
+
+    // Looks `k` tokens ahead without consuming anything; `peek_nth(0)` is the next token
+    pub fn peek_nth(&mut self, k: usize) -> &TokenInfo<'src> {
+        while self.buffer.len() <= k {
+            match self.source.next() {
+                Some(info) => {
+                    if info.token == Token::Eof {
+                        self.eof = info.clone();
+                    }
+                    self.buffer.push_back(info);
+                }
+                None => break,
+            }
+        }
+
+        self.buffer.get(k).unwrap_or(&self.eof)
+    }
+
+    // Consumes the first `len` bytes of the next token and leaves the rest as a
+    // token of its own, as when the first `>` of `>>` closes a type argument list
+    pub fn split_front(&mut self, len: usize, rest: Token) {
+        self.peek();
+        if let Some(info) = self.buffer.front_mut() {
+            info.token = rest;
+            info.lexeme = &info.lexeme[len..];
+            info.column += len;
+            info.span.start_byte += len;
+        }
+    }
+
+    pub fn is_at_end(&mut self) -> bool {
+        self.peek().token == Token::Eof
+    }
+}
+
+impl<'src> Iterator for TokenStream<'src> {
+    type Item = TokenInfo<'src>;
+
+    fn next(&mut self) -> Option<TokenInfo<'src>> {
+        match self.buffer.pop_front() {
+            Some(info) => Some(info),
+            None => self.source.next(),
+        }
+    }
+}
@@ -0,0 +1,145 @@
+use std::fmt;
+use std::rc::Rc;
+
+use crate::lexer::token::TokenInfo;
+use crate::source::expansion::Expansion;
+use crate::source::span::Span;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MacroError {
+    MalformedDeclaration,
+    MissingFragmentName,
+    MissingFragmentKind(String),
+    UnknownFragmentKind(String),
+    MissingRepetitionOperator,
+    NoMatchingRule(String),
+    UnboundFragment(String),
+    RepetitionMismatch(String),
+    RepetitionDepth(String),
+    NothingToRepeat,
+    RecursionLimit(String),
+    UnbalancedDelimiter,
+}
+
+impl MacroError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            MacroError::MalformedDeclaration => "M0001",
+            MacroError::MissingFragmentName => "M0002",
+            MacroError::MissingFragmentKind(_) => "M0003",
+            MacroError::UnknownFragmentKind(_) => "M0004",
+            MacroError::MissingRepetitionOperator => "M0005",
+            MacroError::NoMatchingRule(_) => "M0006",
+            MacroError::UnboundFragment(_) => "M0007",
+            MacroError::RepetitionMismatch(_) => "M0008",
+            MacroError::RepetitionDepth(_) => "M0009",
+            MacroError::NothingToRepeat => "M0010",
+            MacroError::RecursionLimit(_) => "M0011",
+            MacroError::UnbalancedDelimiter => "M0012",
+        }
+    }
+}
+
+impl fmt::Display for MacroError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MacroError::MalformedDeclaration => {
+                write!(f, "Expected `macro name (pattern) => {{ template }}`")
+            }
+            MacroError::MissingFragmentName => {
+                write!(f, "Expected a fragment name or `(` after $")
+            }
+            MacroError::MissingFragmentKind(name) => {
+                write!(f, "Expected a fragment kind after ${}:", name)
+            }
+            MacroError::UnknownFragmentKind(kind) => {
+                write!(
+                    f,
+                    "Unknown fragment kind {}, expected tt, ident, literal or expr",
+                    kind
+                )
+            }
+            MacroError::MissingRepetitionOperator => {
+                write!(f, "Expected *, + or ? after a repetition")
+            }
+            MacroError::NoMatchingRule(name) => {
+                write!(f, "No rule of macro {} matches these arguments", name)
+            }
+            MacroError::UnboundFragment(name) => {
+                write!(f, "Fragment ${} is not bound by the pattern", name)
+            }
+            MacroError::RepetitionMismatch(name) => {
+                write!(
+                    f,
+                    "Repeated fragments of macro {} have different lengths",
+                    name
+                )
+            }
+            MacroError::RepetitionDepth(name) => {
+                write!(
+                    f,
+                    "Fragment ${} is used at the wrong repetition depth",
+                    name
+                )
+            }
+            MacroError::NothingToRepeat => {
+                write!(f, "Repetition in a template uses no repeated fragment")
+            }
+            MacroError::RecursionLimit(name) => {
+                write!(f, "Recursion limit reached while expanding macro {}", name)
+            }
+            MacroError::UnbalancedDelimiter => write!(f, "Unbalanced delimiter"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MacroDiagnostic {
+    pub error: MacroError,
+    pub span: Span,
+    pub line: usize,
+    pub column: usize,
+    // Set when the offending token came out of a macro expansion
+    pub expansion: Option<Rc<Expansion>>,
+}
+
+impl MacroDiagnostic {
+    pub fn at(error: MacroError, token: &TokenInfo) -> Self {
+        Self {
+            error,
+            span: token.span,
+            line: token.line,
+            column: token.column,
+            expansion: token.expansion.clone(),
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        self.error.code()
+    }
+}
+
+impl fmt::Display for MacroDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "error[{}] {}:{}: {}",
+            self.code(),
+            self.line,
+            self.column,
+            self.error
+        )?;
+        if let Some(expansion) = &self.expansion {
+            for outer in expansion.backtrace() {
+                write!(
+                    f,
+                    "\n  in expansion of macro {} at {}:{}",
+                    outer.name, outer.line, outer.column
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for MacroDiagnostic {}
@@ -0,0 +1,406 @@
+use std::collections::{HashMap, VecDeque};
+use std::rc::Rc;
+
+use crate::lexer::symbol::Symbol;
+use crate::lexer::token::{Operation, Reserved, Token, TokenInfo};
+use crate::macros::error::{MacroDiagnostic, MacroError};
+use crate::macros::pattern::{self, Binding, Bindings, MacroPart};
+use crate::macros::token_tree::{self, Delimiter, TokenTree};
+use crate::source::expansion::Expansion;
+use crate::source::span::Span;
+
+// Expansions nested deeper than this are assumed to recurse forever
+const MAX_EXPANSION_DEPTH: usize = 128;
+
+// Total expansions one `expand` call may perform, which also stops macros that
+// recurse more than once per level without nesting deeply
+const MAX_EXPANSIONS: usize = 1 << 16;
+
+#[derive(Debug, Clone)]
+struct MacroRule<'src> {
+    pattern: Vec<MacroPart<'src>>,
+    template: Vec<MacroPart<'src>>,
+}
+
+#[derive(Debug, Clone)]
+struct MacroDefinition<'src> {
+    // Rules are tried in the order they were declared
+    rules: Vec<MacroRule<'src>>,
+    // The first declaration of the macro
+    definition: Span,
+}
+
+// Expands declarative macros:
+//
+//     macro max ($a:expr, $b:expr) => { ($a > $b ? $a : $b) }
+//     macro max ($a:expr, $($rest:expr),+) => { max!($a, max!($($rest),+)) }
+//
+// Declaring a name again adds another rule. Identifiers a template binds with
+// `let` or `for` are renamed per expansion so they cannot capture or shadow
+// identifiers passed in by the caller.
+#[derive(Debug, Default)]
+pub struct MacroExpander<'src> {
+    macros: HashMap<Symbol, MacroDefinition<'src>>,
+    diagnostics: Vec<MacroDiagnostic>,
+    // Number of expansions so far, used to make renamed identifiers unique
+    expansions: usize,
+    // Expansions left in the current `expand` call
+    fuel: usize,
+}
+
+impl<'src> MacroExpander<'src> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn diagnostics(&self) -> &[MacroDiagnostic] {
+        &self.diagnostics
+    }
+
+    pub fn is_defined(&self, name: &str) -> bool {
+        self.macros.contains_key(&Symbol::intern(name))
+    }
+
+    // Removes macro declarations from `tokens` and replaces every invocation
+    // with its expansion
+    pub fn expand(&mut self, tokens: Vec<TokenInfo<'src>>) -> Vec<TokenInfo<'src>> {
+        if self.macros.is_empty()
+            && !tokens
+                .iter()
+                .any(|info| info.token == Token::Reserved(Reserved::Macro))
+        {
+            return tokens;
+        }
+        self.fuel = MAX_EXPANSIONS;
+
+        // The parser skips trivia anyway, and without it patterns can span lines
+        let tokens: Vec<_> = tokens
+            .into_iter()
+            .filter(|info| {
+                !matches!(
+                    info.token,
+                    Token::Whitespace | Token::Newline | Token::Comment { .. }
+                )
+            })
+            .collect();
+        match token_tree::parse_trees(tokens.clone()) {
+            Ok(trees) => token_tree::flatten(self.expand_trees(trees)),
+            Err(unmatched) => {
+                self.report(MacroError::UnbalancedDelimiter, &unmatched);
+                tokens
+            }
+        }
+    }
+
+    // Expansion results are pushed back onto the input so they are rescanned
+    // together with the trees that follow them
+    fn expand_trees(&mut self, trees: Vec<TokenTree<'src>>) -> Vec<TokenTree<'src>> {
+        let mut input: VecDeque<_> = trees.into();
+        let mut output = Vec::new();
+
+        while let Some(tree) = input.pop_front() {
+            let info = match tree {
+                TokenTree::Group {
+                    delimiter,
+                    open,
+                    trees,
+                    close,
+                } => {
+                    output.push(TokenTree::Group {
+                        delimiter,
+                        open,
+                        trees: self.expand_trees(trees),
+                        close,
+                    });
+                    continue;
+                }
+                TokenTree::Leaf(info) => info,
+            };
+
+            match &info.token {
+                Token::Reserved(Reserved::Macro) => self.declaration(info, &mut input),
+                Token::Identifier(name)
+                    if self.macros.contains_key(name) && is_invocation(&input) =>
+                {
+                    let name = *name;
+                    let replacement = self.invocation(name, info, &mut input);
+                    for tree in replacement.into_iter().rev() {
+                        input.push_front(tree);
+                    }
+                }
+                _ => output.push(TokenTree::Leaf(info)),
+            }
+        }
+
+        output
+    }
+
+    // `macro name (pattern) => { template }`
+    fn declaration(&mut self, keyword: TokenInfo<'src>, input: &mut VecDeque<TokenTree<'src>>) {
+        let name = match input.front() {
+            Some(TokenTree::Leaf(TokenInfo {
+                token: Token::Identifier(name),
+                ..
+            })) => *name,
+            _ => {
+                self.report(MacroError::MalformedDeclaration, &keyword);
+                return;
+            }
+        };
+        let well_formed = matches!(
+            input.get(1),
+            Some(TokenTree::Group {
+                delimiter: Delimiter::Paren,
+                ..
+            })
+        ) && input
+            .get(2)
+            .is_some_and(|tree| tree.is_token(&Token::Operation(Operation::FatArrow)))
+            && matches!(
+                input.get(3),
+                Some(TokenTree::Group {
+                    delimiter: Delimiter::Brace,
+                    ..
+                })
+            );
+        if !well_formed {
+            self.report(MacroError::MalformedDeclaration, &keyword);
+            return;
+        }
+
+        input.pop_front();
+        let Some(TokenTree::Group { trees: pattern, .. }) = input.pop_front() else {
+            unreachable!()
+        };
+        input.pop_front();
+        let Some(TokenTree::Group {
+            trees: template,
+            close,
+            ..
+        }) = input.pop_front()
+        else {
+            unreachable!()
+        };
+
+        let rule = pattern::compile(pattern, true).and_then(|pattern| {
+            let template = pattern::compile(template, false)?;
+            Ok(MacroRule { pattern, template })
+        });
+        let rule = match rule {
+            Ok(rule) => rule,
+            Err(diagnostic) => {
+                self.diagnostics.push(diagnostic);
+                return;
+            }
+        };
+
+        let definition = keyword.span.to(close.span);
+        self.macros
+            .entry(name)
+            .or_insert_with(|| MacroDefinition {
+                rules: Vec::new(),
+                definition,
+            })
+            .rules
+            .push(rule);
+    }
+
+    // `name!(...)`, with any kind of delimiter around the arguments
+    fn invocation(
+        &mut self,
+        name: Symbol,
+        token: TokenInfo<'src>,
+        input: &mut VecDeque<TokenTree<'src>>,
+    ) -> Vec<TokenTree<'src>> {
+        input.pop_front();
+        let Some(TokenTree::Group {
+            trees: arguments,
+            close,
+            ..
+        }) = input.pop_front()
+        else {
+            unreachable!()
+        };
+
+        let depth = token
+            .expansion
+            .as_ref()
+            .map_or(0, |e| e.backtrace().count());
+        if depth >= MAX_EXPANSION_DEPTH || self.fuel == 0 {
+            self.report(MacroError::RecursionLimit(name.to_string()), &token);
+            return Vec::new();
+        }
+        self.fuel -= 1;
+
+        let definition = &self.macros[&name];
+        let matched = definition.rules.iter().find_map(|rule| {
+            match pattern::match_parts(&rule.pattern, &arguments, None) {
+                Some((used, bindings)) if used == arguments.len() => Some((rule, bindings)),
+                _ => None,
+            }
+        });
+        let Some((rule, bindings)) = matched else {
+            self.report(MacroError::NoMatchingRule(name.to_string()), &token);
+            return Vec::new();
+        };
+
+        let mut call_site = token.span;
+        if close.span.file_id == call_site.file_id {
+            call_site = call_site.to(close.span);
+        }
+        let expansion = Rc::new(Expansion {
+            name: name.to_string(),
+            call_site,
+            line: token.line,
+            column: token.column,
+            definition: definition.definition,
+            parent: token.expansion.clone(),
+        });
+
+        self.expansions += 1;
+        let transcriber = Transcriber {
+            name,
+            expansion,
+            renames: hygienic_renames(&rule.template, self.expansions),
+        };
+        match transcriber.transcribe(&rule.template, &bindings) {
+            Ok(trees) => trees,
+            Err(error) => {
+                self.report(error, &token);
+                Vec::new()
+            }
+        }
+    }
+
+    fn report(&mut self, error: MacroError, token: &TokenInfo) {
+        self.diagnostics.push(MacroDiagnostic::at(error, token));
+    }
+}
+
+// A declared macro name is only expanded when followed by `!` and a group
+fn is_invocation(input: &VecDeque<TokenTree>) -> bool {
+    input
+        .front()
+        .is_some_and(|tree| tree.is_token(&Token::Operation(Operation::Not)))
+        && matches!(input.get(1), Some(TokenTree::Group { .. }))
+}
+
+// Identifiers the template introduces with `let` or `for`, mapped to names
+// that are unique to this expansion
+fn hygienic_renames(template: &[MacroPart], expansion: usize) -> HashMap<Symbol, Symbol> {
+    let mut renames = HashMap::new();
+    collect_bound_names(template, expansion, &mut renames);
+    renames
+}
+
+fn collect_bound_names(
+    parts: &[MacroPart],
+    expansion: usize,
+    renames: &mut HashMap<Symbol, Symbol>,
+) {
+    for (index, part) in parts.iter().enumerate() {
+        match part {
+            MacroPart::Token(info)
+                if matches!(info.token, Token::Reserved(Reserved::Let | Reserved::For)) =>
+            {
+                if let Some(MacroPart::Token(TokenInfo {
+                    token: Token::Identifier(name),
+                    ..
+                })) = parts.get(index + 1)
+                {
+                    let renamed = Symbol::intern(&format!("{}#{}", name, expansion));
+                    renames.insert(*name, renamed);
+                }
+            }
+            MacroPart::Group { parts, .. } | MacroPart::Repeat { parts, .. } => {
+                collect_bound_names(parts, expansion, renames)
+            }
+            _ => {}
+        }
+    }
+}
+
+struct Transcriber {
+    name: Symbol,
+    expansion: Rc<Expansion>,
+    renames: HashMap<Symbol, Symbol>,
+}
+
+impl Transcriber {
+    fn transcribe<'src>(
+        &self,
+        parts: &[MacroPart<'src>],
+        bindings: &Bindings<'src>,
+    ) -> Result<Vec<TokenTree<'src>>, MacroError> {
+        let mut trees = Vec::new();
+
+        for part in parts {
+            match part {
+                MacroPart::Token(info) => trees.push(TokenTree::Leaf(self.template_token(info))),
+                MacroPart::Group {
+                    delimiter,
+                    open,
+                    parts,
+                    close,
+                } => trees.push(TokenTree::Group {
+                    delimiter: *delimiter,
+                    open: self.template_token(open),
+                    trees: self.transcribe(parts, bindings)?,
+                    close: self.template_token(close),
+                }),
+                // Arguments keep their own tokens, so they are never renamed
+                MacroPart::Fragment { name, .. } => match bindings.get(name) {
+                    Some(Binding::Single(bound)) => trees.extend(bound.iter().cloned()),
+                    Some(Binding::Repeated(_)) => {
+                        return Err(MacroError::RepetitionDepth(name.to_string()));
+                    }
+                    None => return Err(MacroError::UnboundFragment(name.to_string())),
+                },
+                MacroPart::Repeat {
+                    parts, separator, ..
+                } => {
+                    // Repeated fragments used here, which all have to repeat
+                    // the same number of times
+                    let repeated: Vec<_> = pattern::fragment_names(parts)
+                        .into_iter()
+                        .filter_map(|name| match bindings.get(&name) {
+                            Some(Binding::Repeated(entries)) => Some((name, entries)),
+                            _ => None,
+                        })
+                        .collect();
+                    let Some((_, first)) = repeated.first() else {
+                        return Err(MacroError::NothingToRepeat);
+                    };
+                    let count = first.len();
+                    if repeated.iter().any(|(_, entries)| entries.len() != count) {
+                        return Err(MacroError::RepetitionMismatch(self.name.to_string()));
+                    }
+
+                    for index in 0..count {
+                        if let Some(separator) = separator.as_ref().filter(|_| index > 0) {
+                            trees.push(TokenTree::Leaf(self.template_token(separator)));
+                        }
+                        let mut iteration = bindings.clone();
+                        for (name, entries) in &repeated {
+                            iteration.insert(*name, entries[index].clone());
+                        }
+                        trees.extend(self.transcribe(parts, &iteration)?);
+                    }
+                }
+            }
+        }
+
+        Ok(trees)
+    }
+
+    fn template_token<'src>(&self, info: &TokenInfo<'src>) -> TokenInfo<'src> {
+        let mut info = info.clone();
+        if let Token::Identifier(name) = &info.token
+            && let Some(renamed) = self.renames.get(name)
+        {
+            info.token = Token::Identifier(*renamed);
+        }
+        info.expansion = Some(self.expansion.clone());
+        info
+    }
+}
@@ -0,0 +1,12 @@
+#![allow(dead_code, unused, unused_imports)]
+
+pub mod error;
+pub mod expander;
+pub mod pattern;
+pub mod token_tree;
+
+// Re-export main types for easier access
+pub use error::{MacroDiagnostic, MacroError};
+pub use expander::MacroExpander;
+pub use pattern::{Binding, FragmentKind, Kleene, MacroPart};
+pub use token_tree::{Delimiter, TokenTree};
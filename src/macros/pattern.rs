@@ -0,0 +1,345 @@
+use std::collections::HashMap;
+
+use crate::lexer::symbol::Symbol;
+use crate::lexer::token::{Operation, Punctuation, Reserved, Token, TokenInfo};
+use crate::macros::error::{MacroDiagnostic, MacroError};
+use crate::macros::token_tree::{Delimiter, TokenTree};
+
+// What a `$name:kind` fragment in a pattern accepts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FragmentKind {
+    TokenTree, // tt, also used when no kind is given
+    Ident,     // ident
+    Literal,   // literal
+    Expr,      // expr
+}
+
+impl FragmentKind {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "tt" => Some(FragmentKind::TokenTree),
+            "ident" => Some(FragmentKind::Ident),
+            "literal" => Some(FragmentKind::Literal),
+            "expr" => Some(FragmentKind::Expr),
+            _ => None,
+        }
+    }
+
+    // Number of trees at the front of `trees` this fragment matches. An `expr`
+    // runs up to `stop`, or a top-level `,` or `;`.
+    fn match_len(&self, trees: &[TokenTree], stop: Option<&Token>) -> Option<usize> {
+        let first = trees.first()?;
+        let leaf = match first {
+            TokenTree::Leaf(info) => Some(&info.token),
+            TokenTree::Group { .. } => None,
+        };
+
+        match self {
+            FragmentKind::TokenTree => Some(1),
+            FragmentKind::Ident => matches!(leaf, Some(Token::Identifier(_))).then_some(1),
+            FragmentKind::Literal => matches!(
+                leaf,
+                Some(
+                    Token::Integer { .. }
+                        | Token::Float { .. }
+                        | Token::String { .. }
+                        | Token::Char(_)
+                        | Token::Reserved(Reserved::True | Reserved::False | Reserved::Null)
+                )
+            )
+            .then_some(1),
+            FragmentKind::Expr => {
+                let len = trees
+                    .iter()
+                    .take_while(|tree| {
+                        !stop.is_some_and(|stop| tree.is_token(stop))
+                            && !tree.is_token(&Token::Punctuation(Punctuation::Comma))
+                            && !tree.is_token(&Token::Punctuation(Punctuation::Semicolon))
+                    })
+                    .count();
+                (len > 0).then_some(len)
+            }
+        }
+    }
+}
+
+// How often a `$( ... )` repetition may match
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kleene {
+    ZeroOrMore, // *
+    OneOrMore,  // +
+    ZeroOrOne,  // ?
+}
+
+impl Kleene {
+    fn from_token(token: &Token) -> Option<Self> {
+        match token {
+            Token::Operation(Operation::Multiply) => Some(Kleene::ZeroOrMore),
+            Token::Operation(Operation::Add) => Some(Kleene::OneOrMore),
+            Token::Punctuation(Punctuation::QuestionMark) => Some(Kleene::ZeroOrOne),
+            _ => None,
+        }
+    }
+}
+
+// Compiled form of a macro pattern or template
+#[derive(Debug, Clone)]
+pub enum MacroPart<'src> {
+    Token(TokenInfo<'src>),
+    Group {
+        delimiter: Delimiter,
+        open: TokenInfo<'src>,
+        parts: Vec<MacroPart<'src>>,
+        close: TokenInfo<'src>,
+    },
+    // `$name` in a template, `$name:kind` in a pattern
+    Fragment {
+        name: Symbol,
+        kind: FragmentKind,
+        dollar: TokenInfo<'src>,
+    },
+    // `$( ... ) separator? kleene`
+    Repeat {
+        parts: Vec<MacroPart<'src>>,
+        separator: Option<TokenInfo<'src>>,
+        kleene: Kleene,
+    },
+}
+
+// Trees a fragment matched; fragments inside repetitions bind one entry per
+// iteration
+#[derive(Debug, Clone)]
+pub enum Binding<'src> {
+    Single(Vec<TokenTree<'src>>),
+    Repeated(Vec<Binding<'src>>),
+}
+
+pub type Bindings<'src> = HashMap<Symbol, Binding<'src>>;
+
+// Compiles the trees of a pattern (`pattern` true) or template. Fragment kinds
+// are only read in patterns; in a template `$x:y` is `$x` followed by `:y`.
+pub fn compile<'src>(
+    trees: Vec<TokenTree<'src>>,
+    pattern: bool,
+) -> Result<Vec<MacroPart<'src>>, MacroDiagnostic> {
+    let mut trees = trees.into_iter().peekable();
+    let mut parts = Vec::new();
+
+    while let Some(tree) = trees.next() {
+        let dollar = match tree {
+            TokenTree::Leaf(info) if info.token == Token::Punctuation(Punctuation::Dollar) => info,
+            TokenTree::Leaf(info) => {
+                parts.push(MacroPart::Token(info));
+                continue;
+            }
+            TokenTree::Group {
+                delimiter,
+                open,
+                trees: inner,
+                close,
+            } => {
+                parts.push(MacroPart::Group {
+                    delimiter,
+                    open,
+                    parts: compile(inner, pattern)?,
+                    close,
+                });
+                continue;
+            }
+        };
+
+        match trees.next() {
+            Some(TokenTree::Leaf(TokenInfo {
+                token: Token::Identifier(name),
+                ..
+            })) => {
+                let mut kind = FragmentKind::TokenTree;
+                if pattern
+                    && trees
+                        .peek()
+                        .is_some_and(|tree| tree.is_token(&Token::Punctuation(Punctuation::Colon)))
+                {
+                    trees.next();
+                    let kind_name = match trees.next() {
+                        Some(TokenTree::Leaf(info)) => info,
+                        _ => {
+                            return Err(MacroDiagnostic::at(
+                                MacroError::MissingFragmentKind(name.to_string()),
+                                &dollar,
+                            ));
+                        }
+                    };
+                    kind = FragmentKind::from_name(kind_name.lexeme).ok_or_else(|| {
+                        MacroDiagnostic::at(
+                            MacroError::UnknownFragmentKind(kind_name.lexeme.to_string()),
+                            &kind_name,
+                        )
+                    })?;
+                }
+                parts.push(MacroPart::Fragment { name, kind, dollar });
+            }
+            Some(TokenTree::Group {
+                delimiter: Delimiter::Paren,
+                trees: inner,
+                ..
+            }) => {
+                let inner = compile(inner, pattern)?;
+
+                // An optional separator token comes before the operator
+                let mut separator = None;
+                let kleene = loop {
+                    match trees.next() {
+                        Some(TokenTree::Leaf(info)) => match Kleene::from_token(&info.token) {
+                            Some(kleene) => break kleene,
+                            None if separator.is_none() => separator = Some(info),
+                            None => {
+                                return Err(MacroDiagnostic::at(
+                                    MacroError::MissingRepetitionOperator,
+                                    &dollar,
+                                ));
+                            }
+                        },
+                        _ => {
+                            return Err(MacroDiagnostic::at(
+                                MacroError::MissingRepetitionOperator,
+                                &dollar,
+                            ));
+                        }
+                    }
+                };
+
+                parts.push(MacroPart::Repeat {
+                    parts: inner,
+                    separator,
+                    kleene,
+                });
+            }
+            _ => {
+                return Err(MacroDiagnostic::at(
+                    MacroError::MissingFragmentName,
+                    &dollar,
+                ));
+            }
+        }
+    }
+
+    Ok(parts)
+}
+
+// Matches a prefix of `trees`, returning how many trees it used
+pub fn match_parts<'src>(
+    parts: &[MacroPart<'src>],
+    trees: &[TokenTree<'src>],
+    stop: Option<&Token>,
+) -> Option<(usize, Bindings<'src>)> {
+    let mut position = 0;
+    let mut bindings = Bindings::new();
+
+    for (index, part) in parts.iter().enumerate() {
+        // Where an `expr` fragment in this part has to end
+        let next_stop = match parts.get(index + 1) {
+            Some(MacroPart::Token(info)) => Some(&info.token),
+            Some(_) => None,
+            None => stop,
+        };
+        let rest = &trees[position..];
+
+        match part {
+            MacroPart::Token(expected) => match rest.first() {
+                Some(tree) if tree.is_token(&expected.token) => position += 1,
+                _ => return None,
+            },
+            MacroPart::Group {
+                delimiter, parts, ..
+            } => match rest.first() {
+                Some(TokenTree::Group {
+                    delimiter: found,
+                    trees: inner,
+                    ..
+                }) if found == delimiter => {
+                    let (used, inner_bindings) = match_parts(parts, inner, None)?;
+                    if used != inner.len() {
+                        return None;
+                    }
+                    bindings.extend(inner_bindings);
+                    position += 1;
+                }
+                _ => return None,
+            },
+            MacroPart::Fragment { name, kind, .. } => {
+                let used = kind.match_len(rest, next_stop)?;
+                bindings.insert(*name, Binding::Single(rest[..used].to_vec()));
+                position += used;
+            }
+            MacroPart::Repeat {
+                parts,
+                separator,
+                kleene,
+            } => {
+                let inner_stop = separator.as_ref().map(|info| &info.token).or(next_stop);
+                let mut iterations = Vec::new();
+
+                loop {
+                    // The token after the repetition ends it, even if the
+                    // repeated parts would accept it
+                    if next_stop.is_some_and(|stop| {
+                        trees.get(position).is_some_and(|tree| tree.is_token(stop))
+                    }) {
+                        break;
+                    }
+                    let mut start = position;
+                    if let Some(separator) = separator.as_ref().filter(|_| !iterations.is_empty()) {
+                        match trees.get(start) {
+                            Some(tree) if tree.is_token(&separator.token) => start += 1,
+                            _ => break,
+                        }
+                    }
+
+                    match match_parts(parts, &trees[start..], inner_stop) {
+                        Some((used, iteration)) if used > 0 => {
+                            iterations.push(iteration);
+                            position = start + used;
+                        }
+                        _ => break,
+                    }
+                    if *kleene == Kleene::ZeroOrOne {
+                        break;
+                    }
+                }
+
+                if *kleene == Kleene::OneOrMore && iterations.is_empty() {
+                    return None;
+                }
+                for name in fragment_names(parts) {
+                    let entries = iterations
+                        .iter()
+                        .map(|iteration| {
+                            iteration
+                                .get(&name)
+                                .cloned()
+                                .unwrap_or(Binding::Repeated(Vec::new()))
+                        })
+                        .collect();
+                    bindings.insert(name, Binding::Repeated(entries));
+                }
+            }
+        }
+    }
+
+    Some((position, bindings))
+}
+
+// Every fragment name used in `parts`, including inside groups and repetitions
+pub fn fragment_names(parts: &[MacroPart]) -> Vec<Symbol> {
+    let mut names = Vec::new();
+    for part in parts {
+        match part {
+            MacroPart::Token(_) => {}
+            MacroPart::Group { parts, .. } | MacroPart::Repeat { parts, .. } => {
+                names.extend(fragment_names(parts))
+            }
+            MacroPart::Fragment { name, .. } => names.push(*name),
+        }
+    }
+    names
+}
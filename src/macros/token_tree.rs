@@ -0,0 +1,113 @@
+use crate::lexer::token::{Punctuation, Token, TokenInfo};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Delimiter {
+    Paren,   // ( )
+    Bracket, // [ ]
+    Brace,   // { }
+}
+
+impl Delimiter {
+    pub fn opened_by(token: &Token) -> Option<Delimiter> {
+        match token {
+            Token::Punctuation(Punctuation::OpenParen) => Some(Delimiter::Paren),
+            Token::Punctuation(Punctuation::OpenBracket) => Some(Delimiter::Bracket),
+            Token::Punctuation(Punctuation::OpenBrace) => Some(Delimiter::Brace),
+            _ => None,
+        }
+    }
+
+    pub fn closed_by(token: &Token) -> Option<Delimiter> {
+        match token {
+            Token::Punctuation(Punctuation::CloseParen) => Some(Delimiter::Paren),
+            Token::Punctuation(Punctuation::CloseBracket) => Some(Delimiter::Bracket),
+            Token::Punctuation(Punctuation::CloseBrace) => Some(Delimiter::Brace),
+            _ => None,
+        }
+    }
+}
+
+// A single token, or a delimited group of trees
+#[derive(Debug, Clone)]
+pub enum TokenTree<'src> {
+    Leaf(TokenInfo<'src>),
+    Group {
+        delimiter: Delimiter,
+        open: TokenInfo<'src>,
+        trees: Vec<TokenTree<'src>>,
+        close: TokenInfo<'src>,
+    },
+}
+
+impl<'src> TokenTree<'src> {
+    // The leaf itself, or the opening delimiter of a group
+    pub fn first_token(&self) -> &TokenInfo<'src> {
+        match self {
+            TokenTree::Leaf(token) => token,
+            TokenTree::Group { open, .. } => open,
+        }
+    }
+
+    pub fn is_token(&self, token: &Token) -> bool {
+        matches!(self, TokenTree::Leaf(info) if &info.token == token)
+    }
+
+    pub fn flatten_into(self, tokens: &mut Vec<TokenInfo<'src>>) {
+        match self {
+            TokenTree::Leaf(token) => tokens.push(token),
+            TokenTree::Group {
+                open, trees, close, ..
+            } => {
+                tokens.push(open);
+                for tree in trees {
+                    tree.flatten_into(tokens);
+                }
+                tokens.push(close);
+            }
+        }
+    }
+}
+
+// Groups tokens by their delimiters. On failure returns the first delimiter
+// that is not matched.
+pub fn parse_trees<'src>(
+    tokens: Vec<TokenInfo<'src>>,
+) -> Result<Vec<TokenTree<'src>>, TokenInfo<'src>> {
+    // Open groups, innermost last, with the trees read so far inside each
+    let mut stack: Vec<(Delimiter, TokenInfo<'src>, Vec<TokenTree<'src>>)> = Vec::new();
+    let mut trees = Vec::new();
+
+    for token in tokens {
+        if let Some(delimiter) = Delimiter::opened_by(&token.token) {
+            stack.push((delimiter, token, std::mem::take(&mut trees)));
+        } else if let Some(delimiter) = Delimiter::closed_by(&token.token) {
+            match stack.pop() {
+                Some((open_delimiter, open, outer)) if open_delimiter == delimiter => {
+                    let group = TokenTree::Group {
+                        delimiter,
+                        open,
+                        trees: std::mem::replace(&mut trees, outer),
+                        close: token,
+                    };
+                    trees.push(group);
+                }
+                _ => return Err(token),
+            }
+        } else {
+            trees.push(TokenTree::Leaf(token));
+        }
+    }
+
+    match stack.into_iter().next() {
+        Some((_, open, _)) => Err(open),
+        None => Ok(trees),
+    }
+}
+
+pub fn flatten<'src>(trees: Vec<TokenTree<'src>>) -> Vec<TokenInfo<'src>> {
+    let mut tokens = Vec::new();
+    for tree in trees {
+        tree.flatten_into(&mut tokens);
+    }
+    tokens
+}
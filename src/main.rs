@@ -1,6 +1,13 @@
+mod attributes;
 mod lexer;
+mod macros;
+mod parser;
+mod preprocessor;
+mod source;
+mod syntax;
 #[cfg(test)]
 mod tests;
+mod types;
 
 use lexer::Lexer;
 
@@ -0,0 +1,331 @@
+use crate::lexer::token::Operation;
+
+/// Source location of the token a node was built from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Position {
+    pub fn new(line: usize, column: usize) -> Self {
+        Self { line, column }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Program {
+    pub items: Vec<Item>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Item {
+    Function(FunctionDecl),
+    Struct(StructDecl),
+    Enum(EnumDecl),
+    Impl(ImplDecl),
+    Trait(TraitDecl),
+    Use(UseDecl),
+    Statement(Stmt),
+    // An item that failed to parse
+    Error(Position),
+}
+
+// `@name` or `@name(args)` in front of a function, struct, enum, variant, field
+// or statement
+#[derive(Debug, Clone, PartialEq)]
+pub struct Attribute {
+    pub name: String,
+    pub args: Vec<Expr>,
+    pub position: Position,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionDecl {
+    pub attributes: Vec<Attribute>,
+    pub name: String,
+    pub params: Vec<Param>,
+    pub return_type: Option<TypeRef>,
+    // `None` for signatures inside a trait
+    pub body: Option<Block>,
+    pub position: Position,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Param {
+    pub name: String,
+    // `&self` has the type `&Self`
+    pub ty: Option<TypeRef>,
+    pub position: Position,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StructDecl {
+    pub attributes: Vec<Attribute>,
+    pub name: String,
+    // `T` in `struct Vector<T>`
+    pub generics: Vec<GenericParam>,
+    pub fields: Vec<Field>,
+    // Methods written inside the struct body
+    pub methods: Vec<FunctionDecl>,
+    pub position: Position,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GenericParam {
+    pub name: String,
+    pub position: Position,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field {
+    pub attributes: Vec<Attribute>,
+    pub name: String,
+    pub ty: TypeRef,
+    pub position: Position,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnumDecl {
+    pub attributes: Vec<Attribute>,
+    pub name: String,
+    // `T` in `enum Option<T>`
+    pub generics: Vec<GenericParam>,
+    pub variants: Vec<Variant>,
+    pub position: Position,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Variant {
+    pub attributes: Vec<Attribute>,
+    pub name: String,
+    pub kind: VariantKind,
+    pub position: Position,
+}
+
+// What a variant carries
+#[derive(Debug, Clone, PartialEq)]
+pub enum VariantKind {
+    // `None`
+    Unit,
+    // `Some(T)`
+    Tuple(Vec<TypeRef>),
+    // `Rect { w: f64, h: f64 }`
+    Struct(Vec<Field>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImplDecl {
+    // `impl Trait for Target` when present
+    pub trait_name: Option<TypeRef>,
+    pub target: TypeRef,
+    pub methods: Vec<FunctionDecl>,
+    pub position: Position,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraitDecl {
+    pub name: String,
+    pub methods: Vec<FunctionDecl>,
+    pub position: Position,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UseDecl {
+    pub path: Vec<String>,
+    pub position: Position,
+}
+
+// `int`, `Vector<T>` or `&Self`
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeRef {
+    pub name: String,
+    pub args: Vec<TypeRef>,
+    pub reference: bool,
+    pub position: Position,
+}
+
+impl TypeRef {
+    // A type without arguments, written without `&`
+    pub fn named(name: &str, position: Position) -> Self {
+        Self {
+            name: name.to_string(),
+            args: Vec::new(),
+            reference: false,
+            position,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Block {
+    pub statements: Vec<Stmt>,
+    pub position: Position,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stmt {
+    pub attributes: Vec<Attribute>,
+    pub kind: StmtKind,
+    pub position: Position,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StmtKind {
+    Let {
+        name: String,
+        ty: Option<TypeRef>,
+        value: Option<Expr>,
+    },
+    Expr(Expr),
+    Print(Expr),
+    Return(Option<Expr>),
+    If {
+        condition: Expr,
+        then_branch: Block,
+        // Either another `If` statement or a `Block`
+        else_branch: Option<Box<Stmt>>,
+    },
+    While {
+        condition: Expr,
+        body: Block,
+    },
+    For {
+        variable: String,
+        iterable: Expr,
+        body: Block,
+    },
+    Block(Block),
+    Break,
+    Continue,
+    // A statement that failed to parse
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub position: Position,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExprKind {
+    Literal(Literal),
+    // `"a ${x} b"`
+    Interpolation(Vec<StringPart>),
+    Identifier(String),
+    // `!x`, `-x`, `~x`, `++x` and `--x`
+    Unary {
+        op: Operation,
+        operand: Box<Expr>,
+    },
+    Binary {
+        op: Operation,
+        left: Box<Expr>,
+        right: Box<Expr>,
+    },
+    Assign {
+        target: Box<Expr>,
+        value: Box<Expr>,
+    },
+    // `a += b` and the other operators that assign their result
+    CompoundAssign {
+        op: Operation,
+        target: Box<Expr>,
+        value: Box<Expr>,
+    },
+    // `x++` and `x--`
+    Postfix {
+        op: Operation,
+        operand: Box<Expr>,
+    },
+    // `condition ? then_branch : else_branch`
+    Ternary {
+        condition: Box<Expr>,
+        then_branch: Box<Expr>,
+        else_branch: Box<Expr>,
+    },
+    Call {
+        callee: Box<Expr>,
+        args: Vec<Expr>,
+    },
+    Index {
+        object: Box<Expr>,
+        index: Box<Expr>,
+    },
+    Member {
+        object: Box<Expr>,
+        field: String,
+    },
+    // `Shape::Circle`
+    Path(Vec<String>),
+    // `Point { x: 1, y: 2 }` or `Shape::Rect { w: 1.0, h: 2.0 }`
+    StructLiteral {
+        path: Vec<String>,
+        fields: Vec<(String, Expr)>,
+    },
+    Match {
+        scrutinee: Box<Expr>,
+        arms: Vec<MatchArm>,
+    },
+    // `{ ... }` as the body of a match arm
+    Block(Block),
+}
+
+// `pattern if guard => body`
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub guard: Option<Expr>,
+    pub body: Expr,
+    pub position: Position,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pattern {
+    pub kind: PatternKind,
+    pub position: Position,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PatternKind {
+    // `_`
+    Wildcard,
+    // A bare name, which binds the value unless it names a unit variant
+    Binding(String),
+    Literal(Literal),
+    // `Shape::Circle(r)`, `Some(x)`, `Rect { w, .. }` or `Color::Red`
+    Variant {
+        path: Vec<String>,
+        fields: PatternFields,
+    },
+    // `A | B`
+    Or(Vec<Pattern>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PatternFields {
+    Unit,
+    Tuple(Vec<Pattern>),
+    // `rest` is set when the pattern ends with `..`
+    Struct {
+        fields: Vec<(String, Pattern)>,
+        rest: bool,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StringPart {
+    Text(String),
+    Expr(Expr),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Literal {
+    Integer(u128),
+    Float(f64),
+    String(String),
+    Char(char),
+    Bool(bool),
+    Null,
+}
@@ -0,0 +1,64 @@
+use std::fmt;
+use std::rc::Rc;
+
+use crate::lexer::token::Token;
+use crate::parser::ast::Position;
+use crate::source::expansion::Expansion;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseErrorKind {
+    UnexpectedToken { expected: String, found: Token },
+    UnexpectedEof { expected: String },
+    InvalidAssignmentTarget,
+    // Attributes are only allowed on functions, structs, fields and statements
+    MisplacedAttribute(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    pub position: Position,
+    // Set when the offending token came out of a macro expansion
+    pub expansion: Option<Rc<Expansion>>,
+}
+
+impl ParseError {
+    pub fn new(kind: ParseErrorKind, position: Position) -> Self {
+        Self {
+            kind,
+            position,
+            expansion: None,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: ", self.position.line, self.position.column)?;
+        match &self.kind {
+            ParseErrorKind::UnexpectedToken { expected, found } => {
+                write!(f, "expected {}, found {:?}", expected, found)
+            }
+            ParseErrorKind::UnexpectedEof { expected } => {
+                write!(f, "expected {}, found end of file", expected)
+            }
+            ParseErrorKind::InvalidAssignmentTarget => write!(f, "invalid assignment target"),
+            ParseErrorKind::MisplacedAttribute(name) => {
+                write!(f, "attribute @{} cannot be used here", name)
+            }
+        }?;
+
+        if let Some(expansion) = &self.expansion {
+            for outer in expansion.backtrace() {
+                write!(
+                    f,
+                    "\n  in expansion of macro {} at {}:{}",
+                    outer.name, outer.line, outer.column
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ParseError {}
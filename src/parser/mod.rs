@@ -0,0 +1,13 @@
+#![allow(dead_code, unused, unused_imports)]
+
+pub mod ast;
+pub mod error;
+#[allow(clippy::module_inception)]
+pub mod parser;
+pub mod precedence;
+
+// Re-export main types for easier access
+pub use ast::{Expr, ExprKind, Item, Literal, Position, Program, Stmt, StmtKind};
+pub use error::{ParseError, ParseErrorKind};
+pub use parser::Parser;
+pub use precedence::{Associativity, Fixity, OPERATOR_TABLE, OperatorEntry, Precedence};
@@ -0,0 +1,1217 @@
+use crate::lexer::keywords::Keywords;
+use crate::lexer::token::{Operation, Punctuation, Reserved, Token, TokenInfo};
+use crate::lexer::token_stream::TokenStream;
+use crate::parser::ast::{
+    Attribute, Block, EnumDecl, Expr, ExprKind, Field, FunctionDecl, GenericParam, ImplDecl, Item,
+    Literal, MatchArm, Param, Pattern, PatternFields, PatternKind, Position, Program, Stmt,
+    StmtKind, StringPart, StructDecl, TraitDecl, TypeRef, UseDecl, Variant, VariantKind,
+};
+use crate::parser::error::{ParseError, ParseErrorKind};
+use crate::parser::precedence::{self, Associativity, Fixity, Precedence};
+
+pub struct Parser<'src> {
+    tokens: TokenStream<'src>,
+    keywords: Keywords,
+    // Whether errors are recorded and skipped instead of ending the parse
+    recovering: bool,
+    diagnostics: Vec<ParseError>,
+    // Tokens consumed so far, used to tell whether recovery made progress
+    consumed: usize,
+    // Cleared in conditions and match scrutinees, where a `{` opens the block
+    struct_literals: bool,
+}
+
+impl<'src> Parser<'src> {
+    // Accepts a token vector or a `Lexer` directly; tokens are pulled on demand
+    pub fn new<I>(tokens: I) -> Self
+    where
+        I: IntoIterator<Item = TokenInfo<'src>>,
+        I::IntoIter: 'src,
+    {
+        // Layout and comment tokens carry no meaning for the grammar
+        let tokens = tokens.into_iter().filter(|info| {
+            !matches!(
+                info.token,
+                Token::Whitespace | Token::Newline | Token::Comment { .. }
+            )
+        });
+
+        Self {
+            tokens: TokenStream::new(tokens),
+            keywords: Keywords::default(),
+            recovering: false,
+            diagnostics: Vec::new(),
+            consumed: 0,
+            struct_literals: true,
+        }
+    }
+
+    // Must match the lexer's configuration so contextual keywords are recognised
+    pub fn keywords(mut self, keywords: Keywords) -> Self {
+        self.keywords = keywords;
+        self
+    }
+
+    // Stops at the first error without reading any further input
+    pub fn parse(&mut self) -> Result<Program, ParseError> {
+        let mut items = Vec::new();
+
+        while !self.is_at_end() {
+            items.push(self.item()?);
+        }
+
+        Ok(Program { items })
+    }
+
+    // Parses the whole input, collecting every error. A statement or item
+    // that fails to parse becomes an `Error` node, and parsing resumes after
+    // the next `;` or `}`, or at the next `fn`, `struct` or `enum`.
+    pub fn parse_with_diagnostics(&mut self) -> (Program, Vec<ParseError>) {
+        self.recovering = true;
+        let mut items = Vec::new();
+
+        while !self.is_at_end() {
+            let start = self.consumed;
+            let position = self.position();
+            match self.item() {
+                Ok(item) => items.push(item),
+                Err(error) => {
+                    // Outside of any block a `}` can only be left over
+                    if self.recover(error, start) {
+                        self.advance();
+                    }
+                    items.push(Item::Error(position));
+                }
+            }
+        }
+
+        self.recovering = false;
+        (Program { items }, std::mem::take(&mut self.diagnostics))
+    }
+
+    pub fn diagnostics(&self) -> &[ParseError] {
+        &self.diagnostics
+    }
+
+    // Items
+
+    fn item(&mut self) -> Result<Item, ParseError> {
+        let attributes = self.attributes()?;
+
+        match self.keyword() {
+            Some(Reserved::Fn) => Ok(Item::Function(self.function(attributes, true)?)),
+            Some(Reserved::Struct) => Ok(Item::Struct(self.struct_decl(attributes)?)),
+            Some(Reserved::Enum) => Ok(Item::Enum(self.enum_decl(attributes)?)),
+            Some(Reserved::Impl | Reserved::Trait | Reserved::Use) if !attributes.is_empty() => {
+                Err(ParseError::new(
+                    ParseErrorKind::MisplacedAttribute(attributes[0].name.clone()),
+                    attributes[0].position,
+                ))
+            }
+            Some(Reserved::Impl) => Ok(Item::Impl(self.impl_decl()?)),
+            Some(Reserved::Trait) => Ok(Item::Trait(self.trait_decl()?)),
+            Some(Reserved::Use) => Ok(Item::Use(self.use_decl()?)),
+            _ => Ok(Item::Statement(self.attributed_statement(attributes)?)),
+        }
+    }
+
+    // Any number of `@name` or `@name(args)`
+    fn attributes(&mut self) -> Result<Vec<Attribute>, ParseError> {
+        let mut attributes = Vec::new();
+
+        while self.check(&Token::Punctuation(Punctuation::At)) {
+            let position = self.position();
+            self.advance();
+            let (name, _) = self.expect_identifier("attribute name")?;
+
+            let mut args = Vec::new();
+            if self.matches(&Token::Punctuation(Punctuation::OpenParen)) {
+                if !self.check(&Token::Punctuation(Punctuation::CloseParen)) {
+                    loop {
+                        args.push(self.expression()?);
+                        if !self.matches(&Token::Punctuation(Punctuation::Comma)) {
+                            break;
+                        }
+                    }
+                }
+                self.expect(&Token::Punctuation(Punctuation::CloseParen), "')'")?;
+            }
+
+            attributes.push(Attribute {
+                name,
+                args,
+                position,
+            });
+        }
+
+        Ok(attributes)
+    }
+
+    fn function(
+        &mut self,
+        attributes: Vec<Attribute>,
+        require_body: bool,
+    ) -> Result<FunctionDecl, ParseError> {
+        let position = self.expect(&Token::Reserved(Reserved::Fn), "'fn'")?;
+        let (name, _) = self.expect_identifier("function name")?;
+
+        self.expect(&Token::Punctuation(Punctuation::OpenParen), "'('")?;
+        let mut params = Vec::new();
+        if !self.check(&Token::Punctuation(Punctuation::CloseParen)) {
+            loop {
+                params.push(self.param()?);
+
+                if !self.matches(&Token::Punctuation(Punctuation::Comma)) {
+                    break;
+                }
+            }
+        }
+        self.expect(&Token::Punctuation(Punctuation::CloseParen), "')'")?;
+
+        let return_type = if self.matches(&Token::Operation(Operation::Arrow)) {
+            Some(self.type_ref()?)
+        } else {
+            None
+        };
+
+        // Trait methods may omit the body and end with ';'
+        let body = if !require_body && self.matches(&Token::Punctuation(Punctuation::Semicolon)) {
+            None
+        } else {
+            Some(self.block()?)
+        };
+
+        Ok(FunctionDecl {
+            attributes,
+            name,
+            params,
+            return_type,
+            body,
+            position,
+        })
+    }
+
+    fn param(&mut self) -> Result<Param, ParseError> {
+        let position = self.position();
+
+        if self.check(&Token::Operation(Operation::BitAnd))
+            && matches!(self.peek_ahead(1), Token::Identifier(name) if name.as_str() == "self")
+        {
+            self.advance();
+            self.advance();
+            let mut ty = TypeRef::named("Self", position);
+            ty.reference = true;
+            return Ok(Param {
+                name: "self".to_string(),
+                ty: Some(ty),
+                position,
+            });
+        }
+
+        let (name, position) = self.expect_identifier("parameter name")?;
+        let ty = if self.matches(&Token::Punctuation(Punctuation::Colon)) {
+            Some(self.type_ref()?)
+        } else {
+            None
+        };
+        Ok(Param { name, ty, position })
+    }
+
+    // `struct Name<T> { field: T, fn method(&self) { ... } }`. Fields are
+    // separated by commas; a method may follow a field without one.
+    fn struct_decl(&mut self, attributes: Vec<Attribute>) -> Result<StructDecl, ParseError> {
+        let position = self.expect(&Token::Reserved(Reserved::Struct), "'struct'")?;
+        let (name, _) = self.expect_identifier("struct name")?;
+        let generics = self.generic_params()?;
+
+        self.expect(&Token::Punctuation(Punctuation::OpenBrace), "'{'")?;
+        let mut fields = Vec::new();
+        let mut methods = Vec::new();
+        while !self.check(&Token::Punctuation(Punctuation::CloseBrace)) && !self.is_at_end() {
+            let attributes = self.attributes()?;
+            if self.check(&Token::Reserved(Reserved::Fn)) {
+                methods.push(self.function(attributes, true)?);
+                continue;
+            }
+
+            let (name, position) = self.expect_identifier("field name")?;
+            self.expect(&Token::Punctuation(Punctuation::Colon), "':'")?;
+            let ty = self.type_ref()?;
+            fields.push(Field {
+                attributes,
+                name,
+                ty,
+                position,
+            });
+
+            if !self.matches(&Token::Punctuation(Punctuation::Comma))
+                && !self.check(&Token::Reserved(Reserved::Fn))
+            {
+                break;
+            }
+        }
+        self.expect(&Token::Punctuation(Punctuation::CloseBrace), "'}'")?;
+
+        Ok(StructDecl {
+            attributes,
+            name,
+            generics,
+            fields,
+            methods,
+            position,
+        })
+    }
+
+    // `<T, U>` after a declared name, if present
+    fn generic_params(&mut self) -> Result<Vec<GenericParam>, ParseError> {
+        let mut generics = Vec::new();
+        if !self.matches(&Token::Operation(Operation::Less)) {
+            return Ok(generics);
+        }
+
+        loop {
+            let (name, position) = self.expect_identifier("type parameter")?;
+            generics.push(GenericParam { name, position });
+            if !self.matches(&Token::Punctuation(Punctuation::Comma)) {
+                break;
+            }
+        }
+        self.expect_closing_angle()?;
+        Ok(generics)
+    }
+
+    // `enum Name<T> { Unit, Tuple(T, int), Struct { field: T } }`
+    fn enum_decl(&mut self, attributes: Vec<Attribute>) -> Result<EnumDecl, ParseError> {
+        let position = self.expect(&Token::Reserved(Reserved::Enum), "'enum'")?;
+        let (name, _) = self.expect_identifier("enum name")?;
+        let generics = self.generic_params()?;
+
+        self.expect(&Token::Punctuation(Punctuation::OpenBrace), "'{'")?;
+        let mut variants = Vec::new();
+        while !self.check(&Token::Punctuation(Punctuation::CloseBrace)) {
+            let variant_attributes = self.attributes()?;
+            let (name, position) = self.expect_identifier("variant name")?;
+
+            let kind = if self.matches(&Token::Punctuation(Punctuation::OpenParen)) {
+                let mut types = Vec::new();
+                while !self.check(&Token::Punctuation(Punctuation::CloseParen)) {
+                    types.push(self.type_ref()?);
+                    if !self.matches(&Token::Punctuation(Punctuation::Comma)) {
+                        break;
+                    }
+                }
+                self.expect(&Token::Punctuation(Punctuation::CloseParen), "')'")?;
+                VariantKind::Tuple(types)
+            } else if self.matches(&Token::Punctuation(Punctuation::OpenBrace)) {
+                let mut fields = Vec::new();
+                while !self.check(&Token::Punctuation(Punctuation::CloseBrace)) {
+                    let attributes = self.attributes()?;
+                    let (name, position) = self.expect_identifier("field name")?;
+                    self.expect(&Token::Punctuation(Punctuation::Colon), "':'")?;
+                    let ty = self.type_ref()?;
+                    fields.push(Field {
+                        attributes,
+                        name,
+                        ty,
+                        position,
+                    });
+                    if !self.matches(&Token::Punctuation(Punctuation::Comma)) {
+                        break;
+                    }
+                }
+                self.expect(&Token::Punctuation(Punctuation::CloseBrace), "'}'")?;
+                VariantKind::Struct(fields)
+            } else {
+                VariantKind::Unit
+            };
+            variants.push(Variant {
+                attributes: variant_attributes,
+                name,
+                kind,
+                position,
+            });
+
+            if !self.matches(&Token::Punctuation(Punctuation::Comma)) {
+                break;
+            }
+        }
+        self.expect(&Token::Punctuation(Punctuation::CloseBrace), "'}'")?;
+
+        Ok(EnumDecl {
+            attributes,
+            name,
+            generics,
+            variants,
+            position,
+        })
+    }
+
+    fn impl_decl(&mut self) -> Result<ImplDecl, ParseError> {
+        let position = self.expect(&Token::Reserved(Reserved::Impl), "'impl'")?;
+        let first = self.type_ref()?;
+
+        let (trait_name, target) = if self.matches(&Token::Reserved(Reserved::For)) {
+            (Some(first), self.type_ref()?)
+        } else {
+            (None, first)
+        };
+
+        self.expect(&Token::Punctuation(Punctuation::OpenBrace), "'{'")?;
+        let mut methods = Vec::new();
+        while !self.check(&Token::Punctuation(Punctuation::CloseBrace)) && !self.is_at_end() {
+            let attributes = self.attributes()?;
+            methods.push(self.function(attributes, true)?);
+        }
+        self.expect(&Token::Punctuation(Punctuation::CloseBrace), "'}'")?;
+
+        Ok(ImplDecl {
+            trait_name,
+            target,
+            methods,
+            position,
+        })
+    }
+
+    fn trait_decl(&mut self) -> Result<TraitDecl, ParseError> {
+        let position = self.expect(&Token::Reserved(Reserved::Trait), "'trait'")?;
+        let (name, _) = self.expect_identifier("trait name")?;
+
+        self.expect(&Token::Punctuation(Punctuation::OpenBrace), "'{'")?;
+        let mut methods = Vec::new();
+        while !self.check(&Token::Punctuation(Punctuation::CloseBrace)) && !self.is_at_end() {
+            let attributes = self.attributes()?;
+            methods.push(self.function(attributes, false)?);
+        }
+        self.expect(&Token::Punctuation(Punctuation::CloseBrace), "'}'")?;
+
+        Ok(TraitDecl {
+            name,
+            methods,
+            position,
+        })
+    }
+
+    fn use_decl(&mut self) -> Result<UseDecl, ParseError> {
+        let position = self.expect(&Token::Reserved(Reserved::Use), "'use'")?;
+
+        let mut path = vec![self.expect_identifier("module path")?.0];
+        while self.matches(&Token::Operation(Operation::PathSeparator)) {
+            path.push(self.expect_identifier("module path")?.0);
+        }
+        self.expect(&Token::Punctuation(Punctuation::Semicolon), "';'")?;
+
+        Ok(UseDecl { path, position })
+    }
+
+    fn type_ref(&mut self) -> Result<TypeRef, ParseError> {
+        let position = self.position();
+        let reference = self.matches(&Token::Operation(Operation::BitAnd));
+        let (name, _) = self.expect_identifier("type name")?;
+
+        // Types contain no comparisons, so `<` always opens type arguments
+        let mut args = Vec::new();
+        if self.matches(&Token::Operation(Operation::Less)) {
+            loop {
+                args.push(self.type_ref()?);
+                if !self.matches(&Token::Punctuation(Punctuation::Comma)) {
+                    break;
+                }
+            }
+            self.expect_closing_angle()?;
+        }
+
+        Ok(TypeRef {
+            name,
+            args,
+            reference,
+            position,
+        })
+    }
+
+    // The lexer reads `>>` in `Vector<Vector<T>>` as a shift and `>=` in
+    // `let v: Vector<T>= w` as a comparison, so only their first `>` is taken
+    fn expect_closing_angle(&mut self) -> Result<(), ParseError> {
+        let rest = match self.peek() {
+            Token::Operation(Operation::Greater) => {
+                self.advance();
+                return Ok(());
+            }
+            Token::Operation(Operation::ShiftRight) => Operation::Greater,
+            Token::Operation(Operation::GreaterEqual) => Operation::Assign,
+            _ => return Err(self.unexpected("'>'")),
+        };
+
+        self.tokens.split_front(1, Token::Operation(rest));
+        self.consumed += 1;
+        Ok(())
+    }
+
+    // Statements
+
+    fn statement(&mut self) -> Result<Stmt, ParseError> {
+        let attributes = self.attributes()?;
+        self.attributed_statement(attributes)
+    }
+
+    fn attributed_statement(&mut self, attributes: Vec<Attribute>) -> Result<Stmt, ParseError> {
+        let position = self.position();
+
+        let kind = match self.keyword() {
+            Some(Reserved::Let) => self.let_statement()?,
+            Some(Reserved::Print) => {
+                self.advance();
+                let value = self.expression()?;
+                self.expect(&Token::Punctuation(Punctuation::Semicolon), "';'")?;
+                StmtKind::Print(value)
+            }
+            Some(Reserved::Return) => {
+                self.advance();
+                let value = if self.check(&Token::Punctuation(Punctuation::Semicolon)) {
+                    None
+                } else {
+                    Some(self.expression()?)
+                };
+                self.expect(&Token::Punctuation(Punctuation::Semicolon), "';'")?;
+                StmtKind::Return(value)
+            }
+            Some(Reserved::If) => self.if_statement()?,
+            Some(Reserved::While) => {
+                self.advance();
+                let condition = self.expression_allowing(false)?;
+                let body = self.block()?;
+                StmtKind::While { condition, body }
+            }
+            Some(Reserved::For) => self.for_statement()?,
+            Some(Reserved::Break) => {
+                self.advance();
+                self.expect(&Token::Punctuation(Punctuation::Semicolon), "';'")?;
+                StmtKind::Break
+            }
+            Some(Reserved::Continue) => {
+                self.advance();
+                self.expect(&Token::Punctuation(Punctuation::Semicolon), "';'")?;
+                StmtKind::Continue
+            }
+            _ if self.check(&Token::Punctuation(Punctuation::OpenBrace)) => {
+                StmtKind::Block(self.block()?)
+            }
+            _ => {
+                let expr = self.expression()?;
+                // The last expression of a block may leave out its `;`, and
+                // so may a `match`, which ends with a `}` of its own
+                if matches!(expr.kind, ExprKind::Match { .. }) {
+                    self.matches(&Token::Punctuation(Punctuation::Semicolon));
+                } else if !self.check(&Token::Punctuation(Punctuation::CloseBrace)) {
+                    self.expect(&Token::Punctuation(Punctuation::Semicolon), "';'")?;
+                }
+                StmtKind::Expr(expr)
+            }
+        };
+
+        Ok(Stmt {
+            attributes,
+            kind,
+            position,
+        })
+    }
+
+    fn let_statement(&mut self) -> Result<StmtKind, ParseError> {
+        self.expect(&Token::Reserved(Reserved::Let), "'let'")?;
+        let (name, _) = self.expect_identifier("variable name")?;
+
+        let ty = if self.matches(&Token::Punctuation(Punctuation::Colon)) {
+            Some(self.type_ref()?)
+        } else {
+            None
+        };
+
+        let value = if self.matches(&Token::Operation(Operation::Assign)) {
+            Some(self.expression()?)
+        } else {
+            None
+        };
+
+        self.expect(&Token::Punctuation(Punctuation::Semicolon), "';'")?;
+        Ok(StmtKind::Let { name, ty, value })
+    }
+
+    fn if_statement(&mut self) -> Result<StmtKind, ParseError> {
+        self.expect(&Token::Reserved(Reserved::If), "'if'")?;
+        let condition = self.expression_allowing(false)?;
+        let then_branch = self.block()?;
+
+        let else_branch = if self.matches(&Token::Reserved(Reserved::Else)) {
+            let position = self.position();
+            let kind = if self.check(&Token::Reserved(Reserved::If)) {
+                self.if_statement()?
+            } else {
+                StmtKind::Block(self.block()?)
+            };
+            Some(Box::new(Stmt {
+                attributes: Vec::new(),
+                kind,
+                position,
+            }))
+        } else {
+            None
+        };
+
+        Ok(StmtKind::If {
+            condition,
+            then_branch,
+            else_branch,
+        })
+    }
+
+    fn for_statement(&mut self) -> Result<StmtKind, ParseError> {
+        self.expect(&Token::Reserved(Reserved::For), "'for'")?;
+        let (variable, _) = self.expect_identifier("loop variable")?;
+
+        self.expect(&Token::Reserved(Reserved::In), "'in'")?;
+
+        let iterable = self.expression_allowing(false)?;
+        let body = self.block()?;
+
+        Ok(StmtKind::For {
+            variable,
+            iterable,
+            body,
+        })
+    }
+
+    fn block(&mut self) -> Result<Block, ParseError> {
+        let position = self.expect(&Token::Punctuation(Punctuation::OpenBrace), "'{'")?;
+
+        let mut statements = Vec::new();
+        while !self.check(&Token::Punctuation(Punctuation::CloseBrace)) && !self.is_at_end() {
+            let start = self.consumed;
+            let position = self.position();
+            match self.statement() {
+                Ok(stmt) => statements.push(stmt),
+                Err(error) if self.recovering => {
+                    self.recover(error, start);
+                    statements.push(Stmt {
+                        attributes: Vec::new(),
+                        kind: StmtKind::Error,
+                        position,
+                    });
+                }
+                Err(error) => return Err(error),
+            }
+        }
+        self.expect(&Token::Punctuation(Punctuation::CloseBrace), "'}'")?;
+
+        Ok(Block {
+            statements,
+            position,
+        })
+    }
+
+    // Expressions are parsed by precedence climbing; binding strength and
+    // associativity of every operator come from `precedence::OPERATOR_TABLE`
+
+    fn expression(&mut self) -> Result<Expr, ParseError> {
+        self.expression_with(Precedence::Lowest)
+    }
+
+    // Parses an expression with struct literals allowed or not. Delimiters
+    // allow them again inside a condition, as in `if f(Shape::Rect { .. }) {`.
+    fn expression_allowing(&mut self, struct_literals: bool) -> Result<Expr, ParseError> {
+        let allowed = std::mem::replace(&mut self.struct_literals, struct_literals);
+        let expr = self.expression();
+        self.struct_literals = allowed;
+        expr
+    }
+
+    // Parses an expression whose operators all bind at least as strongly as `min`
+    fn expression_with(&mut self, min: Precedence) -> Result<Expr, ParseError> {
+        let mut left = self.prefix()?;
+
+        while let Some((precedence, associativity)) = precedence::continuation(self.peek()) {
+            if precedence < min {
+                break;
+            }
+            // `a - b - c` is `(a - b) - c` and `a = b = c` is `a = (b = c)`
+            let right_min = match associativity {
+                Associativity::Left => precedence.next(),
+                Associativity::Right => precedence,
+            };
+            left = self.infix(left, right_min)?;
+        }
+
+        Ok(left)
+    }
+
+    fn prefix(&mut self) -> Result<Expr, ParseError> {
+        if let Token::Operation(op) = self.peek()
+            && let Some((precedence, _)) = precedence::operator(*op, Fixity::Prefix)
+        {
+            let op = *op;
+            let position = self.position();
+            self.advance();
+
+            let operand = self.expression_with(precedence)?;
+            if matches!(op, Operation::Increment | Operation::Decrement) && !is_place(&operand) {
+                return Err(ParseError::new(
+                    ParseErrorKind::InvalidAssignmentTarget,
+                    position,
+                ));
+            }
+            return Ok(Expr {
+                kind: ExprKind::Unary {
+                    op,
+                    operand: Box::new(operand),
+                },
+                position,
+            });
+        }
+
+        self.primary()
+    }
+
+    // Continues `left` with the operator at the current token. `right_min`
+    // bounds the operators the right operand may contain.
+    fn infix(&mut self, left: Expr, right_min: Precedence) -> Result<Expr, ParseError> {
+        let position = left.position;
+        let operator_position = self.position();
+
+        let kind = match self.peek().clone() {
+            Token::Operation(op) if precedence::operator(op, Fixity::Postfix).is_some() => {
+                self.advance();
+                if !is_place(&left) {
+                    return Err(ParseError::new(
+                        ParseErrorKind::InvalidAssignmentTarget,
+                        operator_position,
+                    ));
+                }
+                ExprKind::Postfix {
+                    op,
+                    operand: Box::new(left),
+                }
+            }
+            Token::Operation(op) => {
+                self.advance();
+                let right = self.expression_with(right_min)?;
+
+                if !precedence::is_assignment(op) {
+                    ExprKind::Binary {
+                        op,
+                        left: Box::new(left),
+                        right: Box::new(right),
+                    }
+                } else if !is_place(&left) {
+                    return Err(ParseError::new(
+                        ParseErrorKind::InvalidAssignmentTarget,
+                        operator_position,
+                    ));
+                } else if op == Operation::Assign {
+                    ExprKind::Assign {
+                        target: Box::new(left),
+                        value: Box::new(right),
+                    }
+                } else {
+                    ExprKind::CompoundAssign {
+                        op,
+                        target: Box::new(left),
+                        value: Box::new(right),
+                    }
+                }
+            }
+            Token::Punctuation(Punctuation::QuestionMark) => {
+                self.advance();
+                // Anything may appear between `?` and `:`
+                let then_branch = self.expression()?;
+                self.expect(&Token::Punctuation(Punctuation::Colon), "':'")?;
+                let else_branch = self.expression_with(right_min)?;
+
+                ExprKind::Ternary {
+                    condition: Box::new(left),
+                    then_branch: Box::new(then_branch),
+                    else_branch: Box::new(else_branch),
+                }
+            }
+            Token::Punctuation(Punctuation::OpenParen) => {
+                self.advance();
+                let mut args = Vec::new();
+                if !self.check(&Token::Punctuation(Punctuation::CloseParen)) {
+                    loop {
+                        args.push(self.expression_allowing(true)?);
+                        if !self.matches(&Token::Punctuation(Punctuation::Comma)) {
+                            break;
+                        }
+                    }
+                }
+                self.expect(&Token::Punctuation(Punctuation::CloseParen), "')'")?;
+
+                ExprKind::Call {
+                    callee: Box::new(left),
+                    args,
+                }
+            }
+            Token::Punctuation(Punctuation::OpenBracket) => {
+                self.advance();
+                let index = self.expression_allowing(true)?;
+                self.expect(&Token::Punctuation(Punctuation::CloseBracket), "']'")?;
+
+                ExprKind::Index {
+                    object: Box::new(left),
+                    index: Box::new(index),
+                }
+            }
+            Token::Punctuation(Punctuation::Dot) => {
+                self.advance();
+                let (field, _) = self.expect_identifier("field name")?;
+
+                ExprKind::Member {
+                    object: Box::new(left),
+                    field,
+                }
+            }
+            _ => unreachable!("`precedence::continuation` accepted the token"),
+        };
+
+        Ok(Expr { kind, position })
+    }
+
+    fn primary(&mut self) -> Result<Expr, ParseError> {
+        let position = self.position();
+
+        let kind = match self.peek().clone() {
+            Token::Integer { value, .. } => ExprKind::Literal(Literal::Integer(value)),
+            Token::Float { value, .. } => ExprKind::Literal(Literal::Float(value)),
+            Token::String { value, .. } => ExprKind::Literal(Literal::String(value)),
+            Token::Char(value) => ExprKind::Literal(Literal::Char(value)),
+            Token::Reserved(Reserved::True) => ExprKind::Literal(Literal::Bool(true)),
+            Token::Reserved(Reserved::False) => ExprKind::Literal(Literal::Bool(false)),
+            Token::Reserved(Reserved::Null) => ExprKind::Literal(Literal::Null),
+            Token::Identifier(_) if self.at_match() => return self.match_expression(),
+            Token::Identifier(_)
+                if self.peek_ahead(1) == &Token::Operation(Operation::PathSeparator)
+                    || (self.struct_literals
+                        && self.peek_ahead(1) == &Token::Punctuation(Punctuation::OpenBrace)) =>
+            {
+                return self.path_expression();
+            }
+            Token::Identifier(name) => ExprKind::Identifier(name.to_string()),
+            Token::StringStart => {
+                self.advance();
+                return self.interpolation(position);
+            }
+            Token::Punctuation(Punctuation::OpenParen) => {
+                self.advance();
+                let expr = self.expression_allowing(true)?;
+                self.expect(&Token::Punctuation(Punctuation::CloseParen), "')'")?;
+                return Ok(expr);
+            }
+            _ => return Err(self.unexpected("expression")),
+        };
+
+        self.advance();
+        Ok(Expr { kind, position })
+    }
+
+    // `match` is contextual, so it only starts a match where the name `match`
+    // could not be followed by the next token. `match (x) { .. }` is read as
+    // a call of `match`.
+    fn at_match(&mut self) -> bool {
+        if self.keyword() != Some(Reserved::Match) {
+            return false;
+        }
+        let next = self.peek_ahead(1);
+        precedence::continuation(next).is_none()
+            && !matches!(
+                next,
+                Token::Punctuation(
+                    Punctuation::Semicolon
+                        | Punctuation::Comma
+                        | Punctuation::Colon
+                        | Punctuation::CloseParen
+                        | Punctuation::CloseBracket
+                        | Punctuation::CloseBrace
+                        | Punctuation::OpenBrace
+                ) | Token::Operation(Operation::FatArrow)
+                    | Token::Eof
+            )
+    }
+
+    // `match scrutinee { pattern if guard => body, ... }`. An arm whose body
+    // is a block needs no comma after it.
+    fn match_expression(&mut self) -> Result<Expr, ParseError> {
+        let position = self.position();
+        self.advance();
+        let scrutinee = self.expression_allowing(false)?;
+
+        self.expect(&Token::Punctuation(Punctuation::OpenBrace), "'{'")?;
+        let mut arms = Vec::new();
+        while !self.check(&Token::Punctuation(Punctuation::CloseBrace)) && !self.is_at_end() {
+            let position = self.position();
+            let pattern = self.pattern()?;
+            let guard = if self.matches(&Token::Reserved(Reserved::If)) {
+                Some(self.expression_allowing(true)?)
+            } else {
+                None
+            };
+            self.expect(&Token::Operation(Operation::FatArrow), "'=>'")?;
+
+            let body = if self.check(&Token::Punctuation(Punctuation::OpenBrace)) {
+                let block = self.block()?;
+                Expr {
+                    position: block.position,
+                    kind: ExprKind::Block(block),
+                }
+            } else {
+                self.expression_allowing(true)?
+            };
+            let ends_with_block = matches!(body.kind, ExprKind::Block(_));
+            arms.push(MatchArm {
+                pattern,
+                guard,
+                body,
+                position,
+            });
+
+            if !self.matches(&Token::Punctuation(Punctuation::Comma)) && !ends_with_block {
+                break;
+            }
+        }
+        self.expect(&Token::Punctuation(Punctuation::CloseBrace), "'}'")?;
+
+        Ok(Expr {
+            kind: ExprKind::Match {
+                scrutinee: Box::new(scrutinee),
+                arms,
+            },
+            position,
+        })
+    }
+
+    // `Enum::Variant`, or a struct or struct variant literal where one is
+    // allowed. Literals take no type arguments, which keeps `<` a comparison.
+    fn path_expression(&mut self) -> Result<Expr, ParseError> {
+        let position = self.position();
+        let path = self.path()?;
+
+        if !self.struct_literals || !self.check(&Token::Punctuation(Punctuation::OpenBrace)) {
+            return Ok(Expr {
+                kind: ExprKind::Path(path),
+                position,
+            });
+        }
+
+        self.advance();
+        let mut fields = Vec::new();
+        while !self.check(&Token::Punctuation(Punctuation::CloseBrace)) {
+            let (name, _) = self.expect_identifier("field name")?;
+            self.expect(&Token::Punctuation(Punctuation::Colon), "':'")?;
+            fields.push((name, self.expression_allowing(true)?));
+            if !self.matches(&Token::Punctuation(Punctuation::Comma)) {
+                break;
+            }
+        }
+        self.expect(&Token::Punctuation(Punctuation::CloseBrace), "'}'")?;
+
+        Ok(Expr {
+            kind: ExprKind::StructLiteral { path, fields },
+            position,
+        })
+    }
+
+    // `a::b::c`
+    fn path(&mut self) -> Result<Vec<String>, ParseError> {
+        let mut path = vec![self.expect_identifier("name")?.0];
+        while self.matches(&Token::Operation(Operation::PathSeparator)) {
+            path.push(self.expect_identifier("name")?.0);
+        }
+        Ok(path)
+    }
+
+    // Patterns
+
+    // A pattern with any number of `|` alternatives
+    fn pattern(&mut self) -> Result<Pattern, ParseError> {
+        let first = self.single_pattern()?;
+        if !self.check(&Token::Operation(Operation::BitOr)) {
+            return Ok(first);
+        }
+
+        let position = first.position;
+        let mut alternatives = vec![first];
+        while self.matches(&Token::Operation(Operation::BitOr)) {
+            alternatives.push(self.single_pattern()?);
+        }
+        Ok(Pattern {
+            kind: PatternKind::Or(alternatives),
+            position,
+        })
+    }
+
+    fn single_pattern(&mut self) -> Result<Pattern, ParseError> {
+        let position = self.position();
+
+        let literal = match self.peek().clone() {
+            Token::Identifier(name) if name.as_str() == "_" => {
+                self.advance();
+                return Ok(Pattern {
+                    kind: PatternKind::Wildcard,
+                    position,
+                });
+            }
+            Token::Identifier(_) => return self.variant_pattern(),
+            Token::Integer { value, .. } => Literal::Integer(value),
+            Token::Float { value, .. } => Literal::Float(value),
+            Token::String { value, .. } => Literal::String(value),
+            Token::Char(value) => Literal::Char(value),
+            Token::Reserved(Reserved::True) => Literal::Bool(true),
+            Token::Reserved(Reserved::False) => Literal::Bool(false),
+            Token::Reserved(Reserved::Null) => Literal::Null,
+            _ => return Err(self.unexpected("pattern")),
+        };
+
+        self.advance();
+        Ok(Pattern {
+            kind: PatternKind::Literal(literal),
+            position,
+        })
+    }
+
+    // A path with optional `(patterns)` or `{ field: pattern, .. }`. A bare
+    // name without either is a binding.
+    fn variant_pattern(&mut self) -> Result<Pattern, ParseError> {
+        let position = self.position();
+        let mut path = self.path()?;
+
+        let fields = if self.matches(&Token::Punctuation(Punctuation::OpenParen)) {
+            let mut patterns = Vec::new();
+            while !self.check(&Token::Punctuation(Punctuation::CloseParen)) {
+                patterns.push(self.pattern()?);
+                if !self.matches(&Token::Punctuation(Punctuation::Comma)) {
+                    break;
+                }
+            }
+            self.expect(&Token::Punctuation(Punctuation::CloseParen), "')'")?;
+            PatternFields::Tuple(patterns)
+        } else if self.matches(&Token::Punctuation(Punctuation::OpenBrace)) {
+            let mut fields = Vec::new();
+            let mut rest = false;
+            while !self.check(&Token::Punctuation(Punctuation::CloseBrace)) {
+                if self.check(&Token::Punctuation(Punctuation::Dot)) {
+                    self.advance();
+                    self.expect(&Token::Punctuation(Punctuation::Dot), "'..'")?;
+                    rest = true;
+                    break;
+                }
+
+                let (name, position) = self.expect_identifier("field name")?;
+                // `{ x }` is short for `{ x: x }`
+                let pattern = if self.matches(&Token::Punctuation(Punctuation::Colon)) {
+                    self.pattern()?
+                } else {
+                    Pattern {
+                        kind: PatternKind::Binding(name.clone()),
+                        position,
+                    }
+                };
+                fields.push((name, pattern));
+                if !self.matches(&Token::Punctuation(Punctuation::Comma)) {
+                    break;
+                }
+            }
+            self.expect(&Token::Punctuation(Punctuation::CloseBrace), "'}'")?;
+            PatternFields::Struct { fields, rest }
+        } else if path.len() == 1 {
+            return Ok(Pattern {
+                kind: PatternKind::Binding(path.remove(0)),
+                position,
+            });
+        } else {
+            PatternFields::Unit
+        };
+
+        Ok(Pattern {
+            kind: PatternKind::Variant { path, fields },
+            position,
+        })
+    }
+
+    // Parts of an interpolated string after its `StringStart`
+    fn interpolation(&mut self, position: Position) -> Result<Expr, ParseError> {
+        let mut parts = Vec::new();
+
+        while !self.matches(&Token::StringEnd) {
+            match self.peek().clone() {
+                Token::StringFragment(text) => {
+                    self.advance();
+                    parts.push(StringPart::Text(text));
+                }
+                Token::InterpolationStart => {
+                    self.advance();
+                    parts.push(StringPart::Expr(self.expression()?));
+                    self.expect(&Token::InterpolationEnd, "'}'")?;
+                }
+                _ => return Err(self.unexpected("string")),
+            }
+        }
+
+        Ok(Expr {
+            kind: ExprKind::Interpolation(parts),
+            position,
+        })
+    }
+
+    // Error recovery
+
+    // Records `error` and skips to a point where parsing can resume. `start`
+    // is the consumed count when the failed statement or item began. Returns
+    // whether skipping stopped at a `}` it did not consume.
+    fn recover(&mut self, error: ParseError, start: usize) -> bool {
+        self.diagnostics.push(error);
+
+        // A token that cannot start anything is dropped on its own, or the
+        // same error would repeat
+        if self.consumed == start {
+            let closes = matches!(
+                self.peek(),
+                Token::Punctuation(Punctuation::Semicolon | Punctuation::CloseBrace)
+            );
+            self.advance();
+            if closes {
+                return false;
+            }
+        }
+        self.synchronize()
+    }
+
+    // Skips past the next `;` or `}` of the current nesting level, stopping
+    // early at an item keyword or a `}` that closes the enclosing block
+    fn synchronize(&mut self) -> bool {
+        let mut depth = 0;
+
+        while !self.is_at_end() {
+            if matches!(
+                self.keyword(),
+                Some(Reserved::Fn | Reserved::Struct | Reserved::Enum)
+            ) {
+                return false;
+            }
+
+            match self.peek() {
+                Token::Punctuation(Punctuation::Semicolon) if depth == 0 => {
+                    self.advance();
+                    return false;
+                }
+                Token::Punctuation(Punctuation::OpenBrace) => depth += 1,
+                Token::Punctuation(Punctuation::CloseBrace) if depth == 0 => return true,
+                Token::Punctuation(Punctuation::CloseBrace) => {
+                    depth -= 1;
+                    if depth == 0 {
+                        self.advance();
+                        return false;
+                    }
+                }
+                _ => {}
+            }
+            self.advance();
+        }
+        false
+    }
+
+    // Token helpers
+
+    fn expect(&mut self, token: &Token, expected: &str) -> Result<Position, ParseError> {
+        if self.check(token) {
+            let position = self.position();
+            self.advance();
+            Ok(position)
+        } else {
+            Err(self.unexpected(expected))
+        }
+    }
+
+    fn expect_identifier(&mut self, expected: &str) -> Result<(String, Position), ParseError> {
+        if let Token::Identifier(name) = self.peek() {
+            let name = name.to_string();
+            let position = self.position();
+            self.advance();
+            Ok((name, position))
+        } else {
+            Err(self.unexpected(expected))
+        }
+    }
+
+    fn unexpected(&mut self, expected: &str) -> ParseError {
+        let expected = expected.to_string();
+        let position = self.position();
+        let info = self.tokens.peek();
+        let kind = match &info.token {
+            Token::Eof => ParseErrorKind::UnexpectedEof { expected },
+            found => ParseErrorKind::UnexpectedToken {
+                expected,
+                found: found.clone(),
+            },
+        };
+
+        ParseError {
+            kind,
+            position,
+            expansion: info.expansion.clone(),
+        }
+    }
+
+    fn matches(&mut self, token: &Token) -> bool {
+        if self.check(token) {
+            self.advance();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn check(&mut self, token: &Token) -> bool {
+        match token {
+            Token::Reserved(keyword) => self.keyword() == Some(*keyword),
+            _ => self.peek() == token,
+        }
+    }
+
+    // Keyword spelled by the next token, including contextual keywords that
+    // arrive as identifiers
+    fn keyword(&mut self) -> Option<Reserved> {
+        match *self.peek() {
+            Token::Reserved(keyword) => Some(keyword),
+            Token::Identifier(name) => self.keywords.contextual_keyword(name.as_str()),
+            _ => None,
+        }
+    }
+
+    fn position(&mut self) -> Position {
+        let info = self.tokens.peek();
+        Position::new(info.line, info.column)
+    }
+
+    fn peek(&mut self) -> &Token {
+        &self.tokens.peek().token
+    }
+
+    fn peek_ahead(&mut self, offset: usize) -> &Token {
+        &self.tokens.peek_nth(offset).token
+    }
+
+    fn advance(&mut self) {
+        if !self.is_at_end() {
+            self.tokens.next();
+            self.consumed += 1;
+        }
+    }
+
+    fn is_at_end(&mut self) -> bool {
+        self.tokens.is_at_end()
+    }
+}
+
+// Expressions an assignment or `++`/`--` can change
+fn is_place(expr: &Expr) -> bool {
+    matches!(
+        expr.kind,
+        ExprKind::Identifier(_) | ExprKind::Index { .. } | ExprKind::Member { .. }
+    )
+}
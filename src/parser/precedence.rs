@@ -0,0 +1,171 @@
+use crate::lexer::token::{Operation, Punctuation, Token};
+
+// Binding strength of an operator, weakest first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Precedence {
+    Lowest,
+    Assignment, // = += -= *= /= |= &=
+    Ternary,    // ?:
+    Or,         // ||
+    And,        // &&
+    BitOr,      // |
+    BitXor,     // ^
+    BitAnd,     // &
+    Equality,   // == !=
+    Comparison, // < > <= >=
+    Shift,      // << >>
+    Term,       // + -
+    Factor,     // * / % %%
+    Prefix,     // !x -x ~x ++x --x
+    Postfix,    // x++ x-- f() a[i] a.b
+}
+
+impl Precedence {
+    // The next stronger level, used for the right operand of a left
+    // associative operator
+    pub fn next(self) -> Precedence {
+        use Precedence::*;
+        match self {
+            Lowest => Assignment,
+            Assignment => Ternary,
+            Ternary => Or,
+            Or => And,
+            And => BitOr,
+            BitOr => BitXor,
+            BitXor => BitAnd,
+            BitAnd => Equality,
+            Equality => Comparison,
+            Comparison => Shift,
+            Shift => Term,
+            Term => Factor,
+            Factor => Prefix,
+            Prefix | Postfix => Postfix,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Associativity {
+    Left,
+    Right,
+}
+
+// Where an operator goes relative to its operands
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Fixity {
+    Prefix,
+    Infix,
+    Postfix,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct OperatorEntry {
+    pub op: Operation,
+    pub fixity: Fixity,
+    pub precedence: Precedence,
+    pub associativity: Associativity,
+}
+
+// Infix operators
+const fn left(op: Operation, precedence: Precedence) -> OperatorEntry {
+    OperatorEntry {
+        op,
+        fixity: Fixity::Infix,
+        precedence,
+        associativity: Associativity::Left,
+    }
+}
+
+const fn right(op: Operation, precedence: Precedence) -> OperatorEntry {
+    OperatorEntry {
+        op,
+        fixity: Fixity::Infix,
+        precedence,
+        associativity: Associativity::Right,
+    }
+}
+
+const fn prefix(op: Operation) -> OperatorEntry {
+    OperatorEntry {
+        op,
+        fixity: Fixity::Prefix,
+        precedence: Precedence::Prefix,
+        associativity: Associativity::Right,
+    }
+}
+
+const fn postfix(op: Operation) -> OperatorEntry {
+    OperatorEntry {
+        op,
+        fixity: Fixity::Postfix,
+        precedence: Precedence::Postfix,
+        associativity: Associativity::Left,
+    }
+}
+
+// Every operator the expression parser accepts. An operation may appear once
+// per fixity, as `-` does for negation and subtraction.
+pub const OPERATOR_TABLE: &[OperatorEntry] = &[
+    right(Operation::Assign, Precedence::Assignment),
+    right(Operation::AddAssign, Precedence::Assignment),
+    right(Operation::SubAssign, Precedence::Assignment),
+    right(Operation::MulAssign, Precedence::Assignment),
+    right(Operation::DivAssign, Precedence::Assignment),
+    right(Operation::OrAssign, Precedence::Assignment),
+    right(Operation::AndAssign, Precedence::Assignment),
+    left(Operation::Or, Precedence::Or),
+    left(Operation::And, Precedence::And),
+    left(Operation::BitOr, Precedence::BitOr),
+    left(Operation::BitXor, Precedence::BitXor),
+    left(Operation::BitAnd, Precedence::BitAnd),
+    left(Operation::IfEqual, Precedence::Equality),
+    left(Operation::NotEqual, Precedence::Equality),
+    left(Operation::Less, Precedence::Comparison),
+    left(Operation::Greater, Precedence::Comparison),
+    left(Operation::LessEqual, Precedence::Comparison),
+    left(Operation::GreaterEqual, Precedence::Comparison),
+    left(Operation::ShiftLeft, Precedence::Shift),
+    left(Operation::ShiftRight, Precedence::Shift),
+    left(Operation::Add, Precedence::Term),
+    left(Operation::Subtract, Precedence::Term),
+    left(Operation::Multiply, Precedence::Factor),
+    left(Operation::Divide, Precedence::Factor),
+    left(Operation::Modulo, Precedence::Factor),
+    left(Operation::Remainder, Precedence::Factor),
+    prefix(Operation::Not),
+    prefix(Operation::Subtract),
+    prefix(Operation::BitNot),
+    prefix(Operation::Increment),
+    prefix(Operation::Decrement),
+    postfix(Operation::Increment),
+    postfix(Operation::Decrement),
+];
+
+pub fn operator(op: Operation, fixity: Fixity) -> Option<(Precedence, Associativity)> {
+    OPERATOR_TABLE
+        .iter()
+        .find(|entry| entry.op == op && entry.fixity == fixity)
+        .map(|entry| (entry.precedence, entry.associativity))
+}
+
+// Operators that change their left operand, which must be a place
+pub fn is_assignment(op: Operation) -> bool {
+    operator(op, Fixity::Infix).is_some_and(|(precedence, _)| precedence == Precedence::Assignment)
+}
+
+// Precedence of whatever `token` would continue an expression with: an infix
+// or postfix operation, `?` of a ternary, or a call, index or member access
+pub fn continuation(token: &Token) -> Option<(Precedence, Associativity)> {
+    match token {
+        Token::Operation(op) => {
+            operator(*op, Fixity::Postfix).or_else(|| operator(*op, Fixity::Infix))
+        }
+        Token::Punctuation(Punctuation::QuestionMark) => {
+            Some((Precedence::Ternary, Associativity::Right))
+        }
+        Token::Punctuation(
+            Punctuation::OpenParen | Punctuation::OpenBracket | Punctuation::Dot,
+        ) => Some((Precedence::Postfix, Associativity::Left)),
+        _ => None,
+    }
+}
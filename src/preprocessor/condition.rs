@@ -0,0 +1,89 @@
+use crate::lexer::token::Operation;
+use crate::parser::precedence::{self, Fixity, Precedence};
+
+// A token of an `#if` condition after `defined` and macros are resolved
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Atom {
+    Number(i128),
+    Op(Operation),
+    Open,
+    Close,
+}
+
+// Value of a whole `#if` condition, or `None` when it is malformed. Operators
+// bind as they do in expressions; assignments and `?:` are not allowed.
+pub fn evaluate(atoms: &[Atom]) -> Option<i128> {
+    let mut position = 0;
+    let value = binary(atoms, &mut position, Precedence::Or)?;
+    (position == atoms.len()).then_some(value)
+}
+
+fn binary(atoms: &[Atom], position: &mut usize, min: Precedence) -> Option<i128> {
+    let mut left = operand(atoms, position)?;
+
+    while let Some(&Atom::Op(op)) = atoms.get(*position)
+        && let Some((precedence, _)) = precedence::operator(op, Fixity::Infix)
+        && precedence >= min
+    {
+        *position += 1;
+        // Every operator allowed here is left associative
+        let right = binary(atoms, position, precedence.next())?;
+        left = apply(op, left, right)?;
+    }
+    Some(left)
+}
+
+fn operand(atoms: &[Atom], position: &mut usize) -> Option<i128> {
+    let atom = *atoms.get(*position)?;
+    *position += 1;
+
+    match atom {
+        Atom::Number(value) => Some(value),
+        Atom::Open => {
+            let value = binary(atoms, position, Precedence::Or)?;
+            if atoms.get(*position) != Some(&Atom::Close) {
+                return None;
+            }
+            *position += 1;
+            Some(value)
+        }
+        Atom::Op(op) => {
+            let value = operand(atoms, position)?;
+            match op {
+                Operation::Not => Some((value == 0) as i128),
+                Operation::Subtract => Some(value.wrapping_neg()),
+                Operation::BitNot => Some(!value),
+                _ => None,
+            }
+        }
+        Atom::Close => None,
+    }
+}
+
+fn apply(op: Operation, left: i128, right: i128) -> Option<i128> {
+    let shift = || u32::try_from(right).ok();
+
+    Some(match op {
+        Operation::Or => (left != 0 || right != 0) as i128,
+        Operation::And => (left != 0 && right != 0) as i128,
+        Operation::BitOr => left | right,
+        Operation::BitXor => left ^ right,
+        Operation::BitAnd => left & right,
+        Operation::IfEqual => (left == right) as i128,
+        Operation::NotEqual => (left != right) as i128,
+        Operation::Less => (left < right) as i128,
+        Operation::Greater => (left > right) as i128,
+        Operation::LessEqual => (left <= right) as i128,
+        Operation::GreaterEqual => (left >= right) as i128,
+        Operation::ShiftLeft => left.checked_shl(shift()?)?,
+        Operation::ShiftRight => left.checked_shr(shift()?)?,
+        Operation::Add => left.wrapping_add(right),
+        Operation::Subtract => left.wrapping_sub(right),
+        Operation::Multiply => left.wrapping_mul(right),
+        Operation::Divide => left.checked_div(right)?,
+        Operation::Modulo => left.checked_rem(right)?,
+        Operation::Remainder => left.checked_rem_euclid(right)?,
+        // Assignments
+        _ => return None,
+    })
+}
@@ -0,0 +1,130 @@
+use std::fmt;
+use std::rc::Rc;
+
+use crate::source::expansion::Expansion;
+use crate::source::span::Span;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PreprocessError {
+    UnknownDirective(String),
+    MissingMacroName(String),
+    InvalidParameters(String),
+    MalformedInclude,
+    IncludeNotFound(String),
+    IncludeTooDeep(String),
+    UnmatchedDirective(String),
+    DuplicateElse,
+    UnterminatedConditional(String),
+    ArgumentCount {
+        name: String,
+        expected: usize,
+        found: usize,
+    },
+    UnterminatedMacroCall(String),
+    InvalidCondition(String),
+}
+
+impl PreprocessError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            PreprocessError::UnknownDirective(_) => "P0001",
+            PreprocessError::MissingMacroName(_) => "P0002",
+            PreprocessError::InvalidParameters(_) => "P0003",
+            PreprocessError::MalformedInclude => "P0004",
+            PreprocessError::IncludeNotFound(_) => "P0005",
+            PreprocessError::IncludeTooDeep(_) => "P0006",
+            PreprocessError::UnmatchedDirective(_) => "P0007",
+            PreprocessError::DuplicateElse => "P0008",
+            PreprocessError::UnterminatedConditional(_) => "P0009",
+            PreprocessError::ArgumentCount { .. } => "P0010",
+            PreprocessError::UnterminatedMacroCall(_) => "P0011",
+            PreprocessError::InvalidCondition(_) => "P0012",
+        }
+    }
+}
+
+impl fmt::Display for PreprocessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PreprocessError::UnknownDirective(name) => write!(f, "Unknown directive #{}", name),
+            PreprocessError::MissingMacroName(directive) => {
+                write!(f, "Expected a macro name after #{}", directive)
+            }
+            PreprocessError::InvalidParameters(name) => {
+                write!(f, "Invalid parameter list for macro {}", name)
+            }
+            PreprocessError::MalformedInclude => {
+                write!(f, "Expected \"file\" or <file> after #include")
+            }
+            PreprocessError::IncludeNotFound(name) => {
+                write!(f, "Cannot find include file {}", name)
+            }
+            PreprocessError::IncludeTooDeep(name) => {
+                write!(f, "Includes nested too deeply while including {}", name)
+            }
+            PreprocessError::UnmatchedDirective(directive) => {
+                write!(f, "#{} without a matching #if", directive)
+            }
+            PreprocessError::DuplicateElse => write!(f, "Conditional already has an #else"),
+            PreprocessError::UnterminatedConditional(directive) => {
+                write!(f, "#{} is never closed by #endif", directive)
+            }
+            PreprocessError::ArgumentCount {
+                name,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Macro {} expects {} arguments, found {}",
+                name, expected, found
+            ),
+            PreprocessError::UnterminatedMacroCall(name) => {
+                write!(f, "Unterminated argument list for macro {}", name)
+            }
+            PreprocessError::InvalidCondition(directive) => {
+                write!(f, "Invalid condition after #{}", directive)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PreprocessDiagnostic {
+    pub error: PreprocessError,
+    pub span: Span,
+    pub line: usize,
+    pub column: usize,
+    // Set when the offending token came out of a macro expansion
+    pub expansion: Option<Rc<Expansion>>,
+}
+
+impl PreprocessDiagnostic {
+    pub fn code(&self) -> &'static str {
+        self.error.code()
+    }
+}
+
+impl fmt::Display for PreprocessDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "error[{}] {}:{}: {}",
+            self.code(),
+            self.line,
+            self.column,
+            self.error
+        )?;
+        if let Some(expansion) = &self.expansion {
+            for outer in expansion.backtrace() {
+                write!(
+                    f,
+                    "\n  in expansion of macro {} at {}:{}",
+                    outer.name, outer.line, outer.column
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for PreprocessDiagnostic {}
@@ -0,0 +1,22 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Where `#include` reads files from, so tools and tests can serve them from memory
+pub trait FileLoader {
+    fn load(&self, path: &Path) -> Option<String>;
+}
+
+pub struct DiskLoader;
+
+impl FileLoader for DiskLoader {
+    fn load(&self, path: &Path) -> Option<String> {
+        fs::read_to_string(path).ok()
+    }
+}
+
+impl FileLoader for HashMap<PathBuf, String> {
+    fn load(&self, path: &Path) -> Option<String> {
+        self.get(path).cloned()
+    }
+}
@@ -0,0 +1,12 @@
+#![allow(dead_code, unused, unused_imports)]
+
+pub mod condition;
+pub mod error;
+pub mod loader;
+#[allow(clippy::module_inception)]
+pub mod preprocessor;
+
+// Re-export main types for easier access
+pub use error::{PreprocessDiagnostic, PreprocessError};
+pub use loader::{DiskLoader, FileLoader};
+pub use preprocessor::{Macro, Preprocessor};
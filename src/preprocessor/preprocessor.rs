@@ -0,0 +1,722 @@
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::lexer::error::LexDiagnostic;
+use crate::lexer::lexer::Lexer;
+use crate::lexer::token::{Operation, Punctuation, Reserved, Token, TokenInfo};
+use crate::preprocessor::condition::{self, Atom};
+use crate::preprocessor::error::{PreprocessDiagnostic, PreprocessError};
+use crate::preprocessor::loader::{DiskLoader, FileLoader};
+use crate::source::expansion::Expansion;
+use crate::source::source_map::SourceMap;
+use crate::source::span::{FileId, Span};
+
+// Guards against a file that includes itself without an include guard
+const MAX_INCLUDE_DEPTH: usize = 64;
+
+#[derive(Debug, Clone)]
+pub struct Macro<'src> {
+    pub name: String,
+    // `None` for object-like macros, the parameter names for function-like ones
+    pub params: Option<Vec<String>>,
+    pub body: Vec<TokenInfo<'src>>,
+    pub definition: Span,
+}
+
+// Names of the macros a token was produced by; a macro is not expanded again
+// inside its own expansion
+type HideSet = Rc<Vec<String>>;
+
+// A token awaiting expansion, with the macros it must not be expanded by
+type Pending<'src> = (TokenInfo<'src>, HideSet);
+
+// One `#if`/`#ifdef`/`#ifndef` ... `#endif` block being processed
+struct Conditional<'src> {
+    opener: TokenInfo<'src>,
+    // Whether the lines currently being read are kept
+    active: bool,
+    // Whether one of the branches has already been kept, or a malformed
+    // condition ruled all of them out
+    taken: bool,
+    seen_else: bool,
+    parent_active: bool,
+}
+
+// Runs between the lexer and the parser: executes `#define`, `#undef`,
+// `#include` and `#if`/`#ifdef`/`#ifndef`/`#elif`/`#else`/`#endif` lines and
+// expands macros. Directive lines are removed and line breaks are dropped from
+// the output.
+pub struct Preprocessor<'src> {
+    sources: &'src SourceMap,
+    loader: Box<dyn FileLoader>,
+    search_paths: Vec<PathBuf>,
+    macros: HashMap<String, Macro<'src>>,
+    // Guard macro of each included file that is wrapped in one, so the file is
+    // not read again once the guard is defined
+    guards: HashMap<PathBuf, String>,
+    include_depth: usize,
+    diagnostics: Vec<PreprocessDiagnostic>,
+    lex_diagnostics: Vec<LexDiagnostic>,
+}
+
+impl<'src> Preprocessor<'src> {
+    // Included files are added to `sources`, which output tokens borrow from
+    pub fn new(sources: &'src SourceMap) -> Self {
+        Self {
+            sources,
+            loader: Box::new(DiskLoader),
+            search_paths: Vec::new(),
+            macros: HashMap::new(),
+            guards: HashMap::new(),
+            include_depth: 0,
+            diagnostics: Vec::new(),
+            lex_diagnostics: Vec::new(),
+        }
+    }
+
+    pub fn loader(mut self, loader: impl FileLoader + 'static) -> Self {
+        self.loader = Box::new(loader);
+        self
+    }
+
+    // Directory searched for `#include <...>`, and for `#include "..."` after the
+    // including file's own directory
+    pub fn search_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.search_paths.push(path.into());
+        self
+    }
+
+    // Predefines an object-like macro, like `-D name=body` on a C compiler
+    pub fn define(&mut self, name: &str, body: &str) {
+        let file = self.sources.add_file(format!("<define {}>", name), body);
+        let mut body = self.lex(file);
+        body.pop(); // Eof
+        body.retain(|info| info.token != Token::Newline);
+
+        let definition = self.sources.file(file).text().len();
+        self.macros.insert(
+            name.to_string(),
+            Macro {
+                name: name.to_string(),
+                params: None,
+                body,
+                definition: Span::new(file, 0, definition),
+            },
+        );
+    }
+
+    pub fn is_defined(&self, name: &str) -> bool {
+        self.macros.contains_key(name)
+    }
+
+    pub fn macro_definition(&self, name: &str) -> Option<&Macro<'src>> {
+        self.macros.get(name)
+    }
+
+    // Preprocesses `file` and everything it includes, ending with `Token::Eof`
+    pub fn run(&mut self, file: FileId) -> Vec<TokenInfo<'src>> {
+        let mut tokens = self.lex(file);
+        let eof = tokens.pop();
+
+        let mut output = Vec::new();
+        self.process(tokens, &mut output);
+        output.extend(eof);
+        output
+    }
+
+    pub fn diagnostics(&self) -> &[PreprocessDiagnostic] {
+        &self.diagnostics
+    }
+
+    // Errors from lexing included files and predefined macros
+    pub fn lex_diagnostics(&self) -> &[LexDiagnostic] {
+        &self.lex_diagnostics
+    }
+
+    fn lex(&mut self, file: FileId) -> Vec<TokenInfo<'src>> {
+        let sources = self.sources;
+        let text = sources.file(file).text();
+
+        let (tokens, diagnostics) = Lexer::new(text).in_file(file).tokenize_with_diagnostics();
+        self.lex_diagnostics.extend(diagnostics);
+        tokens
+    }
+
+    fn process(&mut self, tokens: Vec<TokenInfo<'src>>, output: &mut Vec<TokenInfo<'src>>) {
+        let mut conditionals = Vec::new();
+        // Ordinary lines since the last directive, expanded together so macro
+        // calls can span lines
+        let mut text = Vec::new();
+
+        for line in lines(tokens) {
+            let active = conditionals
+                .last()
+                .is_none_or(|conditional: &Conditional| conditional.active);
+
+            if is_directive(&line) {
+                self.expand_into(std::mem::take(&mut text), output);
+                self.directive(line, &mut conditionals, active, output);
+            } else if active {
+                text.extend(line);
+            }
+        }
+        self.expand_into(text, output);
+
+        // Conditionals must be closed in the file that opened them
+        for conditional in conditionals {
+            let directive = conditional.opener.lexeme.to_string();
+            self.report(
+                PreprocessError::UnterminatedConditional(directive),
+                &conditional.opener,
+            );
+        }
+    }
+
+    // Directives
+
+    fn directive(
+        &mut self,
+        line: Vec<TokenInfo<'src>>,
+        conditionals: &mut Vec<Conditional<'src>>,
+        active: bool,
+        output: &mut Vec<TokenInfo<'src>>,
+    ) {
+        // A lone `#` is a null directive
+        let Some(name) = line.get(1) else {
+            return;
+        };
+
+        match name.lexeme {
+            "ifdef" | "ifndef" | "if" => {
+                // `None` for a malformed condition, which keeps no branch at all
+                let condition = match name.lexeme {
+                    "ifdef" => Some(
+                        self.conditional_name(&line, active)
+                            .is_some_and(|n| self.is_defined(n)),
+                    ),
+                    "ifndef" => Some(
+                        self.conditional_name(&line, active)
+                            .is_some_and(|n| !self.is_defined(n)),
+                    ),
+                    // Conditions in skipped code are not evaluated
+                    _ if !active => Some(false),
+                    _ => self.condition(&line),
+                };
+                conditionals.push(Conditional {
+                    opener: name.clone(),
+                    active: active && condition == Some(true),
+                    taken: condition != Some(false),
+                    seen_else: false,
+                    parent_active: active,
+                });
+            }
+            "elif" => {
+                let Some(conditional) = conditionals.last() else {
+                    self.report(
+                        PreprocessError::UnmatchedDirective("elif".to_string()),
+                        name,
+                    );
+                    return;
+                };
+                if conditional.seen_else {
+                    self.report(PreprocessError::DuplicateElse, name);
+                }
+                let condition =
+                    if conditional.parent_active && !conditional.taken && !conditional.seen_else {
+                        self.condition(&line)
+                    } else {
+                        Some(false)
+                    };
+                let conditional = conditionals.last_mut().unwrap();
+                conditional.active = condition == Some(true);
+                conditional.taken |= condition != Some(false);
+            }
+            "else" => match conditionals.last_mut() {
+                Some(conditional) => {
+                    if conditional.seen_else {
+                        self.report(PreprocessError::DuplicateElse, name);
+                    }
+                    conditional.seen_else = true;
+                    conditional.active = conditional.parent_active && !conditional.taken;
+                    conditional.taken = true;
+                }
+                None => self.report(
+                    PreprocessError::UnmatchedDirective("else".to_string()),
+                    name,
+                ),
+            },
+            "endif" => {
+                if conditionals.pop().is_none() {
+                    self.report(
+                        PreprocessError::UnmatchedDirective("endif".to_string()),
+                        name,
+                    );
+                }
+            }
+            // Everything else is ignored inside a skipped block
+            _ if !active => {}
+            "define" => self.define_directive(&line),
+            "undef" => {
+                if let Some(macro_name) = self.macro_name(&line, "undef") {
+                    let macro_name = macro_name.to_string();
+                    self.macros.remove(&macro_name);
+                }
+            }
+            "include" => self.include(&line, output),
+            other => {
+                let error = PreprocessError::UnknownDirective(other.to_string());
+                self.report(error, name);
+            }
+        }
+    }
+
+    // Whether the expression after `#if`/`#elif` is nonzero, or `None` after
+    // reporting it as malformed
+    fn condition(&mut self, line: &[TokenInfo<'src>]) -> Option<bool> {
+        let directive = &line[1];
+        let value = self
+            .condition_atoms(&line[2..])
+            .and_then(|atoms| condition::evaluate(&atoms));
+        if value.is_none() {
+            let error = PreprocessError::InvalidCondition(directive.lexeme.to_string());
+            self.report(error, directive);
+        }
+        value.map(|value| value != 0)
+    }
+
+    // Replaces `defined X` and `defined(X)` by 0 or 1, then expands macros in
+    // what is left
+    fn condition_atoms(&mut self, mut tokens: &[TokenInfo<'src>]) -> Option<Vec<Atom>> {
+        let mut atoms = Vec::new();
+        let mut run = Vec::new();
+
+        while let Some((token, rest)) = tokens.split_first() {
+            tokens = rest;
+            if token.lexeme != "defined" {
+                run.push(token.clone());
+                continue;
+            }
+            self.push_atoms(std::mem::take(&mut run), &mut atoms)?;
+
+            let name = match rest {
+                [open, name, close, rest @ ..]
+                    if open.token == Token::Punctuation(Punctuation::OpenParen)
+                        && close.token == Token::Punctuation(Punctuation::CloseParen) =>
+                {
+                    tokens = rest;
+                    name
+                }
+                [name, rest @ ..] => {
+                    tokens = rest;
+                    name
+                }
+                [] => return None,
+            };
+            if !is_name(name) {
+                return None;
+            }
+            atoms.push(Atom::Number(self.is_defined(name.lexeme) as i128));
+        }
+        self.push_atoms(run, &mut atoms)?;
+        Some(atoms)
+    }
+
+    // Expands `run` and converts the result; names left over count as 0
+    fn push_atoms(&mut self, run: Vec<TokenInfo<'src>>, atoms: &mut Vec<Atom>) -> Option<()> {
+        let input = run
+            .into_iter()
+            .map(|token| (token, HideSet::default()))
+            .collect();
+
+        for (token, _) in self.expand(input) {
+            atoms.push(match token.token {
+                Token::Integer { value, .. } => Atom::Number(i128::try_from(value).ok()?),
+                Token::Reserved(Reserved::True) => Atom::Number(1),
+                Token::Identifier(_) | Token::Reserved(_) => Atom::Number(0),
+                Token::Operation(op) => Atom::Op(op),
+                Token::Punctuation(Punctuation::OpenParen) => Atom::Open,
+                Token::Punctuation(Punctuation::CloseParen) => Atom::Close,
+                _ => return None,
+            });
+        }
+        Some(())
+    }
+
+    // Name tested by `#ifdef`/`#ifndef`; only reported as missing in active code
+    fn conditional_name(&mut self, line: &[TokenInfo<'src>], active: bool) -> Option<&'src str> {
+        if active {
+            let directive = line[1].lexeme;
+            self.macro_name(line, directive)
+        } else {
+            line.get(2)
+                .filter(|token| is_name(token))
+                .map(|token| token.lexeme)
+        }
+    }
+
+    // The name following `#directive`, reporting its absence
+    fn macro_name(&mut self, line: &[TokenInfo<'src>], directive: &str) -> Option<&'src str> {
+        match line.get(2) {
+            Some(token) if is_name(token) => Some(token.lexeme),
+            _ => {
+                let error = PreprocessError::MissingMacroName(directive.to_string());
+                self.report(error, line.last().unwrap_or(&line[0]));
+                None
+            }
+        }
+    }
+
+    fn define_directive(&mut self, line: &[TokenInfo<'src>]) {
+        let Some(name) = self.macro_name(line, "define") else {
+            return;
+        };
+        let name_token = &line[2];
+
+        // Function-like only when `(` touches the name: `#define F(x)` versus
+        // `#define F (x)`
+        let mut body = &line[3..];
+        let params = match body.first() {
+            Some(open)
+                if open.token == Token::Punctuation(Punctuation::OpenParen)
+                    && open.span.start_byte == name_token.span.end_byte =>
+            {
+                let Some((params, rest)) = parameters(&body[1..]) else {
+                    let error = PreprocessError::InvalidParameters(name.to_string());
+                    self.report(error, open);
+                    return;
+                };
+                body = rest;
+                Some(params)
+            }
+            _ => None,
+        };
+
+        let last = line.last().unwrap_or(name_token);
+        self.macros.insert(
+            name.to_string(),
+            Macro {
+                name: name.to_string(),
+                params,
+                body: body.to_vec(),
+                definition: name_token.span.to(last.span),
+            },
+        );
+    }
+
+    fn include(&mut self, line: &[TokenInfo<'src>], output: &mut Vec<TokenInfo<'src>>) {
+        let hash = &line[0];
+        let Some((name, angled)) = self.include_name(line) else {
+            self.report(PreprocessError::MalformedInclude, &line[1]);
+            return;
+        };
+
+        if self.include_depth >= MAX_INCLUDE_DEPTH {
+            self.report(PreprocessError::IncludeTooDeep(name), hash);
+            return;
+        }
+
+        // Quoted names are looked up next to the including file first
+        let mut candidates = Vec::new();
+        if !angled {
+            let including = Path::new(self.sources.file(hash.span.file_id).name());
+            let directory = including.parent().unwrap_or(Path::new(""));
+            candidates.push(directory.join(&name));
+        }
+        candidates.extend(self.search_paths.iter().map(|path| path.join(&name)));
+
+        for path in candidates {
+            if let Some(guard) = self.guards.get(&path)
+                && self.is_defined(guard)
+            {
+                return;
+            }
+
+            let Some(text) = self.loader.load(&path) else {
+                continue;
+            };
+            let file = self.sources.add_file(path.display().to_string(), text);
+            let mut tokens = self.lex(file);
+            tokens.pop(); // Eof
+
+            if let Some(guard) = include_guard(&tokens) {
+                self.guards.insert(path, guard);
+            }
+
+            self.include_depth += 1;
+            self.process(tokens, output);
+            self.include_depth -= 1;
+            return;
+        }
+
+        self.report(PreprocessError::IncludeNotFound(name), hash);
+    }
+
+    // `"name"` or `<name>`, and whether it was the angled form
+    fn include_name(&self, line: &[TokenInfo<'src>]) -> Option<(String, bool)> {
+        let first = line.get(2)?;
+        match &first.token {
+            Token::String { value, .. } => Some((value.clone(), false)),
+            Token::Operation(Operation::Less) => {
+                let close = line[3..]
+                    .iter()
+                    .find(|token| token.token == Token::Operation(Operation::Greater))?;
+                // The lexer splits `<sys/types.h>` into several tokens, so take the
+                // source text between the brackets instead
+                let text = self.sources.file(first.span.file_id).text();
+                let name = &text[first.span.end_byte..close.span.start_byte];
+                Some((name.trim().to_string(), true))
+            }
+            _ => None,
+        }
+    }
+
+    // Macro expansion
+
+    fn expand_into(&mut self, tokens: Vec<TokenInfo<'src>>, output: &mut Vec<TokenInfo<'src>>) {
+        if tokens.is_empty() {
+            return;
+        }
+        let input = tokens
+            .into_iter()
+            .map(|token| (token, HideSet::default()))
+            .collect();
+        output.extend(self.expand(input).into_iter().map(|(token, _)| token));
+    }
+
+    // Replaces macro uses until none are left. Each replacement is pushed back onto
+    // the input so it is rescanned together with the tokens that follow it.
+    fn expand(&mut self, mut input: VecDeque<Pending<'src>>) -> Vec<Pending<'src>> {
+        let mut output = Vec::new();
+
+        while let Some((token, hide)) = input.pop_front() {
+            let definition = match self.macros.get(token.lexeme) {
+                Some(definition) if is_name(&token) && !hide.contains(&definition.name) => {
+                    definition.clone()
+                }
+                _ => {
+                    output.push((token, hide));
+                    continue;
+                }
+            };
+
+            let mut call_site = token.span;
+            let args = match &definition.params {
+                None => Vec::new(),
+                Some(params) => {
+                    // A function-like macro name without arguments is left alone
+                    let called = input.front().is_some_and(|(next, _)| {
+                        next.token == Token::Punctuation(Punctuation::OpenParen)
+                    });
+                    if !called {
+                        output.push((token, hide));
+                        continue;
+                    }
+
+                    // A call that cannot be expanded is reported and kept as written
+                    let mut consumed = Vec::new();
+                    let Some((mut args, close)) = arguments(&mut input, &mut consumed) else {
+                        let error = PreprocessError::UnterminatedMacroCall(definition.name);
+                        self.report(error, &token);
+                        output.push((token, hide));
+                        output.extend(consumed);
+                        continue;
+                    };
+                    if close.file_id == call_site.file_id {
+                        call_site = call_site.to(close);
+                    }
+
+                    // `F()` passes one empty argument, which is no arguments for `F()`
+                    if params.is_empty() && args.len() == 1 && args[0].is_empty() {
+                        args.clear();
+                    }
+                    if args.len() != params.len() {
+                        let error = PreprocessError::ArgumentCount {
+                            name: definition.name,
+                            expected: params.len(),
+                            found: args.len(),
+                        };
+                        self.report(error, &token);
+                        output.push((token, hide));
+                        output.extend(consumed);
+                        continue;
+                    }
+
+                    // Arguments are fully expanded before being substituted
+                    args.into_iter()
+                        .map(|arg| self.expand(arg.into()))
+                        .collect()
+                }
+            };
+
+            let expansion = Rc::new(Expansion {
+                name: definition.name.clone(),
+                call_site,
+                line: token.line,
+                column: token.column,
+                definition: definition.definition,
+                parent: token.expansion.clone(),
+            });
+            let mut inner_hide = hide.as_ref().clone();
+            inner_hide.push(definition.name.clone());
+            let inner_hide = Rc::new(inner_hide);
+
+            let mut replacement = Vec::new();
+            for body_token in &definition.body {
+                let param = definition.params.as_ref().and_then(|params| {
+                    params
+                        .iter()
+                        .position(|param| is_name(body_token) && param == body_token.lexeme)
+                });
+                match param {
+                    // Substituted tokens are hidden from the macro too, keeping only
+                    // the names they share with the invocation
+                    Some(index) => replacement.extend(args[index].iter().map(|(token, own)| {
+                        let mut arg_hide: Vec<String> = own
+                            .iter()
+                            .filter(|name| hide.contains(name))
+                            .cloned()
+                            .collect();
+                        arg_hide.push(definition.name.clone());
+                        (token.clone(), Rc::new(arg_hide))
+                    })),
+                    None => {
+                        let mut body_token = body_token.clone();
+                        body_token.expansion = Some(expansion.clone());
+                        replacement.push((body_token, inner_hide.clone()));
+                    }
+                }
+            }
+
+            for item in replacement.into_iter().rev() {
+                input.push_front(item);
+            }
+        }
+
+        output
+    }
+
+    fn report(&mut self, error: PreprocessError, token: &TokenInfo<'src>) {
+        self.diagnostics.push(PreprocessDiagnostic {
+            error,
+            span: token.span,
+            line: token.line,
+            column: token.column,
+            expansion: token.expansion.clone(),
+        });
+    }
+}
+
+// Splits tokens at line breaks, dropping empty lines
+fn lines<'src>(tokens: Vec<TokenInfo<'src>>) -> Vec<Vec<TokenInfo<'src>>> {
+    let mut lines = vec![Vec::new()];
+    for token in tokens {
+        if token.token == Token::Newline {
+            lines.push(Vec::new());
+        } else {
+            lines.last_mut().unwrap().push(token);
+        }
+    }
+    lines.retain(|line| !line.is_empty());
+    lines
+}
+
+fn is_directive(line: &[TokenInfo]) -> bool {
+    line.first().is_some_and(is_hash)
+}
+
+fn is_hash(token: &TokenInfo) -> bool {
+    token.token == Token::Punctuation(Punctuation::Hashtag)
+}
+
+// Keywords can be macro names too, so `#define print printf` works
+fn is_name(token: &TokenInfo) -> bool {
+    matches!(token.token, Token::Identifier(_) | Token::Reserved(_))
+}
+
+// Parameter names up to the closing paren, and the tokens after it
+fn parameters<'a, 'src>(
+    tokens: &'a [TokenInfo<'src>],
+) -> Option<(Vec<String>, &'a [TokenInfo<'src>])> {
+    let mut params = Vec::new();
+    let mut rest = tokens;
+
+    if rest.first()?.token == Token::Punctuation(Punctuation::CloseParen) {
+        return Some((params, &rest[1..]));
+    }
+
+    loop {
+        let name = rest.first().filter(|token| is_name(token))?;
+        params.push(name.lexeme.to_string());
+
+        match &rest.get(1)?.token {
+            Token::Punctuation(Punctuation::Comma) => rest = &rest[2..],
+            Token::Punctuation(Punctuation::CloseParen) => return Some((params, &rest[2..])),
+            _ => return None,
+        }
+    }
+}
+
+// Consumes `(a, (b, c))` from the front of `input`, split at top-level commas.
+// Returns the arguments and the span of the closing paren; every token taken
+// from `input` is also copied to `consumed`.
+fn arguments<'src>(
+    input: &mut VecDeque<Pending<'src>>,
+    consumed: &mut Vec<Pending<'src>>,
+) -> Option<(Vec<Vec<Pending<'src>>>, Span)> {
+    consumed.extend(input.pop_front()); // (
+
+    let mut args = vec![Vec::new()];
+    let mut depth = 0;
+    loop {
+        let (token, hide) = input.pop_front()?;
+        consumed.push((token.clone(), hide.clone()));
+        match token.token {
+            Token::Punctuation(Punctuation::CloseParen) if depth == 0 => {
+                return Some((args, token.span));
+            }
+            Token::Punctuation(Punctuation::Comma) if depth == 0 => {
+                args.push(Vec::new());
+                continue;
+            }
+            Token::Punctuation(Punctuation::OpenParen) => depth += 1,
+            Token::Punctuation(Punctuation::CloseParen) => depth -= 1,
+            _ => {}
+        }
+        args.last_mut().unwrap().push((token, hide));
+    }
+}
+
+// `X` when the whole file is wrapped in `#ifndef X` / `#define X` ... `#endif`
+fn include_guard(tokens: &[TokenInfo]) -> Option<String> {
+    let lines = lines(tokens.to_vec());
+
+    let guard = match lines.first()?.as_slice() {
+        [hash, directive, name] if is_hash(hash) && directive.lexeme == "ifndef" => name.lexeme,
+        _ => return None,
+    };
+    match lines.get(1)?.as_slice() {
+        [hash, directive, name, ..]
+            if is_hash(hash) && directive.lexeme == "define" && name.lexeme == guard => {}
+        _ => return None,
+    }
+
+    // The `#endif` matching the `#ifndef` must be the last line
+    let mut depth = 0;
+    for (index, line) in lines.iter().enumerate() {
+        if !is_directive(line) {
+            continue;
+        }
+        match line.get(1).map(|token| token.lexeme) {
+            Some("if" | "ifdef" | "ifndef") => depth += 1,
+            Some("endif") => {
+                depth -= 1;
+                if depth == 0 {
+                    return (index == lines.len() - 1).then(|| guard.to_string());
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
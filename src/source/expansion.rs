@@ -0,0 +1,32 @@
+use std::rc::Rc;
+
+use crate::source::span::Span;
+
+// Macro expansion a token was produced by. Expansions that happen while
+// rescanning another expansion link to it through `parent`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expansion {
+    pub name: String,
+    // The macro name (and arguments, if any) at the place it was used
+    pub call_site: Span,
+    // Start of `call_site`, for messages that have no `SourceMap` at hand
+    pub line: usize,
+    pub column: usize,
+    // The macro's definition
+    pub definition: Span,
+    pub parent: Option<Rc<Expansion>>,
+}
+
+impl Expansion {
+    // This expansion followed by the ones it is nested in, innermost first
+    pub fn backtrace(&self) -> impl Iterator<Item = &Expansion> {
+        std::iter::successors(Some(self), |expansion| expansion.parent.as_deref())
+    }
+
+    // Call site of the outermost expansion, which is where the user wrote code
+    pub fn root_call_site(&self) -> Span {
+        self.backtrace()
+            .last()
+            .map_or(self.call_site, |root| root.call_site)
+    }
+}
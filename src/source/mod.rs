@@ -0,0 +1,10 @@
+#![allow(dead_code, unused, unused_imports)]
+
+pub mod expansion;
+pub mod source_map;
+pub mod span;
+
+// Re-export main types for easier access
+pub use expansion::Expansion;
+pub use source_map::{SourceFile, SourceLocation, SourceMap};
+pub use span::{FileId, Span};
@@ -0,0 +1,149 @@
+use std::fmt;
+
+use elsa::FrozenVec;
+
+use crate::source::span::{FileId, Span};
+
+// Line and column of a byte offset, all 1-based. `column` counts characters like
+// `TokenInfo::column`; the UTF-8 and UTF-16 columns count code units for editors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SourceLocation {
+    pub line: usize,
+    pub column: usize,
+    pub utf8_column: usize,
+    pub utf16_column: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct SourceFile {
+    name: String,
+    text: String,
+    // Byte offset where each line begins
+    line_starts: Vec<usize>,
+}
+
+impl SourceFile {
+    pub fn new(name: String, text: String) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(index, _)| index + 1))
+            .collect();
+
+        Self {
+            name,
+            text,
+            line_starts,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    pub fn location(&self, byte: usize) -> SourceLocation {
+        let byte = self.clamp(byte);
+        let line_index = match self.line_starts.binary_search(&byte) {
+            Ok(index) => index,
+            Err(index) => index - 1,
+        };
+        let prefix = &self.text[self.line_starts[line_index]..byte];
+
+        SourceLocation {
+            line: line_index + 1,
+            column: prefix.chars().count() + 1,
+            utf8_column: prefix.len() + 1,
+            utf16_column: prefix.encode_utf16().count() + 1,
+        }
+    }
+
+    // Byte offset of a 1-based line and character column, if it exists
+    pub fn offset(&self, line: usize, column: usize) -> Option<usize> {
+        let start = *self.line_starts.get(line.checked_sub(1)?)?;
+        let end = self
+            .line_starts
+            .get(line)
+            .copied()
+            .unwrap_or(self.text.len());
+
+        let line_text = &self.text[start..end];
+        match line_text.char_indices().nth(column.checked_sub(1)?) {
+            Some((index, _)) => Some(start + index),
+            None if column - 1 == line_text.chars().count() => Some(end),
+            None => None,
+        }
+    }
+
+    // Snaps out-of-range or mid-character offsets back to a character boundary
+    fn clamp(&self, byte: usize) -> usize {
+        let mut byte = byte.min(self.text.len());
+        while !self.text.is_char_boundary(byte) {
+            byte -= 1;
+        }
+        byte
+    }
+}
+
+// Files are boxed and never removed, so text borrowed from one stays valid while
+// more files are added. That lets the preprocessor load includes through `&self`
+// while tokens still borrow from earlier files.
+#[derive(Clone, Default)]
+pub struct SourceMap {
+    files: FrozenVec<Box<SourceFile>>,
+}
+
+impl SourceMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_file(&self, name: impl Into<String>, text: impl Into<String>) -> FileId {
+        let id = FileId(self.files.len() as u32);
+        self.files
+            .push(Box::new(SourceFile::new(name.into(), text.into())));
+        id
+    }
+
+    pub fn file(&self, id: FileId) -> &SourceFile {
+        &self.files[id.0 as usize]
+    }
+
+    pub fn files(&self) -> impl Iterator<Item = (FileId, &SourceFile)> {
+        self.files
+            .iter()
+            .enumerate()
+            .map(|(index, file)| (FileId(index as u32), file))
+    }
+
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    pub fn text(&self, span: Span) -> &str {
+        &self.file(span.file_id).text()[span.start_byte..span.end_byte]
+    }
+
+    pub fn start(&self, span: Span) -> SourceLocation {
+        self.file(span.file_id).location(span.start_byte)
+    }
+
+    pub fn end(&self, span: Span) -> SourceLocation {
+        self.file(span.file_id).location(span.end_byte)
+    }
+}
+
+impl fmt::Debug for SourceMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.files.iter()).finish()
+    }
+}
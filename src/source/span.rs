@@ -0,0 +1,38 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct FileId(pub u32);
+
+// Half-open byte range `start_byte..end_byte` into one file of a `SourceMap`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Span {
+    pub file_id: FileId,
+    pub start_byte: usize,
+    pub end_byte: usize,
+}
+
+impl Span {
+    pub fn new(file_id: FileId, start_byte: usize, end_byte: usize) -> Self {
+        Self {
+            file_id,
+            start_byte,
+            end_byte,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.end_byte - self.start_byte
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start_byte == self.end_byte
+    }
+
+    // Smallest span covering both; both must come from the same file
+    pub fn to(&self, other: Span) -> Span {
+        debug_assert_eq!(self.file_id, other.file_id);
+        Span {
+            file_id: self.file_id,
+            start_byte: self.start_byte.min(other.start_byte),
+            end_byte: self.end_byte.max(other.end_byte),
+        }
+    }
+}
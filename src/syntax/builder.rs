@@ -0,0 +1,193 @@
+use std::mem;
+use std::rc::Rc;
+
+use crate::lexer::lexer::Lexer;
+use crate::lexer::token::{CommentKind, Punctuation, Reserved, Token, TokenInfo};
+use crate::syntax::green::{GreenElement, GreenNode, GreenToken, SyntaxKind, Trivia, TriviaKind};
+use crate::syntax::red::SyntaxNode;
+
+// Lexes `source` with all of its trivia and builds the syntax tree. Printing
+// the tree gives back `source` unchanged.
+pub fn parse(source: &str) -> SyntaxNode {
+    let tokens = Lexer::new(source)
+        .keep_comments(true)
+        .keep_whitespace(true)
+        .tokenize();
+    SyntaxNode::new_root(Rc::new(build(tokens)))
+}
+
+// Builds the green tree for a token stream that still contains its trivia.
+// The tree only groups delimiters and statements, so it can be built for any
+// input, including input the parser rejects.
+pub fn build(tokens: Vec<TokenInfo>) -> GreenNode {
+    let tokens = attach_trivia(tokens);
+    let mut builder = TreeBuilder {
+        stack: vec![(SyntaxKind::Root, Vec::new())],
+    };
+
+    let mut tokens = tokens.into_iter().peekable();
+    while let Some(token) = tokens.next() {
+        builder.token(token, tokens.peek());
+    }
+    builder.finish()
+}
+
+fn trivia_kind(token: &Token) -> Option<TriviaKind> {
+    match token {
+        Token::Whitespace => Some(TriviaKind::Whitespace),
+        Token::Newline => Some(TriviaKind::Newline),
+        Token::Comment {
+            kind: CommentKind::Line,
+            ..
+        } => Some(TriviaKind::LineComment),
+        Token::Comment {
+            kind: CommentKind::Block,
+            ..
+        } => Some(TriviaKind::BlockComment),
+        _ => None,
+    }
+}
+
+// Trivia after a token up to the end of its line trails the token; a line
+// break and everything after it lead the next token
+fn attach_trivia(tokens: Vec<TokenInfo>) -> Vec<Rc<GreenToken>> {
+    let mut attached: Vec<(Token, &str, Vec<Trivia>, Vec<Trivia>)> = Vec::new();
+    let mut leading = Vec::new();
+    let mut same_line = false;
+
+    for info in tokens {
+        let Some(kind) = trivia_kind(&info.token) else {
+            attached.push((info.token, info.lexeme, mem::take(&mut leading), Vec::new()));
+            same_line = true;
+            continue;
+        };
+
+        let trivia = Trivia {
+            kind,
+            text: info.lexeme.to_string(),
+        };
+        match attached.last_mut() {
+            Some((_, _, _, trailing)) if same_line && kind != TriviaKind::Newline => {
+                trailing.push(trivia)
+            }
+            _ => {
+                same_line = false;
+                leading.push(trivia);
+            }
+        }
+    }
+
+    // Token streams that were cut short still get an end of input to carry the
+    // trailing trivia
+    if !matches!(attached.last(), Some((Token::Eof, ..))) {
+        attached.push((Token::Eof, "", leading, Vec::new()));
+    }
+
+    attached
+        .into_iter()
+        .map(|(token, text, leading, trailing)| {
+            Rc::new(GreenToken::new(token, text, leading, trailing))
+        })
+        .collect()
+}
+
+// Node a delimiter opens or closes
+fn group_kind(token: &Token) -> Option<(SyntaxKind, bool)> {
+    match token {
+        Token::Punctuation(Punctuation::OpenParen) => Some((SyntaxKind::Parens, true)),
+        Token::Punctuation(Punctuation::CloseParen) => Some((SyntaxKind::Parens, false)),
+        Token::Punctuation(Punctuation::OpenBrace) => Some((SyntaxKind::Block, true)),
+        Token::Punctuation(Punctuation::CloseBrace) => Some((SyntaxKind::Block, false)),
+        Token::Punctuation(Punctuation::OpenBracket) => Some((SyntaxKind::Brackets, true)),
+        Token::Punctuation(Punctuation::CloseBracket) => Some((SyntaxKind::Brackets, false)),
+        _ => None,
+    }
+}
+
+// Whether a statement goes on after a block, as in `if a { } else { }` or
+// `let p = Point { x: 1 };`
+fn continues_after_block(next: Option<&Rc<GreenToken>>) -> bool {
+    next.is_some_and(|next| {
+        matches!(
+            next.token(),
+            Token::Reserved(Reserved::Else)
+                | Token::Punctuation(Punctuation::Semicolon | Punctuation::Dot)
+                | Token::Operation(_)
+        )
+    })
+}
+
+struct TreeBuilder {
+    // Nodes still open, innermost last
+    stack: Vec<(SyntaxKind, Vec<GreenElement>)>,
+}
+
+impl TreeBuilder {
+    fn token(&mut self, token: Rc<GreenToken>, next: Option<&Rc<GreenToken>>) {
+        if *token.token() == Token::Eof {
+            while self.stack.len() > 1 {
+                self.close();
+            }
+            self.push(token);
+            return;
+        }
+
+        if let Some((kind, false)) = group_kind(token.token())
+            && let Some(depth) = self.stack.iter().rposition(|(open, _)| *open == kind)
+        {
+            // Anything opened after the matching delimiter is left unclosed
+            while self.stack.len() > depth + 1 {
+                self.close();
+            }
+            self.push(token);
+            self.close();
+
+            if kind == SyntaxKind::Block
+                && self.top() == SyntaxKind::Statement
+                && !continues_after_block(next)
+            {
+                self.close();
+            }
+            return;
+        }
+
+        if matches!(self.top(), SyntaxKind::Root | SyntaxKind::Block) {
+            self.stack.push((SyntaxKind::Statement, Vec::new()));
+        }
+
+        match group_kind(token.token()) {
+            Some((kind, true)) => self.stack.push((kind, vec![GreenElement::Token(token)])),
+            _ => {
+                let ends_statement = *token.token() == Token::Punctuation(Punctuation::Semicolon);
+                self.push(token);
+                if ends_statement && self.top() == SyntaxKind::Statement {
+                    self.close();
+                }
+            }
+        }
+    }
+
+    fn top(&self) -> SyntaxKind {
+        self.stack.last().expect("the root is never closed").0
+    }
+
+    fn push(&mut self, token: Rc<GreenToken>) {
+        let (_, children) = self.stack.last_mut().expect("the root is never closed");
+        children.push(GreenElement::Token(token));
+    }
+
+    // Turns the innermost open node into a child of its parent
+    fn close(&mut self) {
+        let (kind, children) = self.stack.pop().expect("the root is never closed");
+        let (_, parent) = self.stack.last_mut().expect("the root is never closed");
+        parent.push(GreenElement::Node(Rc::new(GreenNode::new(kind, children))));
+    }
+
+    fn finish(mut self) -> GreenNode {
+        while self.stack.len() > 1 {
+            self.close();
+        }
+        let (kind, children) = self.stack.pop().expect("the root is never closed");
+        GreenNode::new(kind, children)
+    }
+}
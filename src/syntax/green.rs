@@ -0,0 +1,162 @@
+use std::fmt;
+use std::rc::Rc;
+
+use crate::lexer::token::Token;
+
+// Kinds of interior node in the concrete syntax tree
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SyntaxKind {
+    Root,
+    // Tokens up to a `;`, or up to a block that ends the statement
+    Statement,
+    Block,    // { }
+    Parens,   // ( )
+    Brackets, // [ ]
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TriviaKind {
+    Whitespace,
+    Newline,
+    LineComment,
+    BlockComment,
+}
+
+// Source text that carries no meaning for the grammar
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trivia {
+    pub kind: TriviaKind,
+    pub text: String,
+}
+
+// A token with its trivia. Trailing trivia runs up to the end of the token's
+// line; the line break and everything after it lead the next token.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GreenToken {
+    token: Token,
+    text: String,
+    leading: Vec<Trivia>,
+    trailing: Vec<Trivia>,
+}
+
+impl GreenToken {
+    pub fn new(token: Token, text: &str, leading: Vec<Trivia>, trailing: Vec<Trivia>) -> Self {
+        Self {
+            token,
+            text: text.to_string(),
+            leading,
+            trailing,
+        }
+    }
+
+    pub fn token(&self) -> &Token {
+        &self.token
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn leading(&self) -> &[Trivia] {
+        &self.leading
+    }
+
+    pub fn trailing(&self) -> &[Trivia] {
+        &self.trailing
+    }
+
+    pub fn leading_width(&self) -> usize {
+        self.leading.iter().map(|trivia| trivia.text.len()).sum()
+    }
+
+    // Byte length including trivia
+    pub fn width(&self) -> usize {
+        let trailing: usize = self.trailing.iter().map(|trivia| trivia.text.len()).sum();
+        self.leading_width() + self.text.len() + trailing
+    }
+}
+
+impl fmt::Display for GreenToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for trivia in &self.leading {
+            f.write_str(&trivia.text)?;
+        }
+        f.write_str(&self.text)?;
+        for trivia in &self.trailing {
+            f.write_str(&trivia.text)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GreenElement {
+    Node(Rc<GreenNode>),
+    Token(Rc<GreenToken>),
+}
+
+impl GreenElement {
+    pub fn width(&self) -> usize {
+        match self {
+            GreenElement::Node(node) => node.width(),
+            GreenElement::Token(token) => token.width(),
+        }
+    }
+}
+
+impl fmt::Display for GreenElement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GreenElement::Node(node) => node.fmt(f),
+            GreenElement::Token(token) => token.fmt(f),
+        }
+    }
+}
+
+// Immutable tree node without positions or parent links, so unchanged
+// subtrees can be shared between versions of a tree
+#[derive(Debug, Clone, PartialEq)]
+pub struct GreenNode {
+    kind: SyntaxKind,
+    width: usize,
+    children: Vec<GreenElement>,
+}
+
+impl GreenNode {
+    pub fn new(kind: SyntaxKind, children: Vec<GreenElement>) -> Self {
+        Self {
+            kind,
+            width: children.iter().map(GreenElement::width).sum(),
+            children,
+        }
+    }
+
+    pub fn kind(&self) -> SyntaxKind {
+        self.kind
+    }
+
+    pub fn children(&self) -> &[GreenElement] {
+        &self.children
+    }
+
+    // Byte length of the source this node covers, trivia included
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    // Copy of this node with one child swapped; the other children are shared
+    pub fn replace_child(&self, index: usize, child: GreenElement) -> GreenNode {
+        let mut children = self.children.clone();
+        children[index] = child;
+        GreenNode::new(self.kind, children)
+    }
+}
+
+impl fmt::Display for GreenNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for child in &self.children {
+            child.fmt(f)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,10 @@
+#![allow(dead_code, unused, unused_imports)]
+
+pub mod builder;
+pub mod green;
+pub mod red;
+
+// Re-export main types for easier access
+pub use builder::{build, parse};
+pub use green::{GreenElement, GreenNode, GreenToken, SyntaxKind, Trivia, TriviaKind};
+pub use red::{SyntaxElement, SyntaxNode, SyntaxToken};
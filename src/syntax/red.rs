@@ -0,0 +1,221 @@
+use std::fmt;
+use std::ops::Range;
+use std::rc::Rc;
+
+use crate::lexer::token::Token;
+use crate::syntax::green::{GreenElement, GreenNode, GreenToken, SyntaxKind, Trivia};
+
+// View of a green node at a position in one tree. Red nodes are created on
+// demand while walking down, and know their parent and absolute offset.
+#[derive(Debug, Clone)]
+pub struct SyntaxNode(Rc<NodeData>);
+
+#[derive(Debug)]
+struct NodeData {
+    green: Rc<GreenNode>,
+    parent: Option<SyntaxNode>,
+    // Index among the parent's children
+    index: usize,
+    offset: usize,
+}
+
+impl SyntaxNode {
+    pub fn new_root(green: Rc<GreenNode>) -> Self {
+        SyntaxNode(Rc::new(NodeData {
+            green,
+            parent: None,
+            index: 0,
+            offset: 0,
+        }))
+    }
+
+    pub fn kind(&self) -> SyntaxKind {
+        self.0.green.kind()
+    }
+
+    pub fn green(&self) -> &Rc<GreenNode> {
+        &self.0.green
+    }
+
+    pub fn parent(&self) -> Option<&SyntaxNode> {
+        self.0.parent.as_ref()
+    }
+
+    pub fn index(&self) -> usize {
+        self.0.index
+    }
+
+    // Bytes covered by the node, including the trivia of its tokens
+    pub fn range(&self) -> Range<usize> {
+        self.0.offset..self.0.offset + self.0.green.width()
+    }
+
+    pub fn children(&self) -> Vec<SyntaxElement> {
+        let mut offset = self.0.offset;
+        let mut children = Vec::new();
+
+        for (index, child) in self.0.green.children().iter().enumerate() {
+            children.push(match child {
+                GreenElement::Node(green) => SyntaxElement::Node(SyntaxNode(Rc::new(NodeData {
+                    green: green.clone(),
+                    parent: Some(self.clone()),
+                    index,
+                    offset,
+                }))),
+                GreenElement::Token(green) => SyntaxElement::Token(SyntaxToken {
+                    green: green.clone(),
+                    parent: self.clone(),
+                    index,
+                    offset,
+                }),
+            });
+            offset += child.width();
+        }
+
+        children
+    }
+
+    pub fn child_nodes(&self) -> Vec<SyntaxNode> {
+        self.children()
+            .into_iter()
+            .filter_map(|child| match child {
+                SyntaxElement::Node(node) => Some(node),
+                SyntaxElement::Token(_) => None,
+            })
+            .collect()
+    }
+
+    // Every token below this node, in source order
+    pub fn tokens(&self) -> Vec<SyntaxToken> {
+        let mut tokens = Vec::new();
+        for child in self.children() {
+            match child {
+                SyntaxElement::Node(node) => tokens.extend(node.tokens()),
+                SyntaxElement::Token(token) => tokens.push(token),
+            }
+        }
+        tokens
+    }
+
+    // Token whose text (not trivia) contains `offset`
+    pub fn token_at(&self, offset: usize) -> Option<SyntaxToken> {
+        self.tokens()
+            .into_iter()
+            .find(|token| token.range().contains(&offset))
+    }
+
+    // New tree in which this node is `green`; returns the new root. Nodes
+    // outside the path to the root are shared with the old tree.
+    pub fn replace_with(&self, green: GreenNode) -> SyntaxNode {
+        self.replace_green(GreenElement::Node(Rc::new(green)))
+    }
+
+    fn replace_green(&self, green: GreenElement) -> SyntaxNode {
+        match self.parent() {
+            None => match green {
+                GreenElement::Node(node) => SyntaxNode::new_root(node),
+                GreenElement::Token(_) => unreachable!("the root is always a node"),
+            },
+            Some(parent) => {
+                let replaced = parent.green().replace_child(self.index(), green);
+                parent.replace_green(GreenElement::Node(Rc::new(replaced)))
+            }
+        }
+    }
+}
+
+impl PartialEq for SyntaxNode {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0.green, &other.0.green) && self.0.offset == other.0.offset
+    }
+}
+
+// Prints the exact source text of the node
+impl fmt::Display for SyntaxNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.green.fmt(f)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SyntaxToken {
+    green: Rc<GreenToken>,
+    parent: SyntaxNode,
+    index: usize,
+    // Start of the leading trivia
+    offset: usize,
+}
+
+impl SyntaxToken {
+    pub fn token(&self) -> &Token {
+        self.green.token()
+    }
+
+    pub fn text(&self) -> &str {
+        self.green.text()
+    }
+
+    pub fn green(&self) -> &Rc<GreenToken> {
+        &self.green
+    }
+
+    pub fn parent(&self) -> &SyntaxNode {
+        &self.parent
+    }
+
+    pub fn leading_trivia(&self) -> &[Trivia] {
+        self.green.leading()
+    }
+
+    pub fn trailing_trivia(&self) -> &[Trivia] {
+        self.green.trailing()
+    }
+
+    // Bytes of the token text, without trivia
+    pub fn range(&self) -> Range<usize> {
+        let start = self.offset + self.green.leading_width();
+        start..start + self.green.text().len()
+    }
+
+    // Bytes of the token including its trivia
+    pub fn full_range(&self) -> Range<usize> {
+        self.offset..self.offset + self.green.width()
+    }
+
+    // New tree in which this token is `green`; returns the new root
+    pub fn replace_with(&self, green: GreenToken) -> SyntaxNode {
+        let replaced = self
+            .parent
+            .green()
+            .replace_child(self.index, GreenElement::Token(Rc::new(green)));
+        self.parent.replace_with(replaced)
+    }
+}
+
+impl fmt::Display for SyntaxToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.green.fmt(f)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(SyntaxToken),
+}
+
+impl SyntaxElement {
+    pub fn as_node(&self) -> Option<&SyntaxNode> {
+        match self {
+            SyntaxElement::Node(node) => Some(node),
+            SyntaxElement::Token(_) => None,
+        }
+    }
+
+    pub fn as_token(&self) -> Option<&SyntaxToken> {
+        match self {
+            SyntaxElement::Node(_) => None,
+            SyntaxElement::Token(token) => Some(token),
+        }
+    }
+}
@@ -1 +1,9 @@
+pub mod tests_attributes;
 pub mod tests_lexer;
+pub mod tests_macros;
+pub mod tests_parser;
+pub mod tests_precedence;
+pub mod tests_preprocessor;
+pub mod tests_source;
+pub mod tests_syntax;
+pub mod tests_types;
@@ -0,0 +1,180 @@
+use crate::attributes::error::AttributeError;
+use crate::attributes::registry::{Annotated, AttributeRegistry, AttributeSpec, AttributeTarget};
+use crate::lexer::lexer::Lexer;
+use crate::parser::ast::{ExprKind, Literal, Program};
+use crate::parser::parser::Parser;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &str) -> Program {
+        let tokens = Lexer::new(input).tokenize();
+        Parser::new(tokens).parse().expect("program should parse")
+    }
+
+    fn errors(registry: &AttributeRegistry, input: &str) -> Vec<AttributeError> {
+        registry
+            .check(&parse(input))
+            .into_iter()
+            .map(|diagnostic| diagnostic.error)
+            .collect()
+    }
+
+    #[test]
+    fn test_builtin_attributes_are_accepted() {
+        let source = "\
+@derive(Eq, Hash) @derive(Debug)
+@deprecated(\"use Point3\")
+struct Point { @deprecated x: int, y: int }
+
+@inline @test
+fn check() { }";
+
+        assert!(errors(&AttributeRegistry::new(), source).is_empty());
+    }
+
+    #[test]
+    fn test_find_attribute() {
+        let program = parse(
+            "@test fn a() { }\nfn b() { }\nimpl T for S { @test fn c() { } }\n@deprecated(\"old\") fn d() { }",
+        );
+        let registry = AttributeRegistry::new();
+
+        let tests: Vec<_> = registry
+            .find(&program, "test")
+            .into_iter()
+            .map(|(node, _)| match node {
+                Annotated::Function(function) => function.name.as_str(),
+                _ => panic!("Expected function"),
+            })
+            .collect();
+        assert_eq!(tests, vec!["a", "c"]);
+
+        let (node, attribute) = registry.find(&program, "deprecated")[0];
+        assert_eq!(node.target(), AttributeTarget::Function);
+        assert_eq!(
+            attribute.args[0].kind,
+            ExprKind::Literal(Literal::String("old".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_nested_statement_attributes() {
+        let program = parse("fn f() { while x { @unroll(4) for i in items { } } }");
+        let registry = AttributeRegistry::new()
+            .register(AttributeSpec::new("unroll", &[AttributeTarget::Statement]).args(1, Some(1)));
+
+        let found = registry.find(&program, "unroll");
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].0.target(), AttributeTarget::Statement);
+        assert!(registry.check(&program).is_empty());
+    }
+
+    #[test]
+    fn test_enum_attributes() {
+        let source = "\
+@derive(Debug) @deprecated
+enum Shape {
+    @deprecated Empty,
+    Rect { @deprecated w: int, @inline h: int },
+    @anything Circle(int),
+}";
+        let registry = AttributeRegistry::new();
+
+        assert_eq!(
+            errors(&registry, source),
+            vec![
+                AttributeError::InvalidTarget {
+                    name: "inline".to_string(),
+                    target: AttributeTarget::Field,
+                },
+                AttributeError::UnknownAttribute("anything".to_string()),
+            ]
+        );
+        let targets: Vec<_> = registry
+            .find(&parse(source), "deprecated")
+            .into_iter()
+            .map(|(node, _)| node.target())
+            .collect();
+        assert_eq!(
+            targets,
+            vec![
+                AttributeTarget::Enum,
+                AttributeTarget::Variant,
+                AttributeTarget::Field
+            ]
+        );
+    }
+
+    #[test]
+    fn test_statements_inside_match_arms() {
+        let program = parse(
+            "fn f() {
+                let x = match s {
+                    A => { @unroll(2) while x { } 0 }
+                    B if g(x) => h(match t { _ => { @unknown let y = 1; y } }),
+                };
+            }",
+        );
+
+        let found = AttributeRegistry::new().check(&program);
+        let errors: Vec<_> = found.into_iter().map(|d| d.error).collect();
+        assert_eq!(
+            errors,
+            vec![
+                AttributeError::UnknownAttribute("unroll".to_string()),
+                AttributeError::UnknownAttribute("unknown".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_attribute_errors() {
+        let source = "\
+@inline struct S { @test x: int }
+@inline(always) @unknown fn f() { }
+@test @test fn g() { }
+@derive struct T { }";
+        let registry = AttributeRegistry::new();
+
+        assert_eq!(
+            errors(&registry, source),
+            vec![
+                AttributeError::InvalidTarget {
+                    name: "inline".to_string(),
+                    target: AttributeTarget::Struct,
+                },
+                AttributeError::InvalidTarget {
+                    name: "test".to_string(),
+                    target: AttributeTarget::Field,
+                },
+                AttributeError::ArgumentCount {
+                    name: "inline".to_string(),
+                    min: 0,
+                    max: Some(0),
+                    found: 1,
+                },
+                AttributeError::UnknownAttribute("unknown".to_string()),
+                AttributeError::Duplicate("test".to_string()),
+                AttributeError::ArgumentCount {
+                    name: "derive".to_string(),
+                    min: 1,
+                    max: None,
+                    found: 0,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diagnostic_display() {
+        let diagnostics =
+            AttributeRegistry::empty().check(&parse("let x = 1;\n  @inline let y = 2;"));
+
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "error[A0001] 2:3: Unknown attribute @inline"
+        );
+    }
+}
@@ -1,10 +1,77 @@
+use crate::lexer::error::{Location, Severity, TokenError};
+use crate::lexer::incremental::{Lexed, TextEdit};
+use crate::lexer::keywords::Keywords;
 use crate::lexer::lexer::Lexer;
-use crate::lexer::token::{Operation, Punctuation, Reserved, Token};
+use crate::lexer::symbol::Symbol;
+use crate::lexer::token::{
+    CommentKind, NumberBase, NumericSuffix, OPERATORS, Operation, Punctuation, Reserved,
+    StringKind, Token,
+};
+use crate::lexer::token_stream::TokenStream;
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn int(value: u128) -> Token {
+        Token::Integer {
+            value,
+            base: NumberBase::Decimal,
+            suffix: None,
+        }
+    }
+
+    fn string(value: &str) -> Token {
+        Token::String {
+            value: value.to_string(),
+            kind: StringKind::Plain,
+        }
+    }
+
+    // Relexes `edit` applied to `source` and checks the result against lexing
+    // the edited text from scratch; returns the number of relexed tokens
+    fn relex(source: &str, edit: TextEdit) -> usize {
+        let previous = Lexer::new(source).keep_comments(true).lex();
+        let edited = edit.apply(source);
+        let relexed = Lexer::new(&edited)
+            .keep_comments(true)
+            .relex(&previous, &edit);
+        let expected = Lexer::new(&edited).keep_comments(true).lex();
+
+        let describe = |lexed: &Lexed| -> Vec<_> {
+            lexed
+                .tokens
+                .iter()
+                .map(|t| {
+                    (
+                        t.token.clone(),
+                        t.lexeme.to_string(),
+                        t.line,
+                        t.column,
+                        t.span,
+                    )
+                })
+                .collect()
+        };
+        assert_eq!(describe(&relexed), describe(&expected));
+        assert_eq!(relexed.states, expected.states);
+
+        let mut diagnostics = relexed.diagnostics.clone();
+        let mut expected_diagnostics = expected.diagnostics.clone();
+        diagnostics.sort_by_key(|d| (d.start.offset, d.code()));
+        expected_diagnostics.sort_by_key(|d| (d.start.offset, d.code()));
+        assert_eq!(diagnostics, expected_diagnostics);
+
+        relexed.relexed.len()
+    }
+
+    fn float(value: f64) -> Token {
+        Token::Float {
+            value,
+            suffix: None,
+        }
+    }
+
     #[test]
     fn test_multi_char_operators() {
         let mut lexer = Lexer::new("== != >= <=");
@@ -16,6 +83,64 @@ mod tests {
         assert_eq!(tokens[3].token, Token::Operation(Operation::LessEqual));
     }
 
+    #[test]
+    fn test_every_operator_spelling() {
+        for &(text, op) in OPERATORS {
+            let tokens = Lexer::new(text).tokenize();
+
+            assert_eq!(tokens[0].token, Token::Operation(op), "lexing {:?}", text);
+            assert_eq!(tokens[0].lexeme, text);
+            assert_eq!(tokens[1].token, Token::Eof, "lexing {:?}", text);
+        }
+    }
+
+    #[test]
+    fn test_operators_use_longest_match() {
+        let tokens: Vec<_> = Lexer::new("a<<=b>>c&&&d|||e+++f--->g::h=>i!==j")
+            .tokenize()
+            .into_iter()
+            .filter_map(|t| match t.token {
+                Token::Operation(op) => Some(op),
+                _ => None,
+            })
+            .collect();
+
+        assert_eq!(
+            tokens,
+            vec![
+                Operation::ShiftLeft,
+                Operation::Assign,
+                Operation::ShiftRight,
+                Operation::And,
+                Operation::BitAnd,
+                Operation::Or,
+                Operation::BitOr,
+                Operation::Increment,
+                Operation::Add,
+                Operation::Decrement,
+                Operation::Arrow,
+                Operation::PathSeparator,
+                Operation::FatArrow,
+                Operation::NotEqual,
+                Operation::Assign,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_compound_assignment_and_comments() {
+        let tokens = Lexer::new("x /= 2 // half\ny *= ~z /* not */ ^ 1")
+            .keep_comments(true)
+            .tokenize();
+
+        assert_eq!(tokens[1].token, Token::Operation(Operation::DivAssign));
+        assert!(matches!(tokens[3].token, Token::Comment { .. }));
+        assert_eq!(tokens[6].token, Token::Operation(Operation::MulAssign));
+        assert_eq!(tokens[7].token, Token::Operation(Operation::BitNot));
+        assert!(matches!(tokens[9].token, Token::Comment { .. }));
+        assert_eq!(tokens[10].token, Token::Operation(Operation::BitXor));
+    }
+
     #[test]
     fn test_keywords() {
         let mut lexer = Lexer::new("let fn true false");
@@ -32,7 +157,7 @@ mod tests {
         let mut lexer = Lexer::new(r#""hello\nworld""#);
         let tokens = lexer.tokenize();
 
-        assert_eq!(tokens[0].token, Token::String("hello\nworld".to_string()));
+        assert_eq!(tokens[0].token, string("hello\nworld"));
     }
 
     #[test]
@@ -42,10 +167,16 @@ mod tests {
 
         assert_eq!(
             tokens[0].token,
-            Token::Identifier("variable_name".to_string())
+            Token::Identifier(Symbol::intern("variable_name"))
+        );
+        assert_eq!(
+            tokens[1].token,
+            Token::Identifier(Symbol::intern("_private"))
+        );
+        assert_eq!(
+            tokens[2].token,
+            Token::Identifier(Symbol::intern("__dunder"))
         );
-        assert_eq!(tokens[1].token, Token::Identifier("_private".to_string()));
-        assert_eq!(tokens[2].token, Token::Identifier("__dunder".to_string()));
     }
 
     #[test]
@@ -53,8 +184,8 @@ mod tests {
         let mut lexer = Lexer::new("123 456.789");
         let tokens = lexer.tokenize();
 
-        assert_eq!(tokens[0].token, Token::Number(123.0));
-        assert_eq!(tokens[1].token, Token::Number(456.789));
+        assert_eq!(tokens[0].token, int(123));
+        assert_eq!(tokens[1].token, float(456.789));
     }
 
     #[test]
@@ -63,9 +194,9 @@ mod tests {
         let tokens = lexer.tokenize();
 
         assert_eq!(tokens[0].token, Token::Operation(Operation::Subtract));
-        assert_eq!(tokens[1].token, Token::Number(123.0));
+        assert_eq!(tokens[1].token, int(123));
         assert_eq!(tokens[2].token, Token::Operation(Operation::Subtract));
-        assert_eq!(tokens[3].token, Token::Number(456.789));
+        assert_eq!(tokens[3].token, float(456.789));
     }
 
     #[test]
@@ -160,16 +291,61 @@ mod tests {
         assert_eq!(tokens[16].token, Token::Reserved(Reserved::False));
     }
 
+    #[test]
+    fn test_keyword_casing() {
+        let tokens = Lexer::new("Return Print FN rEturn").tokenize();
+
+        assert_eq!(tokens[0].token, Token::Reserved(Reserved::Return));
+        assert_eq!(tokens[1].token, Token::Reserved(Reserved::Print));
+        assert_eq!(tokens[2].token, Token::Identifier(Symbol::intern("FN")));
+        assert_eq!(tokens[3].token, Token::Identifier(Symbol::intern("rEturn")));
+    }
+
+    #[test]
+    fn test_only_listed_keywords_are_capitalized() {
+        let tokens = Lexer::new("Module Static Let Fn Match Struct").tokenize();
+
+        assert_eq!(tokens[0].token, Token::Identifier(Symbol::intern("Module")));
+        assert_eq!(tokens[1].token, Token::Identifier(Symbol::intern("Static")));
+        assert_eq!(tokens[2].token, Token::Identifier(Symbol::intern("Let")));
+        assert_eq!(tokens[3].token, Token::Identifier(Symbol::intern("Fn")));
+        assert_eq!(tokens[4].token, Token::Identifier(Symbol::intern("Match")));
+        assert_eq!(tokens[5].token, Token::Reserved(Reserved::Struct));
+    }
+
+    #[test]
+    fn test_strict_keywords() {
+        let tokens = Lexer::new("return Return Print")
+            .keywords(Keywords::strict())
+            .tokenize();
+
+        assert_eq!(tokens[0].token, Token::Reserved(Reserved::Return));
+        assert_eq!(tokens[1].token, Token::Identifier(Symbol::intern("Return")));
+        assert_eq!(tokens[2].token, Token::Identifier(Symbol::intern("Print")));
+    }
+
+    #[test]
+    fn test_contextual_keywords_lex_as_identifiers() {
+        let tokens = Lexer::new("in type macro module")
+            .keywords(Keywords::default().contextual(Reserved::Type))
+            .tokenize();
+
+        assert_eq!(tokens[0].token, Token::Identifier(Symbol::intern("in")));
+        assert_eq!(tokens[1].token, Token::Identifier(Symbol::intern("type")));
+        assert_eq!(tokens[2].token, Token::Reserved(Reserved::Macro));
+        assert_eq!(tokens[3].token, Token::Reserved(Reserved::Module));
+    }
+
     #[test]
     fn test_identifiers_with_numbers() {
         let mut lexer = Lexer::new("var1 v2ar var3_ _4var _5_var");
         let tokens = lexer.tokenize();
 
-        assert_eq!(tokens[0].token, Token::Identifier("var1".to_string()));
-        assert_eq!(tokens[1].token, Token::Identifier("v2ar".to_string()));
-        assert_eq!(tokens[2].token, Token::Identifier("var3_".to_string()));
-        assert_eq!(tokens[3].token, Token::Identifier("_4var".to_string()));
-        assert_eq!(tokens[4].token, Token::Identifier("_5_var".to_string()));
+        assert_eq!(tokens[0].token, Token::Identifier(Symbol::intern("var1")));
+        assert_eq!(tokens[1].token, Token::Identifier(Symbol::intern("v2ar")));
+        assert_eq!(tokens[2].token, Token::Identifier(Symbol::intern("var3_")));
+        assert_eq!(tokens[3].token, Token::Identifier(Symbol::intern("_4var")));
+        assert_eq!(tokens[4].token, Token::Identifier(Symbol::intern("_5_var")));
     }
 
     #[test]
@@ -177,7 +353,7 @@ mod tests {
         let mut lexer = Lexer::new(r#""hello   world""#);
         let tokens = lexer.tokenize();
 
-        assert_eq!(tokens[0].token, Token::String("hello   world".to_string()));
+        assert_eq!(tokens[0].token, string("hello   world"));
     }
 
     #[test]
@@ -185,7 +361,7 @@ mod tests {
         let mut lexer = Lexer::new(r#""!@#$%^&*()""#);
         let tokens = lexer.tokenize();
 
-        assert_eq!(tokens[0].token, Token::String("!@#$%^&*()".to_string()));
+        assert_eq!(tokens[0].token, string("!@#$%^&*()"));
     }
 
     #[test]
@@ -193,7 +369,7 @@ mod tests {
         let mut lexer = Lexer::new(r#""""#);
         let tokens = lexer.tokenize();
 
-        assert_eq!(tokens[0].token, Token::String("".to_string()));
+        assert_eq!(tokens[0].token, string(""));
     }
 
     #[test]
@@ -201,10 +377,7 @@ mod tests {
         let mut lexer = Lexer::new(r#""Hello \"World\"!""#);
         let tokens = lexer.tokenize();
 
-        assert_eq!(
-            tokens[0].token,
-            Token::String("Hello \"World\"!".to_string())
-        );
+        assert_eq!(tokens[0].token, string("Hello \"World\"!"));
     }
 
     #[test]
@@ -212,18 +385,58 @@ mod tests {
         let mut lexer = Lexer::new(r#""Hello\n\t\"World\"\n!""#);
         let tokens = lexer.tokenize();
 
+        assert_eq!(tokens[0].token, string("Hello\n\t\"World\"\n!"));
+    }
+
+    #[test]
+    fn test_string_numeric_escapes() {
+        let tokens = Lexer::new(r#""\x41\0\u{e9}\u{1F600}""#).tokenize();
+
+        assert_eq!(tokens[0].token, string("A\0é😀"));
+    }
+
+    #[test]
+    fn test_string_line_continuation() {
+        let tokens = Lexer::new("\"one \\\n    two \\\r\n\tthree\" x").tokenize();
+
+        assert_eq!(tokens[0].token, string("one two three"));
+        assert_eq!(tokens[1].token, Token::Identifier(Symbol::intern("x")));
+        assert_eq!((tokens[1].line, tokens[1].column), (3, 9));
+    }
+
+    #[test]
+    fn test_string_invalid_escapes_point_at_escape() {
+        let source = "x = \"ok \\q and \\u{110000}\\x4\" y";
+        let (tokens, diagnostics) = Lexer::new(source).tokenize_with_diagnostics();
+
         assert_eq!(
-            tokens[0].token,
-            Token::String("Hello\n\t\"World\"\n!".to_string())
+            tokens[2].token,
+            Token::Invalid("\"ok \\q and \\u{110000}\\x4\"".to_string())
+        );
+        assert_eq!(tokens[3].token, Token::Identifier(Symbol::intern("y")));
+
+        let reported: Vec<_> = diagnostics
+            .iter()
+            .map(|d| (d.error.clone(), d.start.column, d.end.column))
+            .collect();
+        assert_eq!(
+            reported,
+            vec![
+                (TokenError::InvalidEscape("\\q".to_string()), 9, 11),
+                (TokenError::InvalidEscape("\\u{110000}".to_string()), 16, 26),
+                (TokenError::InvalidEscape("\\x".to_string()), 26, 28),
+            ]
         );
+        assert_eq!(diagnostics[0].span().start_byte, 8);
     }
 
     #[test]
     fn test_invalid_tokens() {
-        let mut lexer = Lexer::new("@ # $");
+        let mut lexer = Lexer::new("@ # $ `");
         let tokens = lexer.tokenize();
 
-        assert!(matches!(tokens[2].token, Token::Invalid(_)));
+        assert_eq!(tokens[2].token, Token::Punctuation(Punctuation::Dollar));
+        assert!(matches!(tokens[3].token, Token::Invalid(_)));
     }
 
     #[test]
@@ -243,7 +456,7 @@ mod tests {
         let tokens = lexer.tokenize();
 
         assert_eq!(tokens[0].token, Token::Reserved(Reserved::Fn));
-        assert_eq!(tokens[1].token, Token::Identifier("test".to_string()));
+        assert_eq!(tokens[1].token, Token::Identifier(Symbol::intern("test")));
         assert_eq!(tokens[2].token, Token::Punctuation(Punctuation::OpenParen));
         assert_eq!(tokens[3].token, Token::Punctuation(Punctuation::CloseParen));
         assert_eq!(tokens[4].token, Token::Punctuation(Punctuation::OpenBrace));
@@ -270,14 +483,14 @@ mod tests {
         let mut lexer = Lexer::new("x + y * (z - w)");
         let tokens = lexer.tokenize();
 
-        assert_eq!(tokens[0].token, Token::Identifier("x".to_string()));
+        assert_eq!(tokens[0].token, Token::Identifier(Symbol::intern("x")));
         assert_eq!(tokens[1].token, Token::Operation(Operation::Add));
-        assert_eq!(tokens[2].token, Token::Identifier("y".to_string()));
+        assert_eq!(tokens[2].token, Token::Identifier(Symbol::intern("y")));
         assert_eq!(tokens[3].token, Token::Operation(Operation::Multiply));
         assert_eq!(tokens[4].token, Token::Punctuation(Punctuation::OpenParen));
-        assert_eq!(tokens[5].token, Token::Identifier("z".to_string()));
+        assert_eq!(tokens[5].token, Token::Identifier(Symbol::intern("z")));
         assert_eq!(tokens[6].token, Token::Operation(Operation::Subtract));
-        assert_eq!(tokens[7].token, Token::Identifier("w".to_string()));
+        assert_eq!(tokens[7].token, Token::Identifier(Symbol::intern("w")));
         assert_eq!(tokens[8].token, Token::Punctuation(Punctuation::CloseParen));
     }
 
@@ -287,18 +500,18 @@ mod tests {
         let tokens = lexer.tokenize();
 
         assert_eq!(tokens[0].token, Token::Reserved(Reserved::If));
-        assert_eq!(tokens[1].token, Token::Identifier("x".to_string()));
+        assert_eq!(tokens[1].token, Token::Identifier(Symbol::intern("x")));
         assert_eq!(tokens[2].token, Token::Operation(Operation::Greater));
-        assert_eq!(tokens[3].token, Token::Number(0.0));
+        assert_eq!(tokens[3].token, int(0));
         assert_eq!(tokens[4].token, Token::Punctuation(Punctuation::OpenBrace));
         assert_eq!(tokens[5].token, Token::Reserved(Reserved::Print));
-        assert_eq!(tokens[6].token, Token::Identifier("x".to_string()));
+        assert_eq!(tokens[6].token, Token::Identifier(Symbol::intern("x")));
         assert_eq!(tokens[7].token, Token::Punctuation(Punctuation::Semicolon));
         assert_eq!(tokens[8].token, Token::Punctuation(Punctuation::CloseBrace));
         assert_eq!(tokens[9].token, Token::Reserved(Reserved::Else));
         assert_eq!(tokens[10].token, Token::Punctuation(Punctuation::OpenBrace));
         assert_eq!(tokens[11].token, Token::Reserved(Reserved::Print));
-        assert_eq!(tokens[12].token, Token::Number(0.0));
+        assert_eq!(tokens[12].token, int(0));
         assert_eq!(tokens[13].token, Token::Punctuation(Punctuation::Semicolon));
         assert_eq!(
             tokens[14].token,
@@ -312,15 +525,15 @@ mod tests {
         let tokens = lexer.tokenize();
 
         assert_eq!(tokens[0].token, Token::Reserved(Reserved::While));
-        assert_eq!(tokens[1].token, Token::Identifier("i".to_string()));
+        assert_eq!(tokens[1].token, Token::Identifier(Symbol::intern("i")));
         assert_eq!(tokens[2].token, Token::Operation(Operation::Less));
-        assert_eq!(tokens[3].token, Token::Number(10.0));
+        assert_eq!(tokens[3].token, int(10));
         assert_eq!(tokens[4].token, Token::Punctuation(Punctuation::OpenBrace));
-        assert_eq!(tokens[5].token, Token::Identifier("i".to_string()));
+        assert_eq!(tokens[5].token, Token::Identifier(Symbol::intern("i")));
         assert_eq!(tokens[6].token, Token::Operation(Operation::Assign));
-        assert_eq!(tokens[7].token, Token::Identifier("i".to_string()));
+        assert_eq!(tokens[7].token, Token::Identifier(Symbol::intern("i")));
         assert_eq!(tokens[8].token, Token::Operation(Operation::Add));
-        assert_eq!(tokens[9].token, Token::Number(1.0));
+        assert_eq!(tokens[9].token, int(1));
         assert_eq!(tokens[10].token, Token::Punctuation(Punctuation::Semicolon));
         assert_eq!(
             tokens[11].token,
@@ -334,24 +547,24 @@ mod tests {
         let tokens = lexer.tokenize();
 
         assert_eq!(tokens[0].token, Token::Punctuation(Punctuation::OpenParen));
-        assert_eq!(tokens[1].token, Token::Identifier("a".to_string()));
+        assert_eq!(tokens[1].token, Token::Identifier(Symbol::intern("a")));
         assert_eq!(tokens[2].token, Token::Operation(Operation::Add));
-        assert_eq!(tokens[3].token, Token::Identifier("b".to_string()));
+        assert_eq!(tokens[3].token, Token::Identifier(Symbol::intern("b")));
         assert_eq!(tokens[4].token, Token::Punctuation(Punctuation::CloseParen));
         assert_eq!(tokens[5].token, Token::Operation(Operation::Multiply));
         assert_eq!(tokens[6].token, Token::Punctuation(Punctuation::OpenParen));
-        assert_eq!(tokens[7].token, Token::Identifier("c".to_string()));
+        assert_eq!(tokens[7].token, Token::Identifier(Symbol::intern("c")));
         assert_eq!(tokens[8].token, Token::Operation(Operation::Subtract));
-        assert_eq!(tokens[9].token, Token::Identifier("d".to_string()));
+        assert_eq!(tokens[9].token, Token::Identifier(Symbol::intern("d")));
         assert_eq!(
             tokens[10].token,
             Token::Punctuation(Punctuation::CloseParen)
         );
         assert_eq!(tokens[11].token, Token::Operation(Operation::Divide));
         assert_eq!(tokens[12].token, Token::Punctuation(Punctuation::OpenParen));
-        assert_eq!(tokens[13].token, Token::Identifier("e".to_string()));
+        assert_eq!(tokens[13].token, Token::Identifier(Symbol::intern("e")));
         assert_eq!(tokens[14].token, Token::Operation(Operation::Add));
-        assert_eq!(tokens[15].token, Token::Identifier("f".to_string()));
+        assert_eq!(tokens[15].token, Token::Identifier(Symbol::intern("f")));
         assert_eq!(
             tokens[16].token,
             Token::Punctuation(Punctuation::CloseParen)
@@ -364,25 +577,978 @@ mod tests {
         let tokens = lexer.tokenize();
 
         assert_eq!(tokens[0].token, Token::Reserved(Reserved::Fn));
-        assert_eq!(tokens[1].token, Token::Identifier("sum".to_string()));
+        assert_eq!(tokens[1].token, Token::Identifier(Symbol::intern("sum")));
         assert_eq!(tokens[2].token, Token::Punctuation(Punctuation::OpenParen));
-        assert_eq!(tokens[3].token, Token::Identifier("a".to_string()));
+        assert_eq!(tokens[3].token, Token::Identifier(Symbol::intern("a")));
         assert_eq!(tokens[4].token, Token::Punctuation(Punctuation::Comma));
-        assert_eq!(tokens[5].token, Token::Identifier("b".to_string()));
+        assert_eq!(tokens[5].token, Token::Identifier(Symbol::intern("b")));
         assert_eq!(tokens[6].token, Token::Punctuation(Punctuation::Comma));
-        assert_eq!(tokens[7].token, Token::Identifier("c".to_string()));
+        assert_eq!(tokens[7].token, Token::Identifier(Symbol::intern("c")));
         assert_eq!(tokens[8].token, Token::Punctuation(Punctuation::CloseParen));
         assert_eq!(tokens[9].token, Token::Punctuation(Punctuation::OpenBrace));
         assert_eq!(tokens[10].token, Token::Reserved(Reserved::Return));
-        assert_eq!(tokens[11].token, Token::Identifier("a".to_string()));
+        assert_eq!(tokens[11].token, Token::Identifier(Symbol::intern("a")));
         assert_eq!(tokens[12].token, Token::Operation(Operation::Add));
-        assert_eq!(tokens[13].token, Token::Identifier("b".to_string()));
+        assert_eq!(tokens[13].token, Token::Identifier(Symbol::intern("b")));
         assert_eq!(tokens[14].token, Token::Operation(Operation::Add));
-        assert_eq!(tokens[15].token, Token::Identifier("c".to_string()));
+        assert_eq!(tokens[15].token, Token::Identifier(Symbol::intern("c")));
         assert_eq!(tokens[16].token, Token::Punctuation(Punctuation::Semicolon));
         assert_eq!(
             tokens[17].token,
             Token::Punctuation(Punctuation::CloseBrace)
         );
     }
+
+    #[test]
+    fn test_comments_are_dropped_by_default() {
+        let mut lexer = Lexer::new("x // trailing comment\n/* block */ y");
+        let tokens = lexer.tokenize();
+
+        assert_eq!(tokens[0].token, Token::Identifier(Symbol::intern("x")));
+        assert_eq!(tokens[1].token, Token::Newline);
+        assert_eq!(tokens[2].token, Token::Identifier(Symbol::intern("y")));
+        assert_eq!(tokens[3].token, Token::Eof);
+    }
+
+    #[test]
+    fn test_comments_kept_on_request() {
+        let mut lexer = Lexer::new("// line\n/* block */").keep_comments(true);
+        let tokens = lexer.tokenize();
+
+        assert_eq!(
+            tokens[0].token,
+            Token::Comment {
+                kind: CommentKind::Line,
+                text: " line".to_string()
+            }
+        );
+        assert_eq!(tokens[0].lexeme, "// line");
+        assert_eq!(tokens[1].token, Token::Newline);
+        assert_eq!(
+            tokens[2].token,
+            Token::Comment {
+                kind: CommentKind::Block,
+                text: " block ".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_nested_block_comment() {
+        let mut lexer = Lexer::new("/* outer /* inner */ still outer */ x").keep_comments(true);
+        let tokens = lexer.tokenize();
+
+        assert_eq!(
+            tokens[0].token,
+            Token::Comment {
+                kind: CommentKind::Block,
+                text: " outer /* inner */ still outer ".to_string()
+            }
+        );
+        assert_eq!(tokens[1].token, Token::Identifier(Symbol::intern("x")));
+    }
+
+    #[test]
+    fn test_multiline_block_comment_positions() {
+        let mut lexer = Lexer::new("/*\n * doc\n */\nfoo");
+        let tokens = lexer.tokenize();
+
+        assert_eq!(tokens[0].token, Token::Newline);
+        assert_eq!(tokens[1].token, Token::Identifier(Symbol::intern("foo")));
+        assert_eq!(tokens[1].line, 4);
+        assert_eq!(tokens[1].column, 1);
+    }
+
+    #[test]
+    fn test_unterminated_block_comment() {
+        let mut lexer = Lexer::new("/* never closed /* nested */");
+        let tokens = lexer.tokenize();
+
+        match &tokens[0].token {
+            Token::Invalid(msg) => assert!(msg.contains("Unterminated block comment")),
+            _ => panic!("Expected Invalid token for unterminated block comment"),
+        }
+    }
+
+    #[test]
+    fn test_diagnostics_collects_all_errors() {
+        let mut lexer = Lexer::new("a § b\n` \"open");
+        let (tokens, diagnostics) = lexer.tokenize_with_diagnostics();
+
+        assert_eq!(diagnostics.len(), 3);
+        assert_eq!(diagnostics[0].error, TokenError::UnexpectedCharacter('§'));
+        assert_eq!(diagnostics[1].error, TokenError::UnexpectedCharacter('`'));
+        assert_eq!(diagnostics[2].error, TokenError::UnterminatedString('"'));
+        assert_eq!(diagnostics[2].code(), "L0004");
+
+        // The token stream is still complete
+        assert_eq!(tokens[2].token, Token::Identifier(Symbol::intern("b")));
+        assert!(matches!(tokens[tokens.len() - 1].token, Token::Eof));
+    }
+
+    #[test]
+    fn test_diagnostic_spans() {
+        let mut lexer = Lexer::new("x\n  /* open");
+        let (_, diagnostics) = lexer.tokenize_with_diagnostics();
+
+        assert_eq!(diagnostics[0].error, TokenError::UnterminatedComment);
+        assert_eq!(
+            diagnostics[0].start,
+            Location {
+                line: 2,
+                column: 3,
+                offset: 4
+            }
+        );
+        assert_eq!(diagnostics[0].end.offset, 11);
+        assert_eq!(diagnostics[0].recovery, diagnostics[0].end);
+    }
+
+    #[test]
+    fn test_diagnostic_display() {
+        let mut lexer = Lexer::new("let §");
+        let (_, diagnostics) = lexer.tokenize_with_diagnostics();
+
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "error[L0002] 1:5: Unexpected character '§'"
+        );
+    }
+
+    #[test]
+    fn test_clean_input_has_no_diagnostics() {
+        let mut lexer = Lexer::new("fn main() { return 0; }");
+        let (_, diagnostics) = lexer.tokenize_with_diagnostics();
+
+        assert!(diagnostics.is_empty());
+    }
+
+    #[test]
+    fn test_prefixed_integers() {
+        let mut lexer = Lexer::new("0x1F 0o17 0b1010 075");
+        let tokens = lexer.tokenize();
+
+        let expected = [
+            (31, NumberBase::Hexadecimal),
+            (15, NumberBase::Octal),
+            (10, NumberBase::Binary),
+            (61, NumberBase::Octal),
+        ];
+        for (token, (value, base)) in tokens.iter().zip(expected) {
+            assert_eq!(
+                token.token,
+                Token::Integer {
+                    value,
+                    base,
+                    suffix: None
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn test_digit_separators() {
+        let mut lexer = Lexer::new("1_000_000 0xFF_FF 3_000.000_5");
+        let tokens = lexer.tokenize();
+
+        assert_eq!(tokens[0].token, int(1_000_000));
+        assert_eq!(tokens[0].lexeme, "1_000_000");
+        assert_eq!(
+            tokens[1].token,
+            Token::Integer {
+                value: 0xFFFF,
+                base: NumberBase::Hexadecimal,
+                suffix: None
+            }
+        );
+        assert_eq!(tokens[2].token, float(3000.0005));
+    }
+
+    #[test]
+    fn test_numeric_suffixes() {
+        let mut lexer = Lexer::new("255u8 -12i64 0xFFu16 2.5f32 1f64 18446744073709551615ULL");
+        let tokens = lexer.tokenize();
+
+        assert_eq!(
+            tokens[0].token,
+            Token::Integer {
+                value: 255,
+                base: NumberBase::Decimal,
+                suffix: Some(NumericSuffix::U8)
+            }
+        );
+        assert_eq!(
+            tokens[2].token,
+            Token::Integer {
+                value: 12,
+                base: NumberBase::Decimal,
+                suffix: Some(NumericSuffix::I64)
+            }
+        );
+        assert_eq!(
+            tokens[3].token,
+            Token::Integer {
+                value: 0xFF,
+                base: NumberBase::Hexadecimal,
+                suffix: Some(NumericSuffix::U16)
+            }
+        );
+        assert_eq!(
+            tokens[4].token,
+            Token::Float {
+                value: 2.5,
+                suffix: Some(NumericSuffix::F32)
+            }
+        );
+        assert_eq!(
+            tokens[5].token,
+            Token::Float {
+                value: 1.0,
+                suffix: Some(NumericSuffix::F64)
+            }
+        );
+        assert_eq!(
+            tokens[6].token,
+            Token::Integer {
+                value: 18446744073709551615,
+                base: NumberBase::Decimal,
+                suffix: Some(NumericSuffix::UnsignedLongLong)
+            }
+        );
+    }
+
+    #[test]
+    fn test_large_integers_keep_precision() {
+        let mut lexer = Lexer::new("340282366920938463463374607431768211455 9007199254740993");
+        let tokens = lexer.tokenize();
+
+        assert_eq!(tokens[0].token, int(u128::MAX));
+        assert_eq!(tokens[1].token, int(9007199254740993));
+    }
+
+    #[test]
+    fn test_invalid_integer_literals() {
+        let mut lexer = Lexer::new("0b102 089 0x 12abc 340282366920938463463374607431768211456");
+        let (_, diagnostics) = lexer.tokenize_with_diagnostics();
+
+        assert_eq!(
+            diagnostics[0].error,
+            TokenError::InvalidDigit {
+                digit: '2',
+                base: NumberBase::Binary
+            }
+        );
+        assert_eq!(
+            diagnostics[1].error,
+            TokenError::InvalidDigit {
+                digit: '8',
+                base: NumberBase::Octal
+            }
+        );
+        assert_eq!(
+            diagnostics[2].error,
+            TokenError::InvalidNumber("0x".to_string())
+        );
+        assert_eq!(
+            diagnostics[3].error,
+            TokenError::InvalidSuffix("abc".to_string())
+        );
+        assert!(matches!(
+            diagnostics[4].error,
+            TokenError::IntegerOverflow(_)
+        ));
+        assert_eq!(diagnostics.len(), 5);
+    }
+
+    #[test]
+    fn test_scientific_notation() {
+        let mut lexer = Lexer::new("1.23e-4 5E10 6.02e+23 2e3f32");
+        let tokens = lexer.tokenize();
+
+        assert_eq!(tokens[0].token, float(1.23e-4));
+        assert_eq!(tokens[1].token, float(5e10));
+        assert_eq!(tokens[2].token, float(6.02e23));
+        assert_eq!(
+            tokens[3].token,
+            Token::Float {
+                value: 2e3,
+                suffix: Some(NumericSuffix::F32)
+            }
+        );
+        assert_eq!(tokens[4].token, Token::Eof);
+    }
+
+    #[test]
+    fn test_leading_dot_float() {
+        let mut lexer = Lexer::new("x = .5 + -.25e1;");
+        let tokens = lexer.tokenize();
+
+        assert_eq!(tokens[2].token, float(0.5));
+        assert_eq!(tokens[5].token, float(2.5));
+        assert_eq!(tokens[5].lexeme, ".25e1");
+    }
+
+    #[test]
+    fn test_dot_after_operand_is_member_access() {
+        let mut lexer = Lexer::new("pair.0 1.abs() 1..5");
+        let tokens = lexer.tokenize();
+
+        assert_eq!(tokens[0].token, Token::Identifier(Symbol::intern("pair")));
+        assert_eq!(tokens[1].token, Token::Punctuation(Punctuation::Dot));
+        assert_eq!(tokens[2].token, int(0));
+        assert_eq!(tokens[3].token, int(1));
+        assert_eq!(tokens[4].token, Token::Punctuation(Punctuation::Dot));
+        assert_eq!(tokens[5].token, Token::Identifier(Symbol::intern("abs")));
+        assert_eq!(tokens[8].token, int(1));
+        assert_eq!(tokens[9].token, Token::Punctuation(Punctuation::Dot));
+        assert_eq!(tokens[10].token, Token::Punctuation(Punctuation::Dot));
+        assert_eq!(tokens[11].token, int(5));
+    }
+
+    #[test]
+    fn test_float_lexeme_round_trip() {
+        let source = "1.23e-4 6.02E+23 1_000.5e3 .5 0.0 3e7f64";
+        let mut lexer = Lexer::new(source);
+        let tokens = lexer.tokenize();
+
+        let lexemes: Vec<&str> = tokens[..6].iter().map(|t| t.lexeme).collect();
+        assert_eq!(lexemes, source.split(' ').collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_invalid_exponents() {
+        let mut lexer = Lexer::new("1e+ 2.5e3u8");
+        let (_, diagnostics) = lexer.tokenize_with_diagnostics();
+
+        assert_eq!(
+            diagnostics[0].error,
+            TokenError::InvalidNumber("1e+".to_string())
+        );
+        assert_eq!(
+            diagnostics[1].error,
+            TokenError::InvalidSuffix("2.5e3u8".to_string())
+        );
+    }
+
+    #[test]
+    fn test_unicode_identifiers() {
+        let mut lexer = Lexer::new("café 变量 _ñandú δx");
+        let (tokens, diagnostics) = lexer.tokenize_with_diagnostics();
+
+        assert_eq!(tokens[0].token, Token::Identifier(Symbol::intern("café")));
+        assert_eq!(tokens[1].token, Token::Identifier(Symbol::intern("变量")));
+        assert_eq!(tokens[2].token, Token::Identifier(Symbol::intern("_ñandú")));
+        assert_eq!(tokens[3].token, Token::Identifier(Symbol::intern("δx")));
+        assert!(diagnostics.is_empty());
+    }
+
+    #[test]
+    fn test_identifiers_are_nfc_normalized() {
+        // "e" followed by a combining acute accent
+        let mut lexer = Lexer::new("cafe\u{301} café");
+        let tokens = lexer.tokenize();
+
+        assert_eq!(tokens[0].token, tokens[1].token);
+        assert_eq!(tokens[0].lexeme, "cafe\u{301}");
+    }
+
+    #[test]
+    fn test_non_identifier_symbols_are_invalid() {
+        let mut lexer = Lexer::new("a → b");
+        let (tokens, diagnostics) = lexer.tokenize_with_diagnostics();
+
+        assert!(matches!(tokens[1].token, Token::Invalid(_)));
+        assert_eq!(diagnostics[0].error, TokenError::UnexpectedCharacter('→'));
+    }
+
+    #[test]
+    fn test_identifier_lints_are_opt_in() {
+        // The second `a` is CYRILLIC SMALL LETTER A
+        let source = "paypal p\u{430}ypal";
+
+        let (_, diagnostics) = Lexer::new(source).tokenize_with_diagnostics();
+        assert!(diagnostics.is_empty());
+
+        let (_, diagnostics) = Lexer::new(source)
+            .lint_identifiers(true)
+            .tokenize_with_diagnostics();
+        assert_eq!(
+            diagnostics[0].error,
+            TokenError::MixedScriptIdentifier("p\u{430}ypal".to_string())
+        );
+        assert_eq!(
+            diagnostics[1].error,
+            TokenError::ConfusableIdentifier {
+                name: "p\u{430}ypal".to_string(),
+                similar_to: "paypal".to_string()
+            }
+        );
+        assert!(
+            diagnostics
+                .iter()
+                .all(|d| d.severity() == Severity::Warning)
+        );
+    }
+
+    #[test]
+    fn test_ascii_lookalikes_are_not_linted() {
+        let mut lexer = Lexer::new("rn m").lint_identifiers(true);
+        let (_, diagnostics) = lexer.tokenize_with_diagnostics();
+
+        assert!(diagnostics.is_empty());
+    }
+
+    #[test]
+    fn test_lexemes_borrow_from_source() {
+        let source = String::from("let value = \"text\";");
+        let tokens = Lexer::new(&source).tokenize();
+
+        let range = source.as_bytes().as_ptr_range();
+        for token in &tokens {
+            assert!(range.contains(&token.lexeme.as_ptr()) || token.lexeme.is_empty());
+            assert_eq!(
+                &source[token.span.start_byte..token.span.end_byte],
+                token.lexeme
+            );
+        }
+    }
+
+    #[test]
+    fn test_identifiers_are_interned() {
+        let tokens = Lexer::new("alpha beta alpha").tokenize();
+
+        assert_eq!(tokens[0].token, tokens[2].token);
+        assert_ne!(tokens[0].token, tokens[1].token);
+
+        let Token::Identifier(symbol) = tokens[1].token else {
+            panic!("Expected identifier");
+        };
+        assert_eq!(symbol, Symbol::intern("beta"));
+        assert_eq!(symbol.as_str(), "beta");
+        assert_eq!(format!("{:?}", tokens[1].token), "Identifier(\"beta\")");
+    }
+
+    #[test]
+    fn test_multibyte_positions() {
+        let tokens = Lexer::new("ü + 变量 * 2").tokenize();
+
+        assert_eq!(tokens[2].token, Token::Identifier(Symbol::intern("变量")));
+        assert_eq!(tokens[2].column, 5);
+        assert_eq!(tokens[2].span.start_byte, 5);
+        assert_eq!(tokens[2].span.end_byte, 11);
+        assert_eq!(tokens[4].column, 10);
+    }
+
+    #[test]
+    fn test_iterator_matches_tokenize() {
+        let source = "fn f(a) { // note\n return a * 2; }";
+
+        let eager = Lexer::new(source).tokenize();
+        let streamed: Vec<_> = Lexer::new(source).collect();
+
+        assert_eq!(eager.len(), streamed.len());
+        for (a, b) in eager.iter().zip(&streamed) {
+            assert_eq!(a.token, b.token);
+            assert_eq!(a.span, b.span);
+        }
+        assert_eq!(streamed.last().unwrap().token, Token::Eof);
+    }
+
+    #[test]
+    fn test_iterator_is_lazy() {
+        let mut lexer = Lexer::new("a b \"unterminated");
+
+        assert_eq!(
+            lexer.next().unwrap().token,
+            Token::Identifier(Symbol::intern("a"))
+        );
+        // Nothing past the first token has been scanned yet
+        assert!(lexer.diagnostics().is_empty());
+    }
+
+    #[test]
+    fn test_token_stream_lookahead() {
+        let mut stream = TokenStream::new(Lexer::new("x = 1;"));
+
+        assert_eq!(stream.peek_nth(2).token, int(1));
+        assert_eq!(stream.peek().token, Token::Identifier(Symbol::intern("x")));
+        assert_eq!(
+            stream.next().unwrap().token,
+            Token::Identifier(Symbol::intern("x"))
+        );
+        assert_eq!(stream.peek().token, Token::Operation(Operation::Assign));
+
+        // Lookahead past the end keeps returning the end-of-file token
+        assert_eq!(stream.peek_nth(10).token, Token::Eof);
+        assert_eq!(stream.peek_nth(10).column, 7);
+        assert_eq!(stream.by_ref().count(), 4);
+        assert!(stream.is_at_end());
+    }
+
+    #[test]
+    fn test_char_literals() {
+        let tokens = Lexer::new(r"'a' '\n' '\'' 'é' '变'").tokenize();
+
+        assert_eq!(tokens[0].token, Token::Char('a'));
+        assert_eq!(tokens[1].token, Token::Char('\n'));
+        assert_eq!(tokens[2].token, Token::Char('\''));
+        assert_eq!(tokens[3].token, Token::Char('é'));
+        assert_eq!(tokens[4].token, Token::Char('变'));
+    }
+
+    #[test]
+    fn test_char_literal_hex_and_unicode_escapes() {
+        let tokens = Lexer::new(r"'\x41' '\u{1F600}' '\u{e9}' '\0'").tokenize();
+
+        assert_eq!(tokens[0].token, Token::Char('A'));
+        assert_eq!(tokens[1].token, Token::Char('😀'));
+        assert_eq!(tokens[2].token, Token::Char('é'));
+        assert_eq!(tokens[3].token, Token::Char('\0'));
+    }
+
+    #[test]
+    fn test_single_quotes_are_not_strings() {
+        let (tokens, diagnostics) = Lexer::new("'hello' x").tokenize_with_diagnostics();
+
+        assert!(matches!(tokens[0].token, Token::Invalid(_)));
+        assert_eq!(
+            diagnostics[0].error,
+            TokenError::MultiCharLiteral("'hello'".to_string())
+        );
+        assert_eq!(tokens[1].token, Token::Identifier(Symbol::intern("x")));
+    }
+
+    #[test]
+    fn test_invalid_char_literals() {
+        let source = "'' 'e\u{301}' '\\u{D800}' '\\x4' '\\q' 'a";
+        let (_, diagnostics) = Lexer::new(source).tokenize_with_diagnostics();
+
+        let errors: Vec<_> = diagnostics.into_iter().map(|d| d.error).collect();
+        assert_eq!(
+            errors,
+            vec![
+                TokenError::EmptyCharLiteral,
+                TokenError::MultiCharLiteral("'e\u{301}'".to_string()),
+                TokenError::InvalidEscape("\\u{D800}".to_string()),
+                TokenError::InvalidEscape("\\x".to_string()),
+                TokenError::InvalidEscape("\\q".to_string()),
+                TokenError::UnterminatedChar,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_backslash_before_line_break_in_char() {
+        let source = "let c = '\\\nlet x = 1; }";
+        let (tokens, diagnostics) = Lexer::new(source).tokenize_with_diagnostics();
+
+        let errors: Vec<_> = diagnostics.into_iter().map(|d| d.error).collect();
+        assert_eq!(
+            errors,
+            vec![
+                TokenError::InvalidEscape("\\".to_string()),
+                TokenError::UnterminatedChar,
+            ]
+        );
+        assert_eq!(tokens[3].lexeme, "'\\");
+
+        let rest: Vec<_> = tokens[4..].iter().map(|t| t.lexeme).collect();
+        assert_eq!(rest, vec!["\n", "let", "x", "=", "1", ";", "}", ""]);
+        assert_eq!(tokens[5].line, 2);
+    }
+
+    #[test]
+    fn test_raw_strings() {
+        let source = r###"r"C:\path\n" r#"say "hi" \q"# r##"a "# b"## r x"###;
+        let tokens = Lexer::new(source).tokenize();
+
+        assert_eq!(
+            tokens[0].token,
+            Token::String {
+                value: r"C:\path\n".to_string(),
+                kind: StringKind::Raw { hashes: 0 },
+            }
+        );
+        assert_eq!(
+            tokens[1].token,
+            Token::String {
+                value: r#"say "hi" \q"#.to_string(),
+                kind: StringKind::Raw { hashes: 1 },
+            }
+        );
+        assert_eq!(
+            tokens[2].token,
+            Token::String {
+                value: r##"a "# b"##.to_string(),
+                kind: StringKind::Raw { hashes: 2 },
+            }
+        );
+        assert_eq!(tokens[3].token, Token::Identifier(Symbol::intern("r")));
+        assert_eq!(tokens[4].token, Token::Identifier(Symbol::intern("x")));
+    }
+
+    #[test]
+    fn test_raw_delimited_strings() {
+        let source = r#"R"(Raw \string \with \backslashes)" R"xy(a )" b)xy" R"(two
+lines)""#;
+        let tokens = Lexer::new(source).tokenize();
+
+        assert_eq!(
+            tokens[0].token,
+            Token::String {
+                value: r"Raw \string \with \backslashes".to_string(),
+                kind: StringKind::RawDelimited {
+                    delimiter: String::new()
+                },
+            }
+        );
+        assert_eq!(
+            tokens[1].token,
+            Token::String {
+                value: r#"a )" b"#.to_string(),
+                kind: StringKind::RawDelimited {
+                    delimiter: "xy".to_string()
+                },
+            }
+        );
+        assert_eq!(
+            tokens[2].token,
+            Token::String {
+                value: "two\nlines".to_string(),
+                kind: StringKind::RawDelimited {
+                    delimiter: String::new()
+                },
+            }
+        );
+        assert_eq!(tokens[3].token, Token::Eof);
+    }
+
+    #[test]
+    fn test_invalid_raw_strings() {
+        let source = "R\"a b(x)a b\" r#\"open";
+        let (_, diagnostics) = Lexer::new(source).tokenize_with_diagnostics();
+
+        let errors: Vec<_> = diagnostics.into_iter().map(|d| d.error).collect();
+        assert_eq!(
+            errors,
+            vec![
+                TokenError::InvalidRawDelimiter("a b".to_string()),
+                TokenError::UnterminatedString('"'),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_multi_line_string_strips_indentation() {
+        let source = "let s = \"\"\"\n    first\n      second\\t\n\n    third\n    \"\"\"; x";
+        let tokens = Lexer::new(source).tokenize();
+
+        assert_eq!(
+            tokens[3].token,
+            Token::String {
+                value: "first\n  second\t\n\nthird".to_string(),
+                kind: StringKind::MultiLine,
+            }
+        );
+        assert_eq!(tokens[4].token, Token::Punctuation(Punctuation::Semicolon));
+        assert_eq!((tokens[5].line, tokens[5].column), (6, 10));
+    }
+
+    #[test]
+    fn test_multi_line_string_on_one_line() {
+        let tokens = Lexer::new(r#""""  keeps "quotes" \"""" """""""#).tokenize();
+
+        assert_eq!(
+            tokens[0].token,
+            Token::String {
+                value: "  keeps \"quotes\" \"".to_string(),
+                kind: StringKind::MultiLine,
+            }
+        );
+        assert_eq!(
+            tokens[1].token,
+            Token::String {
+                value: String::new(),
+                kind: StringKind::MultiLine,
+            }
+        );
+    }
+
+    #[test]
+    fn test_unterminated_multi_line_string() {
+        let (tokens, diagnostics) = Lexer::new("\"\"\"\n  open\n").tokenize_with_diagnostics();
+
+        assert!(matches!(tokens[0].token, Token::Invalid(_)));
+        assert_eq!(diagnostics[0].error, TokenError::UnterminatedString('"'));
+        // Lexing resumes on the next line
+        assert_eq!(tokens[1].token, Token::Newline);
+        assert_eq!(tokens[2].token, Token::Identifier(Symbol::intern("open")));
+    }
+
+    #[test]
+    fn test_interpolated_string_tokens() {
+        let tokens = Lexer::new(r#""Value: ${x + 1}" y"#).tokenize();
+
+        let lexed: Vec<_> = tokens.iter().map(|t| (t.token.clone(), t.lexeme)).collect();
+        assert_eq!(
+            lexed,
+            vec![
+                (Token::StringStart, "\""),
+                (Token::StringFragment("Value: ".to_string()), "Value: "),
+                (Token::InterpolationStart, "${"),
+                (Token::Identifier(Symbol::intern("x")), "x"),
+                (Token::Operation(Operation::Add), "+"),
+                (int(1), "1"),
+                (Token::InterpolationEnd, "}"),
+                (Token::StringEnd, "\""),
+                (Token::Identifier(Symbol::intern("y")), "y"),
+                (Token::Eof, ""),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_interpolation_nested_braces_and_strings() {
+        let source = r#""a${ {b} }c${ "in ${d}" + "}" }""#;
+        let tokens: Vec<_> = Lexer::new(source)
+            .tokenize()
+            .into_iter()
+            .map(|t| t.token)
+            .collect();
+
+        assert_eq!(
+            tokens,
+            vec![
+                Token::StringStart,
+                Token::StringFragment("a".to_string()),
+                Token::InterpolationStart,
+                Token::Punctuation(Punctuation::OpenBrace),
+                Token::Identifier(Symbol::intern("b")),
+                Token::Punctuation(Punctuation::CloseBrace),
+                Token::InterpolationEnd,
+                Token::StringFragment("c".to_string()),
+                Token::InterpolationStart,
+                Token::StringStart,
+                Token::StringFragment("in ".to_string()),
+                Token::InterpolationStart,
+                Token::Identifier(Symbol::intern("d")),
+                Token::InterpolationEnd,
+                Token::StringEnd,
+                Token::Operation(Operation::Add),
+                string("}"),
+                Token::InterpolationEnd,
+                Token::StringEnd,
+                Token::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_escaped_interpolation_is_plain_text() {
+        let tokens = Lexer::new(r#""cost: \${x} $5""#).tokenize();
+
+        assert_eq!(tokens[0].token, string("cost: ${x} $5"));
+    }
+
+    #[test]
+    fn test_unterminated_interpolated_string() {
+        let (tokens, diagnostics) = Lexer::new("x = \"a ${b} c").tokenize_with_diagnostics();
+
+        assert_eq!(tokens.last().unwrap().token, Token::Eof);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].error, TokenError::UnterminatedString('"'));
+        assert_eq!(diagnostics[0].start.column, 5);
+    }
+
+    #[test]
+    fn test_unterminated_string_ends_at_line_end() {
+        let source = "let s = \"open;\nlet t = 1;";
+        let (tokens, diagnostics) = Lexer::new(source).tokenize_with_diagnostics();
+
+        assert_eq!(
+            tokens[3].token,
+            Token::Invalid("Unterminated string starting with \"".to_string())
+        );
+        assert_eq!(tokens[3].lexeme, "\"open;");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].end.offset, 14);
+        assert_eq!(diagnostics[0].recovery, diagnostics[0].end);
+
+        let rest: Vec<_> = tokens[4..].iter().map(|t| t.lexeme).collect();
+        assert_eq!(rest, vec!["\n", "let", "t", "=", "1", ";", ""]);
+    }
+
+    #[test]
+    fn test_unterminated_raw_strings_end_at_line_end() {
+        let source = "r#\"open\" x\nR\"d(y\nz";
+        let (tokens, diagnostics) = Lexer::new(source).tokenize_with_diagnostics();
+
+        let lexemes: Vec<_> = tokens.iter().map(|t| t.lexeme).collect();
+        assert_eq!(lexemes, vec!["r#\"open\" x", "\n", "R\"d(y", "\n", "z", ""]);
+        assert_eq!(tokens[4].line, 3);
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!((diagnostics[1].end.line, diagnostics[1].end.column), (2, 6));
+    }
+
+    #[test]
+    fn test_many_unterminated_raw_strings() {
+        let mut source = "r#\"a\nR\"d(b\n".repeat(3000);
+        source.push_str("r\"done\" x");
+        let (tokens, diagnostics) = Lexer::new(&source).tokenize_with_diagnostics();
+
+        assert_eq!(diagnostics.len(), 6000);
+        assert_eq!(diagnostics[5999].start.line, 6000);
+        assert!(
+            diagnostics
+                .iter()
+                .all(|d| d.error == TokenError::UnterminatedString('"'))
+        );
+
+        let last: Vec<_> = tokens.iter().rev().take(3).map(|t| t.lexeme).collect();
+        assert_eq!(last, vec!["", "x", "r\"done\""]);
+        assert_eq!(tokens[tokens.len() - 3].line, 6001);
+    }
+
+    #[test]
+    fn test_unterminated_interpolation_ends_at_line_end() {
+        let source = "x = \"a ${b\ny = \"c ${d}\nz";
+        let (tokens, diagnostics) = Lexer::new(source).tokenize_with_diagnostics();
+
+        assert_eq!(diagnostics.len(), 2);
+        assert!(
+            diagnostics
+                .iter()
+                .all(|d| d.error == TokenError::UnterminatedString('"'))
+        );
+        assert_eq!(diagnostics[1].start.line, 2);
+
+        let last: Vec<_> = tokens
+            .iter()
+            .rev()
+            .take(3)
+            .map(|t| t.token.clone())
+            .collect();
+        assert_eq!(
+            last,
+            vec![
+                Token::Eof,
+                Token::Identifier(Symbol::intern("z")),
+                Token::Newline
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_character_runs_collapse() {
+        let (tokens, diagnostics) = Lexer::new("a §§` b ¤ c").tokenize_with_diagnostics();
+
+        let errors: Vec<_> = diagnostics.into_iter().map(|d| d.error).collect();
+        assert_eq!(
+            errors,
+            vec![
+                TokenError::UnexpectedCharacters("§§`".to_string()),
+                TokenError::UnexpectedCharacter('¤'),
+            ]
+        );
+        assert_eq!(errors[0].code(), "L0017");
+        assert_eq!(tokens[1].lexeme, "§§`");
+        assert_eq!(tokens[2].token, Token::Identifier(Symbol::intern("b")));
+        assert_eq!(tokens.len(), 6);
+    }
+
+    #[test]
+    fn test_relex_renamed_identifier() {
+        let source = "let a = 1;\nlet b = 2;\nlet c = 3;";
+        let edit = TextEdit::new(15..16, "bee");
+
+        // `let` and `bee`; the old tokens line up again from `=` on
+        assert_eq!(relex(source, edit), 2);
+    }
+
+    #[test]
+    fn test_relex_keeps_tokens_before_the_edited_line() {
+        let source = "let a = 1;\nlet b = 2;";
+        let previous = Lexer::new(source).lex();
+        let edit = TextEdit::insert(20, "0");
+        let edited = edit.apply(source);
+        let lexed = Lexer::new(&edited).relex(&previous, &edit);
+
+        assert_eq!(lexed.relexed, 6..10);
+        assert_eq!(lexed.tokens[9].lexeme, "20");
+        assert_eq!(lexed.tokens[10].span.start_byte, 21);
+    }
+
+    #[test]
+    fn test_relex_edits_changing_line_structure() {
+        let source = "a = 1;\nb = 2;\nc = 3;\nd = 4;";
+
+        assert_eq!(relex(source, TextEdit::insert(7, "x;\ny;\n")), 6);
+        assert!(relex(source, TextEdit::delete(6..7)) <= 6);
+        assert_eq!(relex(source, TextEdit::insert(source.len(), " e")), 6);
+        relex(source, TextEdit::delete(0..source.len()));
+        relex("", TextEdit::insert(0, "x"));
+    }
+
+    #[test]
+    fn test_relex_opening_and_closing_block_comments() {
+        let source = "a /* one\ntwo */ b\nc\nd";
+
+        relex(source, TextEdit::insert(1, "/*"));
+        relex(source, TextEdit::delete(13..15));
+        relex(source, TextEdit::new(11..12, "w"));
+    }
+
+    #[test]
+    fn test_relex_strings() {
+        let source = "x = \"a ${b} c\";\ny = 1;\nz = r\"q";
+
+        relex(source, TextEdit::new(9..10, "b + 1"));
+        relex(source, TextEdit::delete(10..11));
+        relex(source, TextEdit::insert(0, "\""));
+        // Closing the raw string on a later line turns the lines in between into its body
+        relex(source, TextEdit::insert(source.len(), "\nw\""));
+        relex(source, TextEdit::insert(0, "\"\"\"\n"));
+    }
+
+    #[test]
+    fn test_relex_below_unterminated_strings() {
+        let source = "s = \"open\nt = 1;\nu = r\"raw\nv = 2;";
+
+        // `t`, `=` and `7`; the plain string ends with its line
+        assert_eq!(relex(source, TextEdit::new(14..15, "7")), 3);
+        // The raw string might now be closed, so lexing restarts at `r"raw`
+        assert_eq!(relex(source, TextEdit::new(31..32, "8")), 5);
+        relex(source, TextEdit::insert(source.len(), "\""));
+    }
+
+    #[test]
+    fn test_relex_resumes_inside_interpolation() {
+        let source = "s = \"${f(1, 2)} and ${g}\";";
+        let previous = Lexer::new(source).lex();
+        let edit = TextEdit::new(11..12, "3");
+        let edited = edit.apply(source);
+        let lexed = Lexer::new(&edited).relex(&previous, &edit);
+
+        // Lexing stops at the `)` inside the interpolation; the rest of the
+        // string is carried over
+        assert_eq!(lexed.relexed, 0..9);
+        assert_eq!(lexed.tokens[9].lexeme, ")");
+        assert_eq!(relex(source, edit), 9);
+    }
+
+    #[test]
+    fn test_relex_identifier_lints() {
+        let source = "let scope = 1;\nlet x = 2;";
+        let previous = Lexer::new(source).lint_identifiers(true).lex();
+        let edit = TextEdit::new(19..20, "ѕсоре");
+        let edited = edit.apply(source);
+        let lexed = Lexer::new(&edited)
+            .lint_identifiers(true)
+            .relex(&previous, &edit);
+
+        assert!(matches!(
+            lexed.diagnostics[0].error,
+            TokenError::ConfusableIdentifier { .. }
+        ));
+    }
 }
@@ -0,0 +1,247 @@
+use crate::lexer::lexer::Lexer;
+use crate::lexer::token::{Token, TokenInfo};
+use crate::macros::error::MacroError;
+use crate::macros::expander::MacroExpander;
+use crate::parser::parser::Parser;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Output spelled out with identifiers by name, so hygienic renames show up
+    fn spell(tokens: &[TokenInfo]) -> String {
+        tokens
+            .iter()
+            .filter(|info| info.token != Token::Eof)
+            .map(|info| match &info.token {
+                Token::Identifier(name) => name.to_string(),
+                _ => info.lexeme.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    fn expand(input: &str) -> (String, Vec<MacroError>) {
+        let mut expander = MacroExpander::new();
+        let tokens = expander.expand(Lexer::new(input).tokenize());
+        let errors = expander
+            .diagnostics()
+            .iter()
+            .map(|diagnostic| diagnostic.error.clone())
+            .collect();
+        (spell(&tokens), errors)
+    }
+
+    #[test]
+    fn test_simple_macro() {
+        let (output, errors) =
+            expand("macro double ($x:expr) => { ($x) * 2 }\nlet y = double!(a + 1);");
+
+        assert_eq!(output, "let y = ( a + 1 ) * 2 ;");
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn test_source_without_macros_is_unchanged() {
+        let (output, errors) = expand("let x = !(a);");
+
+        assert_eq!(output, "let x = ! ( a ) ;");
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn test_undeclared_name_is_not_expanded() {
+        let (output, _) = expand("macro m () => { 1 }\nf!(x)");
+
+        assert_eq!(output, "f ! ( x )");
+    }
+
+    #[test]
+    fn test_repetition_with_separator() {
+        let (output, errors) =
+            expand("macro sum ($($x:expr),+) => { 0 $(+ $x)* }\nsum!(1, b * 2, 3)");
+
+        assert_eq!(output, "0 + 1 + b * 2 + 3");
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn test_rules_are_tried_in_order() {
+        let source = "\
+macro pick () => { none }
+macro pick ($a:literal) => { literal }
+macro pick ($a:ident) => { ident }
+macro pick ($($t:tt)*) => { many }
+pick!() pick![1] pick!{x} pick!(x y)";
+        let (output, errors) = expand(source);
+
+        assert_eq!(output, "none literal ident many");
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn test_hygiene() {
+        let source = "\
+macro swap ($a:ident, $b:ident) => { let tmp = $a; $a = $b; $b = tmp; }
+swap!(tmp, x);
+swap!(x, tmp);";
+        let (output, errors) = expand(source);
+
+        assert_eq!(
+            output,
+            "let tmp#1 = tmp ; tmp = x ; x = tmp#1 ; ; let tmp#2 = x ; x = tmp ; tmp = tmp#2 ; ;"
+        );
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn test_recursive_macro() {
+        let source = "\
+macro count () => { 0 }
+macro count ($x:tt $($rest:tt)*) => { 1 + count!($($rest)*) }
+count!(a b c)";
+        let mut expander = MacroExpander::new();
+        let tokens = expander.expand(Lexer::new(source).tokenize());
+
+        assert_eq!(spell(&tokens), "1 + 1 + 1 + 0");
+        let zero = &tokens[6];
+        let expansion = zero.expansion.as_ref().expect("0 comes from a macro");
+        let sites: Vec<_> = expansion
+            .backtrace()
+            .map(|e| (e.name.as_str(), e.line, e.column))
+            .collect();
+        assert_eq!(
+            sites,
+            vec![
+                ("count", 2, 43),
+                ("count", 2, 43),
+                ("count", 2, 43),
+                ("count", 3, 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_documented_max_macro() {
+        let source = "\
+macro max ($a:expr, $b:expr) => { ($a > $b ? $a : $b) }
+macro max ($a:expr, $($rest:expr),+) => { max!($a, max!($($rest),+)) }
+fn main() {
+    let m = max!(x + 1, y, 3);
+}";
+        let mut expander = MacroExpander::new();
+        let tokens = expander.expand(Lexer::new(source).tokenize());
+
+        assert!(expander.diagnostics().is_empty());
+        assert_eq!(
+            spell(&tokens),
+            "fn main ( ) { let m = ( x + 1 > ( y > 3 ? y : 3 ) ? x + 1 : ( y > 3 ? y : 3 ) ) ; }"
+        );
+        Parser::new(tokens)
+            .parse()
+            .expect("the expansion is an expression");
+    }
+
+    #[test]
+    fn test_recursion_limit() {
+        let (output, errors) = expand("macro forever () => { forever!() }\nforever!()");
+
+        assert_eq!(output, "");
+        assert_eq!(
+            errors,
+            vec![MacroError::RecursionLimit("forever".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_expansion_budget() {
+        let (output, errors) = expand("macro f () => { f!() f!() }\nf!();");
+
+        assert_eq!(output, ";");
+        assert!(!errors.is_empty());
+        assert!(
+            errors
+                .iter()
+                .all(|error| *error == MacroError::RecursionLimit("f".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_no_matching_rule() {
+        let mut expander = MacroExpander::new();
+        expander.expand(Lexer::new("macro one ($a:ident) => { $a }\none!(1)").tokenize());
+
+        let diagnostic = &expander.diagnostics()[0];
+        assert_eq!(
+            diagnostic.error,
+            MacroError::NoMatchingRule("one".to_string())
+        );
+        assert_eq!(
+            diagnostic.to_string(),
+            "error[M0006] 2:1: No rule of macro one matches these arguments"
+        );
+    }
+
+    #[test]
+    fn test_declaration_errors() {
+        let (_, errors) = expand(
+            "macro a ($x:number) => { }\nmacro b ($($x),) => { }\nmacro c ($) => { }\nmacro d { }",
+        );
+
+        assert_eq!(
+            errors,
+            vec![
+                MacroError::UnknownFragmentKind("number".to_string()),
+                MacroError::MissingRepetitionOperator,
+                MacroError::MissingFragmentName,
+                MacroError::MalformedDeclaration,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_transcription_errors() {
+        let (_, errors) = expand(
+            "macro a ($x:tt) => { $y }\na!(1)\n\
+             macro b ($($x:tt)*) => { $x }\nb!(1)\n\
+             macro c ($($x:tt)*; $($y:tt)*) => { $($x $y)* }\nc!(1 2; 3)",
+        );
+
+        assert_eq!(
+            errors,
+            vec![
+                MacroError::UnboundFragment("y".to_string()),
+                MacroError::RepetitionDepth("x".to_string()),
+                MacroError::RepetitionMismatch("c".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unbalanced_delimiter() {
+        let (output, errors) = expand("macro m () => { }\nm!(1");
+
+        assert_eq!(output, "macro m ( ) => { } m ! ( 1");
+        assert_eq!(errors, vec![MacroError::UnbalancedDelimiter]);
+    }
+
+    #[test]
+    fn test_parse_error_inside_expansion() {
+        let source = "\
+macro broken ($x:expr) => { let = $x; }
+macro outer () => { broken!(1) }
+fn main() {
+    outer!();
+}";
+        let mut expander = MacroExpander::new();
+        let tokens = expander.expand(Lexer::new(source).tokenize());
+        let error = Parser::new(tokens).parse().expect_err("let without a name");
+
+        assert_eq!(
+            error.to_string(),
+            "1:33: expected variable name, found Operation(Assign)\n  \
+             in expansion of macro broken at 2:21\n  \
+             in expansion of macro outer at 4:5"
+        );
+    }
+}
@@ -0,0 +1,704 @@
+use std::cell::Cell;
+use std::rc::Rc;
+
+use crate::lexer::keywords::Keywords;
+use crate::lexer::lexer::Lexer;
+use crate::lexer::token::{Operation, Reserved};
+use crate::parser::ast::{
+    Expr, ExprKind, Item, Literal, PatternFields, PatternKind, Position, Program, Stmt, StmtKind,
+    StringPart, VariantKind,
+};
+use crate::parser::error::{ParseError, ParseErrorKind};
+use crate::parser::parser::Parser;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &str) -> Program {
+        let tokens = Lexer::new(input).tokenize();
+        Parser::new(tokens).parse().expect("program should parse")
+    }
+
+    #[test]
+    fn test_let_statement() {
+        let program = parse("let x: int = 42;");
+
+        match &program.items[0] {
+            Item::Statement(stmt) => match &stmt.kind {
+                StmtKind::Let { name, ty, value } => {
+                    assert_eq!(name, "x");
+                    assert_eq!(ty.as_ref().unwrap().name, "int");
+                    assert_eq!(
+                        value.as_ref().unwrap().kind,
+                        ExprKind::Literal(Literal::Integer(42))
+                    );
+                }
+                other => panic!("Expected let statement, got {:?}", other),
+            },
+            other => panic!("Expected statement, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_operator_precedence() {
+        let program = parse("x = 1 + 2 * 3;");
+
+        let Item::Statement(stmt) = &program.items[0] else {
+            panic!("Expected statement");
+        };
+        let StmtKind::Expr(expr) = &stmt.kind else {
+            panic!("Expected expression statement");
+        };
+        let ExprKind::Assign { value, .. } = &expr.kind else {
+            panic!("Expected assignment");
+        };
+        match &value.kind {
+            ExprKind::Binary { op, right, .. } => {
+                assert_eq!(*op, Operation::Add);
+                assert!(matches!(
+                    right.kind,
+                    ExprKind::Binary {
+                        op: Operation::Multiply,
+                        ..
+                    }
+                ));
+            }
+            other => panic!("Expected binary expression, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_function_declaration() {
+        let program = parse("fn sum(a: int, b) -> int { return a + b; }");
+
+        match &program.items[0] {
+            Item::Function(function) => {
+                assert_eq!(function.name, "sum");
+                assert_eq!(function.params.len(), 2);
+                assert_eq!(function.params[1].ty, None);
+                assert_eq!(function.return_type.as_ref().unwrap().name, "int");
+                let body = function.body.as_ref().unwrap();
+                assert!(matches!(body.statements[0].kind, StmtKind::Return(Some(_))));
+            }
+            other => panic!("Expected function, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_if_else_chain() {
+        let program = parse("if x > 0 { print x; } else if x < 0 { print 0; } else { }");
+
+        let Item::Statement(stmt) = &program.items[0] else {
+            panic!("Expected statement");
+        };
+        let StmtKind::If { else_branch, .. } = &stmt.kind else {
+            panic!("Expected if statement");
+        };
+        let nested = else_branch.as_ref().unwrap();
+        match &nested.kind {
+            StmtKind::If { else_branch, .. } => {
+                assert!(matches!(
+                    else_branch.as_ref().unwrap().kind,
+                    StmtKind::Block(_)
+                ));
+            }
+            other => panic!("Expected else-if, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_loops() {
+        let program = parse("while i < 10 { i = i + 1; } for item in items { continue; }");
+
+        assert!(matches!(
+            program.items[0],
+            Item::Statement(ref stmt) if matches!(stmt.kind, StmtKind::While { .. })
+        ));
+        match &program.items[1] {
+            Item::Statement(stmt) => match &stmt.kind {
+                StmtKind::For { variable, .. } => assert_eq!(variable, "item"),
+                other => panic!("Expected for loop, got {:?}", other),
+            },
+            other => panic!("Expected statement, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_postfix_chain() {
+        let program = parse("point.coords[0](1, 2);");
+
+        let Item::Statement(stmt) = &program.items[0] else {
+            panic!("Expected statement");
+        };
+        let StmtKind::Expr(expr) = &stmt.kind else {
+            panic!("Expected expression statement");
+        };
+        match &expr.kind {
+            ExprKind::Call { callee, args } => {
+                assert_eq!(args.len(), 2);
+                assert!(matches!(callee.kind, ExprKind::Index { .. }));
+            }
+            other => panic!("Expected call, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_struct_enum_and_use() {
+        let program =
+            parse("use std::math; struct Point { x: f64, y: f64 } enum Color { Red, Green, Blue }");
+
+        match &program.items[0] {
+            Item::Use(decl) => assert_eq!(decl.path, vec!["std", "math"]),
+            other => panic!("Expected use, got {:?}", other),
+        }
+        match &program.items[1] {
+            Item::Struct(decl) => {
+                assert_eq!(decl.name, "Point");
+                assert_eq!(decl.fields.len(), 2);
+                assert_eq!(decl.fields[1].ty.name, "f64");
+            }
+            other => panic!("Expected struct, got {:?}", other),
+        }
+        match &program.items[2] {
+            Item::Enum(decl) => assert_eq!(decl.variants.len(), 3),
+            other => panic!("Expected enum, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_generic_struct_with_methods() {
+        let program = parse(
+            "struct Vector<T> {
+                x: T,
+                y: T,
+
+                fn length(&self) -> f64 {
+                    sqrt(self.x*self.x + self.y*self.y)
+                }
+            }",
+        );
+
+        let Item::Struct(decl) = &program.items[0] else {
+            panic!("Expected struct");
+        };
+        let generics: Vec<_> = decl.generics.iter().map(|g| g.name.as_str()).collect();
+        assert_eq!(generics, vec!["T"]);
+        assert_eq!(decl.fields.len(), 2);
+        assert_eq!(decl.fields[1].ty.name, "T");
+
+        let method = &decl.methods[0];
+        assert_eq!(method.name, "length");
+        let receiver = method.params[0].ty.as_ref().unwrap();
+        assert_eq!((receiver.name.as_str(), receiver.reference), ("Self", true));
+        assert_eq!(method.return_type.as_ref().unwrap().name, "f64");
+        // The last expression of the body needs no `;`
+        let body = &method.body.as_ref().unwrap().statements;
+        assert!(matches!(body[0].kind, StmtKind::Expr(_)));
+    }
+
+    #[test]
+    fn test_nested_type_arguments_split_shift() {
+        let program = parse(
+            "struct Grid<K, V> { cells: Map<K, Vector<Vector<V>>>, fn get(&self, k: K) -> &V { } }
+             let g: Grid<int, f64>= make();",
+        );
+
+        let Item::Struct(decl) = &program.items[0] else {
+            panic!("Expected struct");
+        };
+        let cells = &decl.fields[0].ty;
+        assert_eq!(cells.args[1].name, "Vector");
+        assert_eq!(cells.args[1].args[0].args[0].name, "V");
+        assert!(decl.methods[0].return_type.as_ref().unwrap().reference);
+
+        let Item::Statement(stmt) = &program.items[1] else {
+            panic!("Expected statement");
+        };
+        let StmtKind::Let { ty, value, .. } = &stmt.kind else {
+            panic!("Expected let statement");
+        };
+        assert_eq!(ty.as_ref().unwrap().args.len(), 2);
+        assert!(matches!(
+            value.as_ref().unwrap().kind,
+            ExprKind::Call { .. }
+        ));
+    }
+
+    #[test]
+    fn test_angle_brackets_in_expressions_compare() {
+        let program = parse("let t: Pair<int, int> = a < b > c;");
+
+        let Item::Statement(stmt) = &program.items[0] else {
+            panic!("Expected statement");
+        };
+        let StmtKind::Let {
+            value: Some(value), ..
+        } = &stmt.kind
+        else {
+            panic!("Expected let statement");
+        };
+        let ExprKind::Binary { op, left, .. } = &value.kind else {
+            panic!("Expected comparison");
+        };
+        assert_eq!(*op, Operation::Greater);
+        assert!(matches!(
+            left.kind,
+            ExprKind::Binary {
+                op: Operation::Less,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_unclosed_type_arguments() {
+        let tokens = Lexer::new("struct S { v: Vector<int; }").tokenize();
+        let error = Parser::new(tokens).parse().unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "1:25: expected '>', found Punctuation(Semicolon)"
+        );
+    }
+
+    #[test]
+    fn test_enum_variants_with_payloads() {
+        let program = parse("enum Shape<T> { Empty, Circle(f64), Rect { w: T, h: T }, }");
+
+        let Item::Enum(decl) = &program.items[0] else {
+            panic!("Expected enum");
+        };
+        assert_eq!(decl.generics[0].name, "T");
+        assert_eq!(decl.variants[0].kind, VariantKind::Unit);
+        let VariantKind::Tuple(types) = &decl.variants[1].kind else {
+            panic!("Expected tuple variant");
+        };
+        assert_eq!(types[0].name, "f64");
+        let VariantKind::Struct(fields) = &decl.variants[2].kind else {
+            panic!("Expected struct variant");
+        };
+        let names: Vec<_> = fields.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, vec!["w", "h"]);
+    }
+
+    // The value of `let name = value;`
+    fn let_value(stmt: &Item) -> &Expr {
+        match stmt {
+            Item::Statement(Stmt {
+                kind: StmtKind::Let {
+                    value: Some(value), ..
+                },
+                ..
+            }) => value,
+            other => panic!("Expected let statement, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_match_expression() {
+        let program = parse(
+            "let area = match shape {
+                Shape::Circle(r) if r > 0.0 => r * r,
+                Shape::Rect { w, h: 2, .. } | Empty => { print w; w }
+                _ => 0,
+            };",
+        );
+
+        let ExprKind::Match { scrutinee, arms } = &let_value(&program.items[0]).kind else {
+            panic!("Expected match");
+        };
+        assert_eq!(scrutinee.kind, ExprKind::Identifier("shape".to_string()));
+        assert_eq!(arms.len(), 3);
+
+        let PatternKind::Variant { path, fields } = &arms[0].pattern.kind else {
+            panic!("Expected variant pattern");
+        };
+        assert_eq!(path, &vec!["Shape", "Circle"]);
+        assert!(matches!(fields, PatternFields::Tuple(patterns)
+            if patterns[0].kind == PatternKind::Binding("r".to_string())));
+        assert!(arms[0].guard.is_some());
+
+        let PatternKind::Or(alternatives) = &arms[1].pattern.kind else {
+            panic!("Expected alternatives");
+        };
+        let PatternKind::Variant {
+            fields: PatternFields::Struct { fields, rest },
+            ..
+        } = &alternatives[0].kind
+        else {
+            panic!("Expected struct pattern");
+        };
+        assert!(*rest);
+        assert_eq!(fields[0].1.kind, PatternKind::Binding("w".to_string()));
+        assert_eq!(fields[1].1.kind, PatternKind::Literal(Literal::Integer(2)));
+        assert_eq!(
+            alternatives[1].kind,
+            PatternKind::Binding("Empty".to_string())
+        );
+        assert!(matches!(arms[1].body.kind, ExprKind::Block(_)));
+
+        assert_eq!(arms[2].pattern.kind, PatternKind::Wildcard);
+    }
+
+    #[test]
+    fn test_match_is_a_contextual_keyword() {
+        let program = parse("let match = 1; print match + 1; match match { _ => 0 } f();");
+
+        assert!(matches!(&program.items[0], Item::Statement(Stmt {
+            kind: StmtKind::Let { name, .. }, ..
+        }) if name == "match"));
+        let Item::Statement(stmt) = &program.items[2] else {
+            panic!("Expected statement");
+        };
+        let StmtKind::Expr(Expr {
+            kind: ExprKind::Match { scrutinee, .. },
+            ..
+        }) = &stmt.kind
+        else {
+            panic!("Expected match statement");
+        };
+        assert_eq!(scrutinee.kind, ExprKind::Identifier("match".to_string()));
+        // A match statement needs no `;`
+        assert_eq!(program.items.len(), 4);
+    }
+
+    #[test]
+    fn test_struct_literals_are_not_conditions() {
+        let program = parse(
+            "let s = Shape::Rect { w: 1, h: f(Shape::Rect { w: 2, h: 3 }) };
+             if s == Color::Red { print 1; }",
+        );
+
+        let ExprKind::StructLiteral { path, fields } = &let_value(&program.items[0]).kind else {
+            panic!("Expected struct literal");
+        };
+        assert_eq!(path, &vec!["Shape", "Rect"]);
+        assert_eq!(fields[1].0, "h");
+
+        let Item::Statement(stmt) = &program.items[1] else {
+            panic!("Expected statement");
+        };
+        let StmtKind::If { condition, .. } = &stmt.kind else {
+            panic!("Expected if statement");
+        };
+        let ExprKind::Binary { right, .. } = &condition.kind else {
+            panic!("Expected comparison");
+        };
+        assert_eq!(
+            right.kind,
+            ExprKind::Path(vec!["Color".to_string(), "Red".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_struct_literals() {
+        let program = parse(
+            "struct Point { x: i32, y: i32 }
+             struct Pair<T> { first: T, second: T }
+             let p = Point { x: 1, y: 2 };
+             let q = Pair { first: p, second: Point { x: a < b, y: 0 } };",
+        );
+
+        let ExprKind::StructLiteral { path, fields } = &let_value(&program.items[2]).kind else {
+            panic!("Expected struct literal");
+        };
+        assert_eq!(path, &vec!["Point"]);
+        assert_eq!(fields.len(), 2);
+
+        let ExprKind::StructLiteral { path, fields } = &let_value(&program.items[3]).kind else {
+            panic!("Expected struct literal");
+        };
+        assert_eq!(path, &vec!["Pair"]);
+        assert_eq!(fields[0].1.kind, ExprKind::Identifier("p".to_string()));
+        let ExprKind::StructLiteral { fields: inner, .. } = &fields[1].1.kind else {
+            panic!("Expected nested struct literal");
+        };
+        assert!(matches!(inner[0].1.kind, ExprKind::Binary { .. }));
+    }
+
+    #[test]
+    fn test_struct_literal_names_in_conditions() {
+        let program = parse("while p { print 1; } if p == q { print 2; }");
+
+        let Item::Statement(stmt) = &program.items[0] else {
+            panic!("Expected statement");
+        };
+        let StmtKind::While { condition, .. } = &stmt.kind else {
+            panic!("Expected while statement");
+        };
+        assert_eq!(condition.kind, ExprKind::Identifier("p".to_string()));
+        assert_eq!(program.items.len(), 2);
+    }
+
+    #[test]
+    fn test_trait_and_impl() {
+        let program = parse(
+            "trait Shape { fn area(self) -> f64; } impl Shape for Circle { fn area(self) -> f64 { return 1; } }",
+        );
+
+        match &program.items[0] {
+            Item::Trait(decl) => assert_eq!(decl.methods[0].body, None),
+            other => panic!("Expected trait, got {:?}", other),
+        }
+        match &program.items[1] {
+            Item::Impl(decl) => {
+                assert_eq!(decl.trait_name.as_ref().unwrap().name, "Shape");
+                assert_eq!(decl.target.name, "Circle");
+                assert!(decl.methods[0].body.is_some());
+            }
+            other => panic!("Expected impl, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_attributes() {
+        let program = parse(
+            "@derive(Eq, Hash) struct P { @deprecated(\"old\") x: int } @inline fn f() { @unroll let a = 1; }",
+        );
+
+        let Item::Struct(decl) = &program.items[0] else {
+            panic!("Expected struct");
+        };
+        let derive = &decl.attributes[0];
+        assert_eq!(derive.name, "derive");
+        assert_eq!(derive.position, Position::new(1, 1));
+        assert_eq!(
+            derive.args[1].kind,
+            ExprKind::Identifier("Hash".to_string())
+        );
+        assert_eq!(decl.fields[0].attributes[0].name, "deprecated");
+
+        let Item::Function(function) = &program.items[1] else {
+            panic!("Expected function");
+        };
+        assert_eq!(function.attributes[0].name, "inline");
+        assert!(function.attributes[0].args.is_empty());
+        let body = function.body.as_ref().unwrap();
+        assert_eq!(body.statements[0].attributes[0].name, "unroll");
+    }
+
+    #[test]
+    fn test_misplaced_attribute() {
+        let tokens = Lexer::new("@inline impl E { }").tokenize();
+        let error = Parser::new(tokens).parse().unwrap_err();
+
+        assert_eq!(
+            error.kind,
+            ParseErrorKind::MisplacedAttribute("inline".to_string())
+        );
+    }
+
+    #[test]
+    fn test_node_positions() {
+        let program = parse("let a = 1;\n  print a + 2;");
+
+        let Item::Statement(stmt) = &program.items[1] else {
+            panic!("Expected statement");
+        };
+        assert_eq!(stmt.position, Position::new(2, 3));
+        let StmtKind::Print(expr) = &stmt.kind else {
+            panic!("Expected print statement");
+        };
+        assert_eq!(expr.position, Position::new(2, 9));
+    }
+
+    #[test]
+    fn test_missing_semicolon() {
+        let tokens = Lexer::new("let x = 1").tokenize();
+        let error = Parser::new(tokens).parse().unwrap_err();
+
+        assert!(matches!(error.kind, ParseErrorKind::UnexpectedEof { .. }));
+    }
+
+    #[test]
+    fn test_invalid_assignment_target() {
+        let tokens = Lexer::new("1 + 2 = 3;").tokenize();
+        let error = Parser::new(tokens).parse().unwrap_err();
+
+        assert_eq!(error.kind, ParseErrorKind::InvalidAssignmentTarget);
+        assert_eq!(error.position, Position::new(1, 7));
+    }
+
+    #[test]
+    fn test_parse_directly_from_lexer() {
+        let program = Parser::new(Lexer::new("let x = 1; print x;"))
+            .parse()
+            .unwrap();
+
+        assert_eq!(program.items.len(), 2);
+    }
+
+    #[test]
+    fn test_parser_stops_lexing_on_error() {
+        let pulled = Rc::new(Cell::new(0));
+        let counter = pulled.clone();
+        let source = format!("let = 1;{}", " let y = 2;".repeat(1000));
+
+        let lexer = Lexer::new(&source).inspect(move |_| counter.set(counter.get() + 1));
+        let error = Parser::new(lexer).parse().unwrap_err();
+
+        assert!(matches!(error.kind, ParseErrorKind::UnexpectedToken { .. }));
+        assert!(pulled.get() < 5);
+    }
+
+    fn parse_recovering(input: &str) -> (Program, Vec<ParseError>) {
+        Parser::new(Lexer::new(input)).parse_with_diagnostics()
+    }
+
+    #[test]
+    fn test_every_mistake_is_reported() {
+        let source = "\
+let = 1;
+fn f() {
+    let x = ;
+    print x
+    x = 2;
+    y = = 2;
+    if { }
+}
+struct S { x int }
+let y = (1;
+fn g(a b) { }
+let z = 1 2;
+}
+";
+        let (program, errors) = parse_recovering(source);
+
+        // The missing `;` after `print x` is found at the start of line 5
+        let lines: Vec<_> = errors.iter().map(|error| error.position.line).collect();
+        assert_eq!(lines, vec![1, 3, 5, 6, 7, 9, 10, 11, 12, 13]);
+
+        assert_eq!(program.items.len(), 7);
+        let Item::Function(function) = &program.items[1] else {
+            panic!("Expected function");
+        };
+        let kinds: Vec<_> = function
+            .body
+            .as_ref()
+            .unwrap()
+            .statements
+            .iter()
+            .map(|stmt| stmt.kind == StmtKind::Error)
+            .collect();
+        assert_eq!(kinds, vec![true, true, true, true]);
+    }
+
+    #[test]
+    fn test_error_nodes_keep_the_rest_of_the_ast() {
+        let (program, errors) = parse_recovering("fn f() { let = 1; print a; }\nlet b = 2;");
+
+        assert_eq!(errors.len(), 1);
+        let Item::Function(function) = &program.items[0] else {
+            panic!("Expected function");
+        };
+        let statements = &function.body.as_ref().unwrap().statements;
+        assert_eq!(statements[0].kind, StmtKind::Error);
+        assert_eq!(statements[0].position, Position::new(1, 10));
+        assert!(matches!(statements[1].kind, StmtKind::Print(_)));
+        assert!(matches!(
+            &program.items[1],
+            Item::Statement(Stmt {
+                kind: StmtKind::Let { .. },
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_recovery_resumes_at_item_keyword() {
+        let (program, errors) = parse_recovering("fn f(1) -> { oops } struct S { x: int }");
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(program.items[0], Item::Error(Position::new(1, 1)));
+        assert!(matches!(&program.items[1], Item::Struct(decl) if decl.name == "S"));
+    }
+
+    #[test]
+    fn test_recovery_skips_nested_blocks() {
+        let (program, errors) = parse_recovering("fn f() { if a == { b; { c; } } print d; }");
+
+        assert_eq!(errors.len(), 1);
+        let Item::Function(function) = &program.items[0] else {
+            panic!("Expected function");
+        };
+        let statements = &function.body.as_ref().unwrap().statements;
+        assert_eq!(statements.len(), 2);
+        assert_eq!(statements[0].kind, StmtKind::Error);
+        assert!(matches!(statements[1].kind, StmtKind::Print(_)));
+    }
+
+    #[test]
+    fn test_recovery_at_end_of_input() {
+        let (program, errors) = parse_recovering("fn f() { let x = 1;");
+
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            errors[0].kind,
+            ParseErrorKind::UnexpectedEof { .. }
+        ));
+        assert_eq!(program.items, vec![Item::Error(Position::new(1, 1))]);
+    }
+
+    #[test]
+    fn test_interpolated_string() {
+        let program = parse(r#"print "Value: ${x + 1}!";"#);
+
+        let Item::Statement(stmt) = &program.items[0] else {
+            panic!("Expected statement");
+        };
+        let StmtKind::Print(expr) = &stmt.kind else {
+            panic!("Expected print statement");
+        };
+        let ExprKind::Interpolation(parts) = &expr.kind else {
+            panic!("Expected interpolated string");
+        };
+        assert_eq!(parts.len(), 3);
+        assert_eq!(parts[0], StringPart::Text("Value: ".to_string()));
+        assert!(matches!(
+            &parts[1],
+            StringPart::Expr(expr) if matches!(expr.kind, ExprKind::Binary { op: Operation::Add, .. })
+        ));
+        assert_eq!(parts[2], StringPart::Text("!".to_string()));
+    }
+
+    #[test]
+    fn test_unclosed_interpolation() {
+        let tokens = Lexer::new(r#"print "a ${x";"#).tokenize();
+        let error = Parser::new(tokens).parse().unwrap_err();
+
+        assert!(matches!(error.kind, ParseErrorKind::UnexpectedToken { .. }));
+    }
+
+    #[test]
+    fn test_contextual_keyword_only_in_position() {
+        let program = parse("let in = items; for x in in { print x; }");
+
+        let Item::Statement(stmt) = &program.items[0] else {
+            panic!("Expected statement");
+        };
+        assert!(matches!(&stmt.kind, StmtKind::Let { name, .. } if name == "in"));
+        let Item::Statement(stmt) = &program.items[1] else {
+            panic!("Expected statement");
+        };
+        let StmtKind::For { iterable, .. } = &stmt.kind else {
+            panic!("Expected for loop");
+        };
+        assert_eq!(iterable.kind, ExprKind::Identifier("in".to_string()));
+    }
+
+    #[test]
+    fn test_registered_contextual_keyword() {
+        let keywords = Keywords::default().contextual(Reserved::Struct);
+        let tokens = Lexer::new("struct P { x: int } let struct = 1;")
+            .keywords(keywords.clone())
+            .tokenize();
+        let program = Parser::new(tokens).keywords(keywords).parse().unwrap();
+
+        assert!(matches!(&program.items[0], Item::Struct(decl) if decl.name == "P"));
+        let Item::Statement(stmt) = &program.items[1] else {
+            panic!("Expected statement");
+        };
+        assert!(matches!(&stmt.kind, StmtKind::Let { name, .. } if name == "struct"));
+    }
+}
@@ -0,0 +1,289 @@
+use crate::lexer::lexer::Lexer;
+use crate::lexer::token::{OPERATORS, Operation};
+use crate::parser::ast::{Expr, ExprKind, Item, Literal, StmtKind};
+use crate::parser::error::{ParseError, ParseErrorKind};
+use crate::parser::parser::Parser;
+use crate::parser::precedence::{
+    Associativity, Fixity, OPERATOR_TABLE, OperatorEntry, Precedence, operator,
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spelling(op: Operation) -> &'static str {
+        OPERATORS
+            .iter()
+            .find(|(_, entry)| *entry == op)
+            .map(|(text, _)| *text)
+            .expect("every operation has a spelling")
+    }
+
+    // Fully parenthesized form of an expression
+    fn sexp(expr: &Expr) -> String {
+        match &expr.kind {
+            ExprKind::Identifier(name) => name.clone(),
+            ExprKind::Literal(Literal::Integer(value)) => value.to_string(),
+            ExprKind::Unary { op, operand } => format!("({} {})", spelling(*op), sexp(operand)),
+            ExprKind::Postfix { op, operand } => format!("({} {})", sexp(operand), spelling(*op)),
+            ExprKind::Binary { op, left, right } => {
+                format!("({} {} {})", spelling(*op), sexp(left), sexp(right))
+            }
+            ExprKind::Assign { target, value } => format!("(= {} {})", sexp(target), sexp(value)),
+            ExprKind::CompoundAssign { op, target, value } => {
+                format!("({} {} {})", spelling(*op), sexp(target), sexp(value))
+            }
+            ExprKind::Ternary {
+                condition,
+                then_branch,
+                else_branch,
+            } => format!(
+                "(? {} {} {})",
+                sexp(condition),
+                sexp(then_branch),
+                sexp(else_branch)
+            ),
+            ExprKind::Call { callee, args } => {
+                let mut parts = vec!["call".to_string(), sexp(callee)];
+                parts.extend(args.iter().map(sexp));
+                format!("({})", parts.join(" "))
+            }
+            ExprKind::Index { object, index } => {
+                format!("(index {} {})", sexp(object), sexp(index))
+            }
+            ExprKind::Member { object, field } => format!("(. {} {})", sexp(object), field),
+            other => panic!("unexpected expression {:?}", other),
+        }
+    }
+
+    fn try_parse(input: &str) -> Result<String, ParseError> {
+        let source = format!("{};", input);
+        let tokens = Lexer::new(&source).tokenize();
+        let program = Parser::new(tokens).parse()?;
+        let Item::Statement(stmt) = &program.items[0] else {
+            panic!("Expected statement");
+        };
+        let StmtKind::Expr(expr) = &stmt.kind else {
+            panic!("Expected expression statement");
+        };
+        Ok(sexp(expr))
+    }
+
+    fn parse(input: &str) -> String {
+        try_parse(input).unwrap_or_else(|error| panic!("{}: {}", input, error))
+    }
+
+    fn assert_invalid_target(input: &str) {
+        let error = try_parse(input).expect_err(input);
+        assert_eq!(
+            error.kind,
+            ParseErrorKind::InvalidAssignmentTarget,
+            "{}",
+            input
+        );
+    }
+
+    fn entries(fixity: Fixity) -> impl Iterator<Item = &'static OperatorEntry> {
+        OPERATOR_TABLE
+            .iter()
+            .filter(move |entry| entry.fixity == fixity)
+    }
+
+    fn is_assignment(entry: &OperatorEntry) -> bool {
+        entry.precedence == Precedence::Assignment
+    }
+
+    #[test]
+    fn test_every_infix_pair() {
+        for first in entries(Fixity::Infix) {
+            for second in entries(Fixity::Infix) {
+                let (one, two) = (spelling(first.op), spelling(second.op));
+                let input = format!("a {} b {} c", one, two);
+
+                let groups_left = first.precedence > second.precedence
+                    || (first.precedence == second.precedence
+                        && first.associativity == Associativity::Left);
+                if !groups_left {
+                    assert_eq!(
+                        parse(&input),
+                        format!("({} a ({} b c))", one, two),
+                        "{}",
+                        input
+                    );
+                } else if is_assignment(second) {
+                    // `a + b = c` assigns to `a + b`
+                    assert_invalid_target(&input);
+                } else {
+                    assert_eq!(
+                        parse(&input),
+                        format!("({} ({} a b) c)", two, one),
+                        "{}",
+                        input
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_prefix_against_infix() {
+        for prefix in entries(Fixity::Prefix) {
+            for infix in entries(Fixity::Infix) {
+                let (pre, op) = (spelling(prefix.op), spelling(infix.op));
+
+                // The operand of a prefix operator ends before any infix one
+                let input = format!("{}a {} b", pre, op);
+                if is_assignment(infix) {
+                    assert_invalid_target(&input);
+                } else {
+                    assert_eq!(parse(&input), format!("({} ({} a) b)", op, pre));
+                }
+
+                let input = format!("a {} {}b", op, pre);
+                assert_eq!(parse(&input), format!("({} a ({} b))", op, pre));
+            }
+        }
+    }
+
+    #[test]
+    fn test_postfix_against_infix() {
+        for postfix in entries(Fixity::Postfix) {
+            for infix in entries(Fixity::Infix) {
+                let (post, op) = (spelling(postfix.op), spelling(infix.op));
+
+                let input = format!("a {} b{}", op, post);
+                assert_eq!(parse(&input), format!("({} a (b {}))", op, post));
+
+                let input = format!("a{} {} b", post, op);
+                if is_assignment(infix) {
+                    assert_invalid_target(&input);
+                } else {
+                    assert_eq!(parse(&input), format!("({} (a {}) b)", op, post));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_access_against_infix() {
+        for infix in entries(Fixity::Infix) {
+            let op = spelling(infix.op);
+
+            assert_eq!(
+                parse(&format!("a {} f(b).c[d]", op)),
+                format!("({} a (index (. (call f b) c) d))", op)
+            );
+            // Calls, indexing and member access produce places again
+            assert_eq!(
+                parse(&format!("x.y[0] {} b", op)),
+                format!("({} (index (. x y) 0) b)", op)
+            );
+        }
+    }
+
+    #[test]
+    fn test_prefix_against_postfix() {
+        for prefix in entries(Fixity::Prefix) {
+            let pre = spelling(prefix.op);
+
+            for postfix in entries(Fixity::Postfix) {
+                let post = spelling(postfix.op);
+                let input = format!("{}a{}", pre, post);
+                // `++a++` increments `a++`, which is not a place
+                if matches!(prefix.op, Operation::Increment | Operation::Decrement) {
+                    assert_invalid_target(&input);
+                } else {
+                    assert_eq!(parse(&input), format!("({} (a {}))", pre, post));
+                }
+            }
+            assert_eq!(
+                parse(&format!("{}a.b(c)[d]", pre)),
+                format!("({} (index (call (. a b) c) d))", pre)
+            );
+        }
+    }
+
+    #[test]
+    fn test_ternary_against_infix() {
+        for infix in entries(Fixity::Infix) {
+            let op = spelling(infix.op);
+
+            // Anything goes between `?` and `:`
+            assert_eq!(
+                parse(&format!("a ? b {} c : d", op)),
+                format!("(? a ({} b c) d)", op)
+            );
+
+            if is_assignment(infix) {
+                assert_eq!(
+                    parse(&format!("a {} b ? c : d", op)),
+                    format!("({} a (? b c d))", op)
+                );
+                assert_invalid_target(&format!("a ? b : c {} d", op));
+            } else {
+                assert_eq!(
+                    parse(&format!("a {} b ? c : d", op)),
+                    format!("(? ({} a b) c d)", op)
+                );
+                assert_eq!(
+                    parse(&format!("a ? b : c {} d", op)),
+                    format!("(? a b ({} c d))", op)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_ternary_is_right_associative() {
+        assert_eq!(parse("a ? b : c ? d : e"), "(? a b (? c d e))");
+        assert_eq!(parse("a ? b ? c : d : e"), "(? a (? b c d) e)");
+        assert_eq!(parse("-a ? b++ : c.d"), "(? (- a) (b ++) (. c d))");
+    }
+
+    #[test]
+    fn test_nested_prefix_and_postfix() {
+        assert_eq!(parse("- -a"), "(- (- a))");
+        assert_eq!(parse("!~a"), "(! (~ a))");
+        assert_eq!(parse("--a[0]"), "(-- (index a 0))");
+        assert_eq!(parse("f(a)(b)"), "(call (call f a) b)");
+    }
+
+    #[test]
+    fn test_parentheses_override_precedence() {
+        assert_eq!(parse("(a + b) * c"), "(* (+ a b) c)");
+        assert_eq!(parse("-(a + b)"), "(- (+ a b))");
+        assert_eq!(parse("(a ? b : c) + d"), "(+ (? a b c) d)");
+    }
+
+    #[test]
+    fn test_increment_needs_a_place() {
+        assert_invalid_target("++1");
+        assert_invalid_target("f()++");
+        assert_invalid_target("(a + b)--");
+        assert_eq!(parse("f().x++"), "((. (call f) x) ++)");
+    }
+
+    #[test]
+    fn test_missing_ternary_colon() {
+        let error = try_parse("a ? b c").expect_err("no ':'");
+
+        assert!(matches!(
+            error.kind,
+            ParseErrorKind::UnexpectedToken { ref expected, .. } if expected == "':'"
+        ));
+    }
+
+    #[test]
+    fn test_table_lookup() {
+        assert_eq!(
+            operator(Operation::Subtract, Fixity::Prefix),
+            Some((Precedence::Prefix, Associativity::Right))
+        );
+        assert_eq!(
+            operator(Operation::Subtract, Fixity::Infix),
+            Some((Precedence::Term, Associativity::Left))
+        );
+        assert_eq!(operator(Operation::Not, Fixity::Infix), None);
+        assert_eq!(operator(Operation::Arrow, Fixity::Infix), None);
+    }
+}
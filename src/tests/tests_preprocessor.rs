@@ -0,0 +1,435 @@
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::lexer::token::{Token, TokenInfo};
+use crate::parser::ast::{ExprKind, Item, Literal, StmtKind};
+use crate::parser::parser::Parser;
+use crate::preprocessor::error::PreprocessError;
+use crate::preprocessor::loader::FileLoader;
+use crate::preprocessor::preprocessor::Preprocessor;
+use crate::source::source_map::SourceMap;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn files(entries: &[(&str, &str)]) -> HashMap<PathBuf, String> {
+        entries
+            .iter()
+            .map(|(path, text)| (PathBuf::from(path), text.to_string()))
+            .collect()
+    }
+
+    // Lexemes of the output (without the final Eof) and the reported errors
+    fn preprocess(input: &str) -> (Vec<String>, Vec<PreprocessError>) {
+        let sources = SourceMap::new();
+        let main = sources.add_file("main.d", input);
+        let mut preprocessor = Preprocessor::new(&sources).loader(files(&[]));
+
+        let tokens = preprocessor.run(main);
+        (lexemes(&tokens), errors(&preprocessor))
+    }
+
+    fn lexemes(tokens: &[TokenInfo]) -> Vec<String> {
+        tokens
+            .iter()
+            .filter(|info| info.token != Token::Eof)
+            .map(|info| info.lexeme.to_string())
+            .collect()
+    }
+
+    fn errors(preprocessor: &Preprocessor) -> Vec<PreprocessError> {
+        preprocessor
+            .diagnostics()
+            .iter()
+            .map(|diagnostic| diagnostic.error.clone())
+            .collect()
+    }
+
+    struct CountingLoader {
+        files: HashMap<PathBuf, String>,
+        loads: Rc<Cell<usize>>,
+    }
+
+    impl FileLoader for CountingLoader {
+        fn load(&self, path: &Path) -> Option<String> {
+            let text = self.files.get(path).cloned();
+            if text.is_some() {
+                self.loads.set(self.loads.get() + 1);
+            }
+            text
+        }
+    }
+
+    #[test]
+    fn test_object_like_macro() {
+        let (tokens, errors) = preprocess("#define MAX 100\nlet x = MAX;");
+
+        assert_eq!(tokens, vec!["let", "x", "=", "100", ";"]);
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn test_function_like_macro() {
+        let (tokens, _) = preprocess("#define SQUARE(x) ((x) * (x))\nSQUARE(a + 1)");
+
+        assert_eq!(tokens.join(" "), "( ( a + 1 ) * ( a + 1 ) )");
+    }
+
+    #[test]
+    fn test_space_before_paren_makes_object_like_macro() {
+        let (tokens, _) = preprocess("#define F (x)\nF(1)");
+
+        assert_eq!(tokens.join(" "), "( x ) ( 1 )");
+    }
+
+    #[test]
+    fn test_function_like_macro_without_call_is_kept() {
+        let (tokens, _) = preprocess("#define F(a) a\nlet f = F;");
+
+        assert_eq!(tokens.join(" "), "let f = F ;");
+    }
+
+    #[test]
+    fn test_arguments_with_nested_parens_and_commas() {
+        let (tokens, _) = preprocess(
+            "#define FIRST(a, b) a\n#define ONE 1\nFIRST(g(ONE, 2), (3, 4)) FIRST((), x)",
+        );
+
+        assert_eq!(tokens.join(" "), "g ( 1 , 2 ) ( )");
+    }
+
+    #[test]
+    fn test_rescanning_stops_at_recursive_macros() {
+        let (tokens, _) = preprocess("#define A B\n#define B A + 1\nA");
+
+        assert_eq!(tokens.join(" "), "A + 1");
+    }
+
+    #[test]
+    fn test_self_applying_macro_stops() {
+        let (tokens, errors) = preprocess("#define f(x) x(x)\nf(f)");
+
+        assert_eq!(tokens.join(" "), "f ( f )");
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn test_expansion_result_can_call_function_like_macro() {
+        let (tokens, _) = preprocess("#define CALL ADD\n#define ADD(a, b) a + b\nCALL(1, 2)");
+
+        assert_eq!(tokens.join(" "), "1 + 2");
+    }
+
+    #[test]
+    fn test_expansion_origin() {
+        let sources = SourceMap::new();
+        let main = sources.add_file("main.d", "#define INNER 7\n#define OUTER INNER\nx = OUTER;");
+        let tokens = Preprocessor::new(&sources).run(main);
+
+        assert_eq!(tokens[0].expansion, None);
+        let seven = &tokens[2];
+        assert_eq!(seven.lexeme, "7");
+        assert_eq!(seven.line, 1);
+
+        let expansion = seven.expansion.as_ref().expect("7 comes from a macro");
+        let names: Vec<_> = expansion.backtrace().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["INNER", "OUTER"]);
+        assert_eq!(sources.text(expansion.call_site), "INNER");
+        assert_eq!(sources.text(expansion.definition), "INNER 7");
+        assert_eq!(sources.text(expansion.root_call_site()), "OUTER");
+    }
+
+    #[test]
+    fn test_macro_argument_count_error() {
+        let sources = SourceMap::new();
+        let main = sources.add_file("main.d", "#define PAIR(a, b) a b\n#define ONE PAIR(1)\nONE");
+        let mut preprocessor = Preprocessor::new(&sources);
+        preprocessor.run(main);
+
+        let diagnostic = &preprocessor.diagnostics()[0];
+        assert_eq!(
+            diagnostic.error,
+            PreprocessError::ArgumentCount {
+                name: "PAIR".to_string(),
+                expected: 2,
+                found: 1,
+            }
+        );
+        assert_eq!(
+            diagnostic.to_string(),
+            "error[P0010] 2:13: Macro PAIR expects 2 arguments, found 1\n  in expansion of macro ONE at 3:1"
+        );
+    }
+
+    #[test]
+    fn test_argument_count_error_keeps_call() {
+        let (tokens, errors) = preprocess("#define F(a) a\nF(1, (2, 3)) x");
+
+        assert_eq!(
+            tokens,
+            vec!["F", "(", "1", ",", "(", "2", ",", "3", ")", ")", "x"]
+        );
+        assert_eq!(errors.len(), 1);
+    }
+
+    #[test]
+    fn test_unterminated_macro_call() {
+        let (tokens, errors) = preprocess("#define F(a) a\nF(1, 2");
+
+        assert_eq!(tokens, vec!["F", "(", "1", ",", "2"]);
+        assert_eq!(
+            errors,
+            vec![PreprocessError::UnterminatedMacroCall("F".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_undef() {
+        let (tokens, _) = preprocess("#define X 1\nX\n#undef X\nX");
+
+        assert_eq!(tokens, vec!["1", "X"]);
+    }
+
+    #[test]
+    fn test_conditional_compilation() {
+        let source = "\
+#define DEBUG
+#ifdef DEBUG
+a
+#else
+b
+#endif
+#ifndef DEBUG
+c
+#else
+d
+#endif
+#ifdef MISSING
+#ifdef DEBUG
+e
+#endif
+#define HIDDEN
+#else
+f
+#endif
+HIDDEN";
+        let (tokens, errors) = preprocess(source);
+
+        assert_eq!(tokens, vec!["a", "d", "f", "HIDDEN"]);
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn test_conditional_errors() {
+        let (_, errors) = preprocess("#endif\n#ifdef X\n#else\n#else\n#ifdef\n#endif");
+
+        assert_eq!(
+            errors,
+            vec![
+                PreprocessError::UnmatchedDirective("endif".to_string()),
+                PreprocessError::DuplicateElse,
+                PreprocessError::UnterminatedConditional("ifdef".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_if_conditions() {
+        let source = "\
+#define A
+#define LEVEL 3
+#define MAX(a, b) a > b
+#if defined(A) && !defined B
+a
+#endif
+#if LEVEL * 2 >= 6 && (LEVEL - 4) < 0
+b
+#endif
+#if UNDEFINED || MAX(1, 2) || ~0 != -1
+c
+#endif
+#if 1 << 4 == 16 && 7 % 4 == 3
+d
+#endif";
+        let (tokens, errors) = preprocess(source);
+
+        assert_eq!(tokens, vec!["a", "b", "d"]);
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn test_elif_chains() {
+        let source = "\
+#define A
+#ifdef A
+a
+#elif 1
+b
+#else
+c
+#endif
+#if 0
+d
+#elif defined(A)
+e
+#elif 1
+f
+#endif
+#ifdef MISSING
+#if 1
+g
+#elif 1
+h
+#endif
+#elif 0
+i
+#else
+j
+#endif";
+        let (tokens, errors) = preprocess(source);
+
+        assert_eq!(tokens, vec!["a", "e", "j"]);
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn test_invalid_condition() {
+        let (tokens, errors) = preprocess("#if 1 +\na\n#elif 1\nb\n#else\nc\n#endif\nd");
+
+        // No branch of a malformed conditional is kept
+        assert_eq!(tokens, vec!["d"]);
+        assert_eq!(
+            errors,
+            vec![PreprocessError::InvalidCondition("if".to_string())]
+        );
+
+        let (_, errors) = preprocess("#if 1 / 0\n#endif\n#if x = 1\n#endif\n#if \"s\"\n#endif");
+        assert_eq!(errors.len(), 3);
+    }
+
+    #[test]
+    fn test_elif_errors() {
+        let (tokens, errors) = preprocess("#elif 1\n#if 0\n#else\na\n#elif 1\nb\n#endif");
+
+        assert_eq!(tokens, vec!["a"]);
+        assert_eq!(
+            errors,
+            vec![
+                PreprocessError::UnmatchedDirective("elif".to_string()),
+                PreprocessError::DuplicateElse,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unknown_directive() {
+        let (tokens, errors) = preprocess("#pragma once\nx");
+
+        assert_eq!(tokens, vec!["x"]);
+        assert_eq!(
+            errors,
+            vec![PreprocessError::UnknownDirective("pragma".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_include_search_paths() {
+        let sources = SourceMap::new();
+        let main = sources.add_file("src/main.d", "#include \"util.h\"\n#include <sys/lib.h>\nx");
+        let loader = files(&[
+            ("src/util.h", "#define U u\nU"),
+            ("include/sys/lib.h", "l U"),
+        ]);
+        let mut preprocessor = Preprocessor::new(&sources)
+            .loader(loader)
+            .search_path("include");
+
+        let tokens = preprocessor.run(main);
+
+        assert_eq!(lexemes(&tokens), vec!["u", "l", "u", "x"]);
+        assert!(errors(&preprocessor).is_empty());
+        assert_eq!(
+            sources.file(tokens[1].span.file_id).name(),
+            "include/sys/lib.h"
+        );
+        assert_eq!(tokens.last().unwrap().token, Token::Eof);
+    }
+
+    #[test]
+    fn test_angled_include_ignores_current_directory() {
+        let sources = SourceMap::new();
+        let main = sources.add_file("main.d", "#include <local.h>");
+        let mut preprocessor = Preprocessor::new(&sources).loader(files(&[("local.h", "x")]));
+        preprocessor.run(main);
+
+        assert_eq!(
+            errors(&preprocessor),
+            vec![PreprocessError::IncludeNotFound("local.h".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_include_guard_skips_reading_again() {
+        let loads = Rc::new(Cell::new(0));
+        let loader = CountingLoader {
+            files: files(&[
+                (
+                    "guarded.h",
+                    "// header\n#ifndef GUARD\n#define GUARD\ng\n#endif\n",
+                ),
+                ("plain.h", "p"),
+            ]),
+            loads: loads.clone(),
+        };
+        let sources = SourceMap::new();
+        let main = sources.add_file(
+            "main.d",
+            "#include \"guarded.h\"\n#include \"guarded.h\"\n#include \"plain.h\"\n#include \"plain.h\"",
+        );
+        let mut preprocessor = Preprocessor::new(&sources).loader(loader);
+
+        let tokens = preprocessor.run(main);
+
+        assert_eq!(lexemes(&tokens), vec!["g", "p", "p"]);
+        assert_eq!(loads.get(), 3);
+    }
+
+    #[test]
+    fn test_recursive_include_is_cut_off() {
+        let sources = SourceMap::new();
+        let main = sources.add_file("main.d", "#include \"loop.h\"");
+        let mut preprocessor =
+            Preprocessor::new(&sources).loader(files(&[("loop.h", "#include \"loop.h\"\n")]));
+        preprocessor.run(main);
+
+        assert_eq!(
+            errors(&preprocessor),
+            vec![PreprocessError::IncludeTooDeep("loop.h".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_predefined_macro_feeds_parser() {
+        let sources = SourceMap::new();
+        let main = sources.add_file("main.d", "#ifdef VERSION\nlet v = VERSION;\n#endif");
+        let mut preprocessor = Preprocessor::new(&sources);
+        preprocessor.define("VERSION", "3");
+
+        let tokens = preprocessor.run(main);
+        let program = Parser::new(tokens).parse().expect("program should parse");
+
+        let Item::Statement(stmt) = &program.items[0] else {
+            panic!("Expected statement");
+        };
+        let StmtKind::Let {
+            value: Some(value), ..
+        } = &stmt.kind
+        else {
+            panic!("Expected let with a value");
+        };
+        assert_eq!(value.kind, ExprKind::Literal(Literal::Integer(3)));
+    }
+}
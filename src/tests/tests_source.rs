@@ -0,0 +1,87 @@
+use crate::lexer::lexer::Lexer;
+use crate::source::source_map::{SourceLocation, SourceMap};
+use crate::source::span::{FileId, Span};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_register_files() {
+        let map = SourceMap::new();
+        let main = map.add_file("main.d", "fn main() {}");
+        let util = map.add_file("util.d", "let x = 1;");
+
+        assert_eq!(main, FileId(0));
+        assert_eq!(util, FileId(1));
+        assert_eq!(map.file(util).name(), "util.d");
+        assert_eq!(map.text(Span::new(util, 4, 5)), "x");
+    }
+
+    #[test]
+    fn test_byte_offset_to_line_column() {
+        let map = SourceMap::new();
+        let file = map.add_file("a.d", "ab\ncd\n\nef");
+
+        let expect = |line, column| SourceLocation {
+            line,
+            column,
+            utf8_column: column,
+            utf16_column: column,
+        };
+        assert_eq!(map.file(file).location(0), expect(1, 1));
+        assert_eq!(map.file(file).location(2), expect(1, 3));
+        assert_eq!(map.file(file).location(3), expect(2, 1));
+        assert_eq!(map.file(file).location(7), expect(4, 1));
+        assert_eq!(map.file(file).location(100), expect(4, 3));
+        assert_eq!(map.file(file).line_count(), 4);
+    }
+
+    #[test]
+    fn test_utf8_and_utf16_columns() {
+        let map = SourceMap::new();
+        // 'é' is 2 bytes / 1 UTF-16 unit, '😀' is 4 bytes / 2 UTF-16 units
+        let file = map.add_file("u.d", "é😀x");
+
+        assert_eq!(
+            map.file(file).location(6),
+            SourceLocation {
+                line: 1,
+                column: 3,
+                utf8_column: 7,
+                utf16_column: 4
+            }
+        );
+        // Offsets inside a character snap back to its start
+        assert_eq!(map.file(file).location(4).column, 2);
+    }
+
+    #[test]
+    fn test_line_column_to_offset() {
+        let map = SourceMap::new();
+        let file = map.add_file("a.d", "ab\nçd");
+
+        assert_eq!(map.file(file).offset(2, 2), Some(5));
+        assert_eq!(map.file(file).offset(2, 3), Some(6));
+        assert_eq!(map.file(file).offset(2, 4), None);
+        assert_eq!(map.file(file).offset(3, 1), None);
+    }
+
+    #[test]
+    fn test_token_spans_resolve_through_source_map() {
+        let map = SourceMap::new();
+        map.add_file("first.d", "unused");
+        let source = "let café = \"ünï\nline two\";\nprint café; /* a\nb */ x";
+        let file = map.add_file("second.d", source);
+
+        let tokens = Lexer::new(source).in_file(file).tokenize();
+
+        for token in &tokens {
+            assert_eq!(token.span.file_id, file);
+            assert_eq!(map.text(token.span), token.lexeme);
+            let start = map.start(token.span);
+            assert_eq!((start.line, start.column), (token.line, token.column));
+        }
+        assert_eq!(tokens[1].span, Span::new(file, 4, 9));
+    }
+}
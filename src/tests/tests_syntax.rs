@@ -0,0 +1,176 @@
+use std::rc::Rc;
+
+use crate::lexer::token::{Reserved, Token};
+use crate::syntax::{GreenToken, SyntaxElement, SyntaxKind, SyntaxNode, Trivia, TriviaKind, parse};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Kinds of the child nodes, with `t` for each token
+    fn shape(node: &SyntaxNode) -> String {
+        node.children()
+            .iter()
+            .map(|child| match child {
+                SyntaxElement::Node(node) => format!("{:?}[{}]", node.kind(), shape(node)),
+                SyntaxElement::Token(_) => "t".to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let sources = [
+            "",
+            "   \n\n",
+            "fn main() {\n    let x = 1; // one\n}\n",
+            "/* head */ let a = [1, 2]; /* tail */\r\n\r\nlet b = a;\t\n",
+            "let s = \"a ${x + 1} b\";\nlet r = r#\"raw\"#;\n",
+            "let t = \"\"\"\nmulti\n\"\"\";",
+            "let bad = 1 ¤¤ 2;\n",
+            "let open = \"never closed\nlet next = 2;",
+            "fn f( {\n  [ ) }\n}}",
+            "/* unterminated",
+            "// only a comment",
+        ];
+
+        for source in sources {
+            let tree = parse(source);
+            assert_eq!(tree.to_string(), source);
+            assert_eq!(tree.range(), 0..source.len());
+        }
+    }
+
+    #[test]
+    fn test_trivia_attachment() {
+        let tree = parse("let x = 1; // one\n\n// two\nx");
+        let tokens = tree.tokens();
+
+        let semicolon = &tokens[4];
+        assert_eq!(semicolon.text(), ";");
+        assert_eq!(
+            semicolon.trailing_trivia(),
+            &[
+                Trivia {
+                    kind: TriviaKind::Whitespace,
+                    text: " ".to_string()
+                },
+                Trivia {
+                    kind: TriviaKind::LineComment,
+                    text: "// one".to_string()
+                },
+            ]
+        );
+
+        let x = &tokens[5];
+        assert_eq!(x.text(), "x");
+        let leading: Vec<_> = x.leading_trivia().iter().map(|t| t.kind).collect();
+        assert_eq!(
+            leading,
+            vec![
+                TriviaKind::Newline,
+                TriviaKind::Newline,
+                TriviaKind::LineComment,
+                TriviaKind::Newline
+            ]
+        );
+        assert!(x.trailing_trivia().is_empty());
+    }
+
+    #[test]
+    fn test_trailing_trivia_at_end_of_input() {
+        let tree = parse("x /* c */\n  ");
+        let tokens = tree.tokens();
+
+        assert_eq!(tokens.len(), 2);
+        assert_eq!(tokens[0].trailing_trivia().len(), 2);
+        assert_eq!(*tokens[1].token(), Token::Eof);
+        assert_eq!(tokens[1].leading_trivia().len(), 2);
+    }
+
+    #[test]
+    fn test_tree_shape() {
+        let tree = parse("let a = f(x[0]);\nif a { b; } else { c }\nfn g() {}\n");
+
+        assert_eq!(tree.kind(), SyntaxKind::Root);
+        assert_eq!(
+            shape(&tree),
+            "Statement[t t t t Parens[t t Brackets[t t t] t] t] \
+             Statement[t t Block[t Statement[t t] t] t Block[t Statement[t] t]] \
+             Statement[t t Parens[t t] Block[t t]] t"
+        );
+    }
+
+    #[test]
+    fn test_unbalanced_delimiters() {
+        let tree = parse("f(a; } g");
+
+        // `}` closes nothing, and the `(` is closed at the end of input
+        assert_eq!(shape(&tree), "Statement[t Parens[t t t t t]] t");
+    }
+
+    #[test]
+    fn test_red_tree_positions() {
+        let source = "fn f() {\n    ret(1);\n}";
+        let tree = parse(source);
+
+        let one = tree
+            .token_at(source.find('1').unwrap())
+            .expect("token at 1");
+        assert_eq!(one.text(), "1");
+        assert_eq!(&source[one.range()], "1");
+
+        let parens = one.parent();
+        assert_eq!(parens.kind(), SyntaxKind::Parens);
+        assert_eq!(parens.to_string(), "(1)");
+        let statement = parens.parent().expect("parens are in a statement");
+        assert_eq!(statement.kind(), SyntaxKind::Statement);
+        assert_eq!(statement.to_string(), "\n    ret(1);");
+        assert_eq!(&source[statement.range()], "\n    ret(1);");
+
+        let ret = &statement.tokens()[0];
+        assert_eq!(ret.range(), 13..16);
+        assert_eq!(ret.full_range(), 8..16);
+
+        let block = statement.parent().expect("statement is in a block");
+        assert_eq!(block.kind(), SyntaxKind::Block);
+        let root = block.parent().and_then(|s| s.parent()).expect("root");
+        assert_eq!(root.kind(), SyntaxKind::Root);
+        assert!(root.parent().is_none());
+    }
+
+    #[test]
+    fn test_replace_token_shares_unchanged_nodes() {
+        let tree = parse("let a = 1;\nlet b = (2);\n");
+        let two = tree.tokens()[9].clone();
+        assert_eq!(two.text(), "2");
+
+        let replaced = GreenToken::new(
+            two.token().clone(),
+            "20",
+            two.leading_trivia().to_vec(),
+            two.trailing_trivia().to_vec(),
+        );
+        let edited = two.replace_with(replaced);
+
+        assert_eq!(edited.to_string(), "let a = 1;\nlet b = (20);\n");
+        assert_eq!(tree.to_string(), "let a = 1;\nlet b = (2);\n");
+
+        // Only the nodes on the path to the root are new
+        let old = tree.child_nodes();
+        let new = edited.child_nodes();
+        assert!(Rc::ptr_eq(old[0].green(), new[0].green()));
+        assert!(!Rc::ptr_eq(old[1].green(), new[1].green()));
+        assert_eq!(new[1].range(), 10..24);
+    }
+
+    #[test]
+    fn test_tokens_keep_their_kind() {
+        let tree = parse("while x { }");
+        let tokens = tree.tokens();
+
+        assert_eq!(*tokens[0].token(), Token::Reserved(Reserved::While));
+        assert_eq!(*tokens.last().unwrap().token(), Token::Eof);
+    }
+}
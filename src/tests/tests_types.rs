@@ -0,0 +1,453 @@
+use crate::lexer::lexer::Lexer;
+use crate::parser::ast::Program;
+use crate::parser::parser::Parser;
+use crate::types::checker::TypeChecker;
+use crate::types::error::TypeError;
+use crate::types::ty::{Primitive, Type};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &str) -> Program {
+        Parser::new(Lexer::new(input))
+            .parse()
+            .expect("program should parse")
+    }
+
+    fn errors(input: &str) -> Vec<TypeError> {
+        let program = parse(input);
+        TypeChecker::new(&program)
+            .check()
+            .into_iter()
+            .map(|diagnostic| diagnostic.error)
+            .collect()
+    }
+
+    fn vector(arg: Type) -> Type {
+        Type::Struct {
+            name: "Vector".to_string(),
+            args: vec![arg],
+        }
+    }
+
+    const VECTOR: &str = "
+struct Vector<T> {
+    x: T,
+    y: T,
+
+    fn length(&self) -> f64 {
+        sqrt(self.x*self.x + self.y*self.y)
+    }
+
+    fn scaled(&self, by: T) -> Vector<T> {
+        return self;
+    }
+}
+";
+
+    #[test]
+    fn test_readme_example_checks() {
+        assert_eq!(errors(VECTOR), vec![]);
+    }
+
+    #[test]
+    fn test_field_types_are_substituted() {
+        let program = parse(&format!(
+            "{}\nstruct Line {{ from: Vector<f64>, to: Vector<f64> }}",
+            VECTOR
+        ));
+        let checker = TypeChecker::new(&program);
+
+        let f64_vector = vector(Type::Primitive(Primitive::F64));
+        assert_eq!(
+            checker.field_type(&f64_vector, "x"),
+            Some(Type::Primitive(Primitive::F64))
+        );
+        let line = Type::Struct {
+            name: "Line".to_string(),
+            args: vec![],
+        };
+        assert_eq!(checker.field_type(&line, "from"), Some(f64_vector.clone()));
+        assert_eq!(checker.field_type(&line, "length"), None);
+        assert!(checker.method(&f64_vector, "length").is_some());
+        assert_eq!(f64_vector.to_string(), "Vector<f64>");
+    }
+
+    #[test]
+    fn test_unknown_types_and_argument_counts() {
+        let found = errors(
+            "struct Pair<A, B> { a: A, b: B }
+             struct S<T> { p: Pair<T>, q: Pair<T, Strange>, r: T<int>, s: int<T>, t: Missing }",
+        );
+
+        assert_eq!(
+            found,
+            vec![
+                TypeError::TypeArgumentCount {
+                    name: "Pair".to_string(),
+                    expected: 2,
+                    found: 1
+                },
+                TypeError::UnknownType("Strange".to_string()),
+                TypeError::TypeArgumentCount {
+                    name: "T".to_string(),
+                    expected: 0,
+                    found: 1
+                },
+                TypeError::TypeArgumentCount {
+                    name: "int".to_string(),
+                    expected: 0,
+                    found: 1
+                },
+                TypeError::UnknownType("Missing".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_type_parameters_are_scoped_to_their_struct() {
+        let found = errors(
+            "struct Box<T> { value: T }
+             fn unwrap(b: Box<int>) -> T { }",
+        );
+
+        assert_eq!(found, vec![TypeError::UnknownType("T".to_string())]);
+    }
+
+    #[test]
+    fn test_duplicates() {
+        let found = errors(
+            "struct P<T, T> { x: T, x: T, fn x(&self) { } }
+             struct P { }",
+        );
+
+        assert_eq!(
+            found,
+            vec![
+                TypeError::DuplicateTypeParameter("T".to_string()),
+                TypeError::DuplicateMember {
+                    ty: "P".to_string(),
+                    member: "x".to_string()
+                },
+                TypeError::DuplicateMember {
+                    ty: "P".to_string(),
+                    member: "x".to_string()
+                },
+                TypeError::DuplicateType("P".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_member_access() {
+        let found = errors(&format!(
+            "{}
+fn main(v: Vector<f64>) {{
+    let n = v.length();
+    let inner: Vector<Vector<int>> = make();
+    print inner.x.y + inner.x.z + v.w;
+    print n.size;
+}}",
+            VECTOR
+        ));
+
+        assert_eq!(
+            found,
+            vec![
+                TypeError::UnknownMember {
+                    ty: "Vector<int>".to_string(),
+                    member: "z".to_string()
+                },
+                TypeError::UnknownMember {
+                    ty: "Vector<f64>".to_string(),
+                    member: "w".to_string()
+                },
+                TypeError::UnknownMember {
+                    ty: "f64".to_string(),
+                    member: "size".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_members_of_type_parameters() {
+        let found = errors("struct W<T> { v: T, fn f(&self) { print self.v.anything; } }");
+
+        assert_eq!(
+            found,
+            vec![TypeError::UnknownMember {
+                ty: "T".to_string(),
+                member: "anything".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_methods_from_impl_blocks() {
+        let found = errors(
+            "struct P { x: int }
+             trait Sized { fn size(&self) -> int; }
+             impl P { fn len(&self) -> int { return self.x; } fn me(&self) -> Self { return self; } }
+             impl Sized for P { fn size(&self) -> int { return 1; } }
+             fn f(p: P) {
+                 print p.len() + p.size();
+                 print p.me().x + p.me().y;
+                 print p.missing();
+             }",
+        );
+
+        assert_eq!(
+            found,
+            vec![
+                TypeError::UnknownMember {
+                    ty: "P".to_string(),
+                    member: "y".to_string()
+                },
+                TypeError::UnknownMember {
+                    ty: "P".to_string(),
+                    member: "missing".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_methods_of_enums() {
+        let found = errors(
+            "enum S { A, B }
+             impl S { fn area(&self) -> int { return 1; } fn flip(&self) -> S { return self; } }
+             fn f(s: S) {
+                 print s.area() + s.flip().area();
+                 print s.flip().size;
+             }",
+        );
+
+        assert_eq!(
+            found,
+            vec![TypeError::UnknownMember {
+                ty: "S".to_string(),
+                member: "size".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_self_outside_method() {
+        let program = parse("fn free(&self) { }");
+        let diagnostics = TypeChecker::new(&program).check();
+
+        assert_eq!(diagnostics[0].error, TypeError::SelfOutsideMethod);
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "error[T0007] 1:9: &self is only allowed in methods"
+        );
+    }
+
+    #[test]
+    fn test_diagnostic_display() {
+        let program = parse("struct S { a: Vector<int, int> }");
+        let diagnostics = TypeChecker::new(&program).check();
+
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "error[T0001] 1:15: Unknown type Vector"
+        );
+    }
+
+    const SHAPES: &str = "
+enum Shape {
+    Empty,
+    Circle(f64),
+    Rect { w: f64, h: f64 },
+}
+
+enum Option<T> {
+    Some(T),
+    None,
+}
+";
+
+    // Errors from `body` checked as the body of `fn f(s: Shape, o: Option<Shape>, b: bool, n: int)`
+    fn match_errors(body: &str) -> Vec<TypeError> {
+        errors(&format!(
+            "{}\nfn f(s: Shape, o: Option<Shape>, b: bool, n: int) {{\n{}\n}}",
+            SHAPES, body
+        ))
+    }
+
+    fn non_exhaustive(missing: &[&str]) -> TypeError {
+        TypeError::NonExhaustiveMatch(missing.iter().map(|m| m.to_string()).collect())
+    }
+
+    #[test]
+    fn test_exhaustive_matches() {
+        let found = match_errors(
+            "let area = match s {
+                Shape::Empty => 0.0,
+                Shape::Circle(r) if r > 10.0 => 100.0,
+                Shape::Circle(r) => r * r,
+                Shape::Rect { w, h } => w * h,
+            };
+            match o {
+                Some(Circle(_)) | None => 0,
+                Some(Rect { w: _, .. }) => { print 1; }
+                Some(Empty) => 2,
+            }
+            match b { true => 1, false => 0 }
+            match n { 0 => 0, other => other }",
+        );
+
+        assert_eq!(found, vec![]);
+    }
+
+    #[test]
+    fn test_missing_variants_are_named() {
+        let found = match_errors(
+            "match s { Shape::Circle(r) => r, }
+             match o { Some(Shape::Circle(_)) => 1, None => 0 }
+             match b { true => 1 }
+             match n { 1 => 1, 2 => 2 }",
+        );
+
+        assert_eq!(
+            found,
+            vec![
+                non_exhaustive(&["Shape::Empty", "Shape::Rect { .. }"]),
+                non_exhaustive(&[
+                    "Option::Some(Shape::Empty)",
+                    "Option::Some(Shape::Rect { .. })"
+                ]),
+                non_exhaustive(&["false"]),
+                non_exhaustive(&["_"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_guarded_arms_do_not_cover() {
+        let found = match_errors(
+            "match s {
+                Shape::Empty => 0,
+                Shape::Circle(r) if r > 1.0 => 1,
+                Shape::Rect { .. } => 2,
+            }",
+        );
+
+        assert_eq!(found, vec![non_exhaustive(&["Shape::Circle(_)"])]);
+    }
+
+    #[test]
+    fn test_unreachable_patterns() {
+        let program = parse(&format!(
+            "{}\nfn f(s: Shape) {{
+    match s {{
+        Empty | Circle(_) | Empty => 0,
+        Rect {{ w, .. }} => 1,
+        Rect {{ h, .. }} => 2,
+        anything => 3,
+    }}
+}}",
+            SHAPES
+        ));
+        let diagnostics = TypeChecker::new(&program).check();
+
+        let found: Vec<_> = diagnostics.iter().map(|d| d.to_string()).collect();
+        assert_eq!(
+            found,
+            vec![
+                "error[T0014] 15:29: Unreachable pattern",
+                "error[T0014] 17:9: Unreachable pattern",
+                "error[T0014] 18:9: Unreachable pattern",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_non_exhaustive_diagnostic() {
+        let program = parse(&format!(
+            "{}\nfn f(s: Shape) {{ match s {{ Shape::Empty => 0 }} }}",
+            SHAPES
+        ));
+        let diagnostics = TypeChecker::new(&program).check();
+
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "error[T0013] 13:18: Non-exhaustive match: Shape::Circle(_) and \
+             Shape::Rect { .. } are not covered"
+        );
+    }
+
+    #[test]
+    fn test_malformed_patterns() {
+        let found = match_errors(
+            "match s {
+                Shape::Square => 0,
+                Shape::Circle(a, b) => 1,
+                Shape::Circle { .. } => 2,
+                Shape::Rect { w } => 3,
+                Shape::Rect { d, .. } => 4,
+                Option::None => 5,
+                1 => 6,
+            }",
+        );
+
+        assert_eq!(
+            found,
+            vec![
+                TypeError::UnknownVariant("Shape::Square".to_string()),
+                TypeError::PatternFieldCount {
+                    variant: "Shape::Circle".to_string(),
+                    expected: 1,
+                    found: 2
+                },
+                TypeError::PatternShape {
+                    variant: "Shape::Circle".to_string(),
+                    kind: "tuple"
+                },
+                TypeError::MissingPatternFields {
+                    variant: "Shape::Rect".to_string(),
+                    fields: vec!["h".to_string()]
+                },
+                TypeError::UnknownMember {
+                    ty: "Shape::Rect".to_string(),
+                    member: "d".to_string()
+                },
+                TypeError::PatternTypeMismatch {
+                    expected: "Shape".to_string(),
+                    found: "Option".to_string()
+                },
+                TypeError::PatternTypeMismatch {
+                    expected: "Shape".to_string(),
+                    found: "int".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_enum_declarations() {
+        let found = errors(
+            "enum E<T, T> { A(T), A, B { x: int, x: Missing } }
+             struct E { }",
+        );
+
+        assert_eq!(
+            found,
+            vec![
+                TypeError::DuplicateTypeParameter("T".to_string()),
+                TypeError::DuplicateMember {
+                    ty: "E".to_string(),
+                    member: "A".to_string()
+                },
+                TypeError::DuplicateMember {
+                    ty: "E::B".to_string(),
+                    member: "x".to_string()
+                },
+                TypeError::UnknownType("Missing".to_string()),
+                TypeError::DuplicateType("E".to_string()),
+            ]
+        );
+    }
+}
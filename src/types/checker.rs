@@ -0,0 +1,642 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::parser::ast::{
+    Block, EnumDecl, Expr, ExprKind, FunctionDecl, GenericParam, ImplDecl, Item, Literal, MatchArm,
+    Pattern, PatternFields, PatternKind, Program, Stmt, StmtKind, StringPart, StructDecl, TypeRef,
+    VariantKind,
+};
+use crate::types::error::{TypeDiagnostic, TypeError};
+use crate::types::exhaustiveness;
+use crate::types::ty::{Primitive, Type};
+
+// What a type name can refer to at some point of the program
+#[derive(Debug, Clone, Default)]
+struct Scope<'a> {
+    // Type parameters of the enclosing struct or enum
+    generics: &'a [GenericParam],
+    // What `Self` means inside a struct or impl
+    self_type: Option<Type>,
+}
+
+// Local variables whose type is known
+type Locals = HashMap<String, Type>;
+
+// Checks struct and enum declarations with their fields and methods, the
+// signatures of functions, and the member accesses and `match` expressions
+// inside their bodies. Types of other expressions are only inferred where a
+// member access or a `match` needs them.
+pub struct TypeChecker<'a> {
+    program: &'a Program,
+    // The first declaration of each struct name
+    structs: HashMap<&'a str, &'a StructDecl>,
+    // The first declaration of each enum name
+    enums: HashMap<&'a str, &'a EnumDecl>,
+    // `impl` blocks, with or without a trait, by the name of their target
+    impls: HashMap<&'a str, Vec<&'a ImplDecl>>,
+}
+
+impl<'a> TypeChecker<'a> {
+    pub fn new(program: &'a Program) -> Self {
+        let mut structs = HashMap::new();
+        let mut enums = HashMap::new();
+        let mut impls: HashMap<_, Vec<_>> = HashMap::new();
+        for item in &program.items {
+            match item {
+                Item::Struct(decl) => {
+                    structs.entry(decl.name.as_str()).or_insert(decl);
+                }
+                Item::Enum(decl) => {
+                    enums.entry(decl.name.as_str()).or_insert(decl);
+                }
+                Item::Impl(decl) => impls
+                    .entry(decl.target.name.as_str())
+                    .or_default()
+                    .push(decl),
+                _ => {}
+            }
+        }
+
+        Self {
+            program,
+            structs,
+            enums,
+            impls,
+        }
+    }
+
+    pub fn struct_decl(&self, name: &str) -> Option<&'a StructDecl> {
+        self.structs.get(name).copied()
+    }
+
+    pub fn enum_decl(&self, name: &str) -> Option<&'a EnumDecl> {
+        self.enums.get(name).copied()
+    }
+
+    pub fn enum_decls(&self) -> impl Iterator<Item = &'a EnumDecl> + '_ {
+        self.enums.values().copied()
+    }
+
+    pub fn check(&self) -> Vec<TypeDiagnostic> {
+        let mut diagnostics = Vec::new();
+        // Structs and enums share one namespace
+        let mut types = HashSet::new();
+
+        for item in &self.program.items {
+            match item {
+                Item::Struct(decl) => {
+                    if !types.insert(decl.name.as_str()) {
+                        diagnostics.push(TypeDiagnostic {
+                            error: TypeError::DuplicateType(decl.name.clone()),
+                            position: decl.position,
+                        });
+                    }
+                    self.check_struct(decl, &mut diagnostics);
+                }
+                Item::Enum(decl) => {
+                    if !types.insert(decl.name.as_str()) {
+                        diagnostics.push(TypeDiagnostic {
+                            error: TypeError::DuplicateType(decl.name.clone()),
+                            position: decl.position,
+                        });
+                    }
+                    self.check_enum(decl, &mut diagnostics);
+                }
+                Item::Function(function) => {
+                    self.check_function(function, &Scope::default(), &mut diagnostics)
+                }
+                Item::Impl(decl) => {
+                    let self_type = self.report(
+                        self.resolve(&decl.target, &Scope::default()),
+                        &mut diagnostics,
+                    );
+                    let scope = Scope {
+                        generics: &[],
+                        self_type,
+                    };
+                    for method in &decl.methods {
+                        self.check_function(method, &scope, &mut diagnostics);
+                    }
+                }
+                Item::Statement(stmt) => self.check_statement(
+                    stmt,
+                    &Scope::default(),
+                    &mut Locals::new(),
+                    &mut diagnostics,
+                ),
+                // `Self` in a trait stands for every implementing type
+                Item::Trait(_) | Item::Use(_) | Item::Error(_) => {}
+            }
+        }
+
+        diagnostics
+    }
+
+    // Type named by `ty` where `scope` applies
+    fn resolve(&self, ty: &TypeRef, scope: &Scope) -> Result<Type, TypeDiagnostic> {
+        let error = |error| TypeDiagnostic {
+            error,
+            position: ty.position,
+        };
+        let arity = |expected: usize| {
+            if ty.args.len() == expected {
+                Ok(())
+            } else {
+                Err(error(TypeError::TypeArgumentCount {
+                    name: ty.name.clone(),
+                    expected,
+                    found: ty.args.len(),
+                }))
+            }
+        };
+
+        let resolved =
+            if let Some(self_type) = scope.self_type.as_ref().filter(|_| ty.name == "Self") {
+                arity(0)?;
+                self_type.clone()
+            } else if scope.generics.iter().any(|param| param.name == ty.name) {
+                arity(0)?;
+                Type::Param(ty.name.clone())
+            } else if let Some(primitive) = Primitive::from_name(&ty.name) {
+                arity(0)?;
+                Type::Primitive(primitive)
+            } else if let Some(decl) = self.struct_decl(&ty.name) {
+                arity(decl.generics.len())?;
+                Type::Struct {
+                    name: ty.name.clone(),
+                    args: ty
+                        .args
+                        .iter()
+                        .map(|arg| self.resolve(arg, scope))
+                        .collect::<Result<_, _>>()?,
+                }
+            } else if let Some(decl) = self.enum_decl(&ty.name) {
+                arity(decl.generics.len())?;
+                Type::Enum {
+                    name: ty.name.clone(),
+                    args: ty
+                        .args
+                        .iter()
+                        .map(|arg| self.resolve(arg, scope))
+                        .collect::<Result<_, _>>()?,
+                }
+            } else {
+                return Err(error(TypeError::UnknownType(ty.name.clone())));
+            };
+
+        Ok(if ty.reference {
+            Type::Reference(Box::new(resolved))
+        } else {
+            resolved
+        })
+    }
+
+    fn report(
+        &self,
+        result: Result<Type, TypeDiagnostic>,
+        diagnostics: &mut Vec<TypeDiagnostic>,
+    ) -> Option<Type> {
+        result
+            .map_err(|diagnostic| diagnostics.push(diagnostic))
+            .ok()
+    }
+
+    // Scope inside the body of `decl`, where `Self` is the struct applied to
+    // its own type parameters
+    fn struct_scope(decl: &'a StructDecl) -> Scope<'a> {
+        Scope {
+            generics: &decl.generics,
+            self_type: Some(Type::Struct {
+                name: decl.name.clone(),
+                args: decl
+                    .generics
+                    .iter()
+                    .map(|param| Type::Param(param.name.clone()))
+                    .collect(),
+            }),
+        }
+    }
+
+    // Type of the field `name` of `ty`, with the struct's type parameters
+    // replaced by the arguments of `ty`
+    pub fn field_type(&self, ty: &Type, name: &str) -> Option<Type> {
+        let Type::Struct { name: owner, args } = ty.dereference() else {
+            return None;
+        };
+        let decl = self.struct_decl(owner)?;
+        let field = decl.fields.iter().find(|field| field.name == name)?;
+
+        let declared = self.resolve(&field.ty, &Self::struct_scope(decl)).ok()?;
+        Some(declared.substitute(&decl.generics, args))
+    }
+
+    // Methods written in a struct body come before those of `impl` blocks,
+    // which are the only place enum methods can be written
+    pub fn method(&self, ty: &Type, name: &str) -> Option<&'a FunctionDecl> {
+        let (Type::Struct { name: owner, .. } | Type::Enum { name: owner, .. }) = ty.dereference()
+        else {
+            return None;
+        };
+        let inline = self
+            .struct_decl(owner)
+            .into_iter()
+            .flat_map(|decl| &decl.methods);
+        let implemented = self
+            .impls
+            .get(owner.as_str())
+            .into_iter()
+            .flatten()
+            .flat_map(|decl| &decl.methods);
+
+        inline.chain(implemented).find(|method| method.name == name)
+    }
+
+    // Return type of calling the method `name` on `ty`
+    fn method_return_type(&self, ty: &Type, name: &str) -> Option<Type> {
+        let method = self.method(ty, name)?;
+        let return_type = method.return_type.as_ref()?;
+
+        if let Type::Struct { name: owner, args } = ty.dereference()
+            && let Some(decl) = self.struct_decl(owner)
+            && decl
+                .methods
+                .iter()
+                .any(|inline| std::ptr::eq(inline, method))
+        {
+            let declared = self.resolve(return_type, &Self::struct_scope(decl)).ok()?;
+            return Some(declared.substitute(&decl.generics, args));
+        }
+
+        // `impl` blocks take no type parameters, so `Self` is all they add
+        let scope = Scope {
+            generics: &[],
+            self_type: Some(ty.dereference().clone()),
+        };
+        self.resolve(return_type, &scope).ok()
+    }
+
+    fn check_generics(generics: &[GenericParam], diagnostics: &mut Vec<TypeDiagnostic>) {
+        let mut seen = HashSet::new();
+        for param in generics {
+            if !seen.insert(param.name.as_str()) {
+                diagnostics.push(TypeDiagnostic {
+                    error: TypeError::DuplicateTypeParameter(param.name.clone()),
+                    position: param.position,
+                });
+            }
+        }
+    }
+
+    fn check_struct(&self, decl: &'a StructDecl, diagnostics: &mut Vec<TypeDiagnostic>) {
+        Self::check_generics(&decl.generics, diagnostics);
+
+        let members = decl
+            .fields
+            .iter()
+            .map(|field| (&field.name, field.position))
+            .chain(
+                decl.methods
+                    .iter()
+                    .map(|method| (&method.name, method.position)),
+            );
+        let mut seen = HashSet::new();
+        for (member, position) in members {
+            if !seen.insert(member) {
+                diagnostics.push(TypeDiagnostic {
+                    error: TypeError::DuplicateMember {
+                        ty: decl.name.clone(),
+                        member: member.clone(),
+                    },
+                    position,
+                });
+            }
+        }
+
+        let scope = Self::struct_scope(decl);
+        for field in &decl.fields {
+            self.report(self.resolve(&field.ty, &scope), diagnostics);
+        }
+        for method in &decl.methods {
+            self.check_function(method, &scope, diagnostics);
+        }
+    }
+
+    // Variant names must be unique within the enum, as must the fields of a
+    // struct variant
+    fn check_enum(&self, decl: &'a EnumDecl, diagnostics: &mut Vec<TypeDiagnostic>) {
+        Self::check_generics(&decl.generics, diagnostics);
+
+        let scope = Scope {
+            generics: &decl.generics,
+            self_type: Some(Type::Enum {
+                name: decl.name.clone(),
+                args: decl
+                    .generics
+                    .iter()
+                    .map(|param| Type::Param(param.name.clone()))
+                    .collect(),
+            }),
+        };
+        let mut variants = HashSet::new();
+        for variant in &decl.variants {
+            if !variants.insert(variant.name.as_str()) {
+                diagnostics.push(TypeDiagnostic {
+                    error: TypeError::DuplicateMember {
+                        ty: decl.name.clone(),
+                        member: variant.name.clone(),
+                    },
+                    position: variant.position,
+                });
+            }
+
+            match &variant.kind {
+                VariantKind::Unit => {}
+                VariantKind::Tuple(types) => {
+                    for ty in types {
+                        self.report(self.resolve(ty, &scope), diagnostics);
+                    }
+                }
+                VariantKind::Struct(fields) => {
+                    let mut seen = HashSet::new();
+                    for field in fields {
+                        if !seen.insert(field.name.as_str()) {
+                            diagnostics.push(TypeDiagnostic {
+                                error: TypeError::DuplicateMember {
+                                    ty: format!("{}::{}", decl.name, variant.name),
+                                    member: field.name.clone(),
+                                },
+                                position: field.position,
+                            });
+                        }
+                        self.report(self.resolve(&field.ty, &scope), diagnostics);
+                    }
+                }
+            }
+        }
+    }
+
+    fn check_function(
+        &self,
+        function: &FunctionDecl,
+        scope: &Scope,
+        diagnostics: &mut Vec<TypeDiagnostic>,
+    ) {
+        let mut locals = Locals::new();
+
+        for param in &function.params {
+            let ty = match (&param.ty, &scope.self_type) {
+                // `self` without a type is the receiver itself
+                (None, Some(self_type)) if param.name == "self" => Some(self_type.clone()),
+                (None, _) => None,
+                (Some(ty), None) if ty.name == "Self" && param.name == "self" => {
+                    diagnostics.push(TypeDiagnostic {
+                        error: TypeError::SelfOutsideMethod,
+                        position: param.position,
+                    });
+                    None
+                }
+                (Some(ty), _) => self.report(self.resolve(ty, scope), diagnostics),
+            };
+            if let Some(ty) = ty {
+                locals.insert(param.name.clone(), ty);
+            }
+        }
+
+        if let Some(return_type) = &function.return_type {
+            self.report(self.resolve(return_type, scope), diagnostics);
+        }
+        if let Some(body) = &function.body {
+            self.check_block(body, scope, &locals, diagnostics);
+        }
+    }
+
+    // Variables declared in the block go out of scope at its end
+    fn check_block(
+        &self,
+        block: &Block,
+        scope: &Scope,
+        locals: &Locals,
+        diagnostics: &mut Vec<TypeDiagnostic>,
+    ) {
+        let mut locals = locals.clone();
+        for stmt in &block.statements {
+            self.check_statement(stmt, scope, &mut locals, diagnostics);
+        }
+    }
+
+    fn check_statement(
+        &self,
+        stmt: &Stmt,
+        scope: &Scope,
+        locals: &mut Locals,
+        diagnostics: &mut Vec<TypeDiagnostic>,
+    ) {
+        match &stmt.kind {
+            StmtKind::Let { name, ty, value } => {
+                if let Some(value) = value {
+                    self.check_expr(value, scope, locals, diagnostics);
+                }
+                let declared = match ty {
+                    Some(ty) => self.report(self.resolve(ty, scope), diagnostics),
+                    None => value
+                        .as_ref()
+                        .and_then(|value| self.expr_type(value, locals)),
+                };
+                // A new variable of unknown type hides an outer one
+                match declared {
+                    Some(declared) => locals.insert(name.clone(), declared),
+                    None => locals.remove(name),
+                };
+            }
+            StmtKind::Expr(expr) | StmtKind::Print(expr) | StmtKind::Return(Some(expr)) => {
+                self.check_expr(expr, scope, locals, diagnostics)
+            }
+            StmtKind::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                self.check_expr(condition, scope, locals, diagnostics);
+                self.check_block(then_branch, scope, locals, diagnostics);
+                if let Some(else_branch) = else_branch {
+                    self.check_statement(else_branch, scope, locals, diagnostics);
+                }
+            }
+            StmtKind::While { condition, body } => {
+                self.check_expr(condition, scope, locals, diagnostics);
+                self.check_block(body, scope, locals, diagnostics);
+            }
+            StmtKind::For {
+                variable,
+                iterable,
+                body,
+            } => {
+                self.check_expr(iterable, scope, locals, diagnostics);
+                let mut inner = locals.clone();
+                inner.remove(variable);
+                self.check_block(body, scope, &inner, diagnostics);
+            }
+            StmtKind::Block(block) => self.check_block(block, scope, locals, diagnostics),
+            StmtKind::Return(None) | StmtKind::Break | StmtKind::Continue | StmtKind::Error => {}
+        }
+    }
+
+    // Reports member accesses on values of known type that name no field or
+    // method of that type, and `match` expressions with missing or
+    // unreachable arms
+    fn check_expr(
+        &self,
+        expr: &Expr,
+        scope: &Scope,
+        locals: &Locals,
+        diagnostics: &mut Vec<TypeDiagnostic>,
+    ) {
+        if let ExprKind::Match { scrutinee, arms } = &expr.kind {
+            self.check_expr(scrutinee, scope, locals, diagnostics);
+            self.check_arms(arms, scope, locals, diagnostics);
+            let ty = self.expr_type(scrutinee, locals);
+            exhaustiveness::check_match(self, ty.as_ref(), arms, expr.position, diagnostics);
+            return;
+        }
+        if let ExprKind::Block(block) = &expr.kind {
+            self.check_block(block, scope, locals, diagnostics);
+            return;
+        }
+        let mut check = |expr: &Expr| self.check_expr(expr, scope, locals, diagnostics);
+
+        match &expr.kind {
+            ExprKind::Member { object, field } => {
+                check(object);
+                if let Some(ty) = self.expr_type(object, locals)
+                    && self.field_type(&ty, field).is_none()
+                    && self.method(&ty, field).is_none()
+                {
+                    diagnostics.push(TypeDiagnostic {
+                        error: TypeError::UnknownMember {
+                            ty: ty.dereference().to_string(),
+                            member: field.clone(),
+                        },
+                        position: expr.position,
+                    });
+                }
+            }
+            ExprKind::Interpolation(parts) => {
+                for part in parts {
+                    if let StringPart::Expr(expr) = part {
+                        check(expr);
+                    }
+                }
+            }
+            ExprKind::Unary { operand, .. } | ExprKind::Postfix { operand, .. } => check(operand),
+            ExprKind::Binary { left, right, .. } => {
+                check(left);
+                check(right);
+            }
+            ExprKind::Assign { target, value } | ExprKind::CompoundAssign { target, value, .. } => {
+                check(target);
+                check(value);
+            }
+            ExprKind::Ternary {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                check(condition);
+                check(then_branch);
+                check(else_branch);
+            }
+            ExprKind::Call { callee, args } => {
+                check(callee);
+                for arg in args {
+                    check(arg);
+                }
+            }
+            ExprKind::Index { object, index } => {
+                check(object);
+                check(index);
+            }
+            ExprKind::StructLiteral { fields, .. } => {
+                for (_, value) in fields {
+                    check(value);
+                }
+            }
+            ExprKind::Literal(_) | ExprKind::Identifier(_) | ExprKind::Path(_) => {}
+            ExprKind::Match { .. } | ExprKind::Block(_) => unreachable!("checked above"),
+        }
+    }
+
+    // Names bound by a pattern hide outer variables of the same name in the
+    // guard and body of their arm
+    fn check_arms(
+        &self,
+        arms: &[MatchArm],
+        scope: &Scope,
+        locals: &Locals,
+        diagnostics: &mut Vec<TypeDiagnostic>,
+    ) {
+        for arm in arms {
+            let mut inner = locals.clone();
+            remove_bindings(&arm.pattern, &mut inner);
+            if let Some(guard) = &arm.guard {
+                self.check_expr(guard, scope, &inner, diagnostics);
+            }
+            self.check_expr(&arm.body, scope, &inner, diagnostics);
+        }
+    }
+
+    // Type of `expr` when it follows from literals, variables, fields and
+    // method return types alone
+    fn expr_type(&self, expr: &Expr, locals: &Locals) -> Option<Type> {
+        match &expr.kind {
+            ExprKind::Literal(literal) => {
+                let primitive = match literal {
+                    Literal::Integer(_) => Primitive::Int,
+                    Literal::Float(_) => Primitive::Float,
+                    Literal::String(_) => Primitive::String,
+                    Literal::Char(_) => Primitive::Char,
+                    Literal::Bool(_) => Primitive::Bool,
+                    Literal::Null => return None,
+                };
+                Some(Type::Primitive(primitive))
+            }
+            ExprKind::Interpolation(_) => Some(Type::Primitive(Primitive::String)),
+            ExprKind::Identifier(name) => locals.get(name).cloned(),
+            ExprKind::Member { object, field } => {
+                self.field_type(&self.expr_type(object, locals)?, field)
+            }
+            ExprKind::Call { callee, .. } => match &callee.kind {
+                ExprKind::Member { object, field } => {
+                    self.method_return_type(&self.expr_type(object, locals)?, field)
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+fn remove_bindings(pattern: &Pattern, locals: &mut Locals) {
+    match &pattern.kind {
+        PatternKind::Binding(name) => {
+            locals.remove(name);
+        }
+        PatternKind::Variant { fields, .. } => match fields {
+            PatternFields::Unit => {}
+            PatternFields::Tuple(patterns) => {
+                for pattern in patterns {
+                    remove_bindings(pattern, locals);
+                }
+            }
+            PatternFields::Struct { fields, .. } => {
+                for (_, pattern) in fields {
+                    remove_bindings(pattern, locals);
+                }
+            }
+        },
+        PatternKind::Or(alternatives) => {
+            for alternative in alternatives {
+                remove_bindings(alternative, locals);
+            }
+        }
+        PatternKind::Wildcard | PatternKind::Literal(_) => {}
+    }
+}
@@ -0,0 +1,163 @@
+use std::fmt;
+
+use crate::parser::ast::Position;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TypeError {
+    UnknownType(String),
+    TypeArgumentCount {
+        name: String,
+        expected: usize,
+        found: usize,
+    },
+    DuplicateType(String),
+    DuplicateTypeParameter(String),
+    // Fields and methods of a struct share one namespace
+    DuplicateMember {
+        ty: String,
+        member: String,
+    },
+    UnknownMember {
+        ty: String,
+        member: String,
+    },
+    SelfOutsideMethod,
+    UnknownVariant(String),
+    PatternFieldCount {
+        variant: String,
+        expected: usize,
+        found: usize,
+    },
+    // A tuple pattern for a struct variant, or the other way round
+    PatternShape {
+        variant: String,
+        kind: &'static str,
+    },
+    // Struct variant fields a pattern leaves out without `..`
+    MissingPatternFields {
+        variant: String,
+        fields: Vec<String>,
+    },
+    PatternTypeMismatch {
+        expected: String,
+        found: String,
+    },
+    // The values no arm matches, as patterns
+    NonExhaustiveMatch(Vec<String>),
+    UnreachablePattern,
+}
+
+impl TypeError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            TypeError::UnknownType(_) => "T0001",
+            TypeError::TypeArgumentCount { .. } => "T0002",
+            TypeError::DuplicateType(_) => "T0003",
+            TypeError::DuplicateTypeParameter(_) => "T0004",
+            TypeError::DuplicateMember { .. } => "T0005",
+            TypeError::UnknownMember { .. } => "T0006",
+            TypeError::SelfOutsideMethod => "T0007",
+            TypeError::UnknownVariant(_) => "T0008",
+            TypeError::PatternFieldCount { .. } => "T0009",
+            TypeError::PatternShape { .. } => "T0010",
+            TypeError::MissingPatternFields { .. } => "T0011",
+            TypeError::PatternTypeMismatch { .. } => "T0012",
+            TypeError::NonExhaustiveMatch(_) => "T0013",
+            TypeError::UnreachablePattern => "T0014",
+        }
+    }
+}
+
+impl fmt::Display for TypeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TypeError::UnknownType(name) => write!(f, "Unknown type {}", name),
+            TypeError::TypeArgumentCount {
+                name,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Type {} expects {} type arguments, found {}",
+                name, expected, found
+            ),
+            TypeError::DuplicateType(name) => {
+                write!(f, "Type {} is declared more than once", name)
+            }
+            TypeError::DuplicateTypeParameter(name) => {
+                write!(f, "Type parameter {} is declared more than once", name)
+            }
+            TypeError::DuplicateMember { ty, member } => {
+                write!(f, "Type {} already has a member named {}", ty, member)
+            }
+            TypeError::UnknownMember { ty, member } => {
+                write!(f, "Type {} has no field or method named {}", ty, member)
+            }
+            TypeError::SelfOutsideMethod => write!(f, "&self is only allowed in methods"),
+            TypeError::UnknownVariant(name) => write!(f, "Unknown variant {}", name),
+            TypeError::PatternFieldCount {
+                variant,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Variant {} has {} fields, but the pattern has {}",
+                variant, expected, found
+            ),
+            TypeError::PatternShape { variant, kind } => {
+                write!(f, "Pattern does not fit the {} variant {}", kind, variant)
+            }
+            TypeError::MissingPatternFields { variant, fields } => write!(
+                f,
+                "Pattern for {} is missing fields {}",
+                variant,
+                fields.join(", ")
+            ),
+            TypeError::PatternTypeMismatch { expected, found } => {
+                write!(
+                    f,
+                    "Expected a pattern of type {}, found {}",
+                    expected, found
+                )
+            }
+            TypeError::NonExhaustiveMatch(missing) => match missing.as_slice() {
+                [one] => write!(f, "Non-exhaustive match: {} is not covered", one),
+                [init @ .., last] => write!(
+                    f,
+                    "Non-exhaustive match: {} and {} are not covered",
+                    init.join(", "),
+                    last
+                ),
+                [] => write!(f, "Non-exhaustive match"),
+            },
+            TypeError::UnreachablePattern => write!(f, "Unreachable pattern"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeDiagnostic {
+    pub error: TypeError,
+    pub position: Position,
+}
+
+impl TypeDiagnostic {
+    pub fn code(&self) -> &'static str {
+        self.error.code()
+    }
+}
+
+impl fmt::Display for TypeDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "error[{}] {}:{}: {}",
+            self.code(),
+            self.position.line,
+            self.position.column,
+            self.error
+        )
+    }
+}
+
+impl std::error::Error for TypeDiagnostic {}
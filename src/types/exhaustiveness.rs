@@ -0,0 +1,569 @@
+use std::fmt;
+
+use crate::parser::ast::{
+    EnumDecl, Literal, MatchArm, Pattern, PatternFields, PatternKind, Position, TypeRef,
+    VariantKind,
+};
+use crate::types::checker::TypeChecker;
+use crate::types::error::{TypeDiagnostic, TypeError};
+use crate::types::ty::{Primitive, Type};
+
+// A pattern reduced to the values it matches. Bindings become wildcards and
+// the fields of a struct variant are put in declaration order.
+#[derive(Debug, Clone)]
+enum Pat<'a> {
+    Wild,
+    Ctor(Ctor<'a>, Vec<Pat<'a>>),
+    Or(Vec<Pat<'a>>),
+}
+
+// What a value is built from at the top
+#[derive(Debug, Clone)]
+enum Ctor<'a> {
+    // The variant at an index of an enum
+    Variant(&'a EnumDecl, usize),
+    Bool(bool),
+    // Any other literal, of a type with too many values to list
+    Literal(String),
+}
+
+impl PartialEq for Ctor<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Ctor::Variant(a, i), Ctor::Variant(b, j)) => std::ptr::eq(*a, *b) && i == j,
+            (Ctor::Bool(a), Ctor::Bool(b)) => a == b,
+            (Ctor::Literal(a), Ctor::Literal(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl<'a> Ctor<'a> {
+    fn arity(&self) -> usize {
+        match self {
+            Ctor::Variant(decl, index) => match &decl.variants[*index].kind {
+                VariantKind::Unit => 0,
+                VariantKind::Tuple(types) => types.len(),
+                VariantKind::Struct(fields) => fields.len(),
+            },
+            Ctor::Bool(_) | Ctor::Literal(_) => 0,
+        }
+    }
+
+    // Every constructor of the type this one belongs to, if they can be listed
+    fn signature(&self) -> Option<Vec<Ctor<'a>>> {
+        match self {
+            Ctor::Variant(decl, _) => Some(
+                (0..decl.variants.len())
+                    .map(|index| Ctor::Variant(decl, index))
+                    .collect(),
+            ),
+            Ctor::Bool(_) => Some(vec![Ctor::Bool(true), Ctor::Bool(false)]),
+            Ctor::Literal(_) => None,
+        }
+    }
+}
+
+// Witnesses print as the source pattern that would cover them
+impl fmt::Display for Pat<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Pat::Wild => f.write_str("_"),
+            Pat::Or(alternatives) => {
+                let alternatives: Vec<_> = alternatives.iter().map(Pat::to_string).collect();
+                f.write_str(&alternatives.join(" | "))
+            }
+            Pat::Ctor(Ctor::Bool(value), _) => write!(f, "{}", value),
+            Pat::Ctor(Ctor::Literal(text), _) => f.write_str(text),
+            Pat::Ctor(Ctor::Variant(decl, index), args) => {
+                let variant = &decl.variants[*index];
+                write!(f, "{}::{}", decl.name, variant.name)?;
+
+                match &variant.kind {
+                    VariantKind::Unit => Ok(()),
+                    VariantKind::Tuple(_) => {
+                        let args: Vec<_> = args.iter().map(Pat::to_string).collect();
+                        write!(f, "({})", args.join(", "))
+                    }
+                    VariantKind::Struct(fields) => {
+                        // Fields that may hold anything are left to `..`
+                        let named: Vec<_> = fields
+                            .iter()
+                            .zip(args)
+                            .filter(|(_, arg)| !matches!(arg, Pat::Wild))
+                            .map(|(field, arg)| format!("{}: {}", field.name, arg))
+                            .collect();
+                        if named.is_empty() {
+                            f.write_str(" { .. }")
+                        } else if named.len() == fields.len() {
+                            write!(f, " {{ {} }}", named.join(", "))
+                        } else {
+                            write!(f, " {{ {}, .. }}", named.join(", "))
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+type Row<'a> = Vec<Pat<'a>>;
+
+// `row` once for each alternative of the `|` pattern at its head
+fn expand<'a>(alternatives: &[Pat<'a>], row: &Row<'a>) -> Vec<Row<'a>> {
+    alternatives
+        .iter()
+        .map(|alternative| {
+            let mut expanded = vec![alternative.clone()];
+            expanded.extend_from_slice(&row[1..]);
+            expanded
+        })
+        .collect()
+}
+
+// Rows that can match a value built by `ctor`, with their head replaced by
+// patterns for its fields
+fn specialize<'a>(rows: &[Row<'a>], ctor: &Ctor<'a>) -> Vec<Row<'a>> {
+    let mut specialized = Vec::new();
+
+    for row in rows {
+        match &row[0] {
+            Pat::Wild => {
+                let mut fields = vec![Pat::Wild; ctor.arity()];
+                fields.extend_from_slice(&row[1..]);
+                specialized.push(fields);
+            }
+            Pat::Ctor(head, args) if head == ctor => {
+                let mut fields = args.clone();
+                fields.extend_from_slice(&row[1..]);
+                specialized.push(fields);
+            }
+            Pat::Ctor(..) => {}
+            Pat::Or(alternatives) => {
+                specialized.extend(specialize(&expand(alternatives, row), ctor));
+            }
+        }
+    }
+    specialized
+}
+
+// Rows whose head matches anything, without that head
+fn default_rows<'a>(rows: &[Row<'a>]) -> Vec<Row<'a>> {
+    let mut defaults = Vec::new();
+
+    for row in rows {
+        match &row[0] {
+            Pat::Wild => defaults.push(row[1..].to_vec()),
+            Pat::Ctor(..) => {}
+            Pat::Or(alternatives) => defaults.extend(default_rows(&expand(alternatives, row))),
+        }
+    }
+    defaults
+}
+
+fn head_ctors<'a>(pattern: &Pat<'a>, ctors: &mut Vec<Ctor<'a>>) {
+    match pattern {
+        Pat::Wild => {}
+        Pat::Ctor(ctor, _) => {
+            if !ctors.contains(ctor) {
+                ctors.push(ctor.clone());
+            }
+        }
+        Pat::Or(alternatives) => {
+            for alternative in alternatives {
+                head_ctors(alternative, ctors);
+            }
+        }
+    }
+}
+
+// `witness` with its first `arity` patterns folded back into `ctor`
+fn rebuild<'a>(ctor: &Ctor<'a>, mut witness: Row<'a>) -> Row<'a> {
+    let rest = witness.split_off(ctor.arity());
+    let mut rebuilt = vec![Pat::Ctor(ctor.clone(), witness)];
+    rebuilt.extend(rest);
+    rebuilt
+}
+
+// Values matched by `vector` that no row matches, as witness rows. Empty
+// when every value `vector` matches is already covered by `rows`.
+fn usefulness<'a>(rows: &[Row<'a>], vector: &[Pat<'a>]) -> Vec<Row<'a>> {
+    let Some((head, tail)) = vector.split_first() else {
+        return if rows.is_empty() {
+            vec![Vec::new()]
+        } else {
+            Vec::new()
+        };
+    };
+
+    match head {
+        Pat::Ctor(ctor, args) => {
+            let mut vector = args.clone();
+            vector.extend_from_slice(tail);
+            usefulness(&specialize(rows, ctor), &vector)
+                .into_iter()
+                .map(|witness| rebuild(ctor, witness))
+                .collect()
+        }
+        Pat::Or(alternatives) => alternatives
+            .iter()
+            .flat_map(|alternative| {
+                let mut vector = vec![alternative.clone()];
+                vector.extend_from_slice(tail);
+                usefulness(rows, &vector)
+            })
+            .collect(),
+        Pat::Wild => {
+            let mut used = Vec::new();
+            for row in rows {
+                head_ctors(&row[0], &mut used);
+            }
+            let signature = used.first().and_then(Ctor::signature);
+
+            // With every constructor present, each must be checked on its own
+            if let Some(signature) = &signature
+                && signature.iter().all(|ctor| used.contains(ctor))
+            {
+                return signature
+                    .iter()
+                    .flat_map(|ctor| {
+                        let mut vector = vec![Pat::Wild; ctor.arity()];
+                        vector.extend_from_slice(tail);
+                        usefulness(&specialize(rows, ctor), &vector)
+                            .into_iter()
+                            .map(|witness| rebuild(ctor, witness))
+                    })
+                    .collect();
+            }
+
+            // Otherwise a missing constructor is uncovered whenever the rest is
+            let witnesses = usefulness(&default_rows(rows), tail);
+            let heads: Vec<Pat<'a>> = match signature {
+                Some(signature) => signature
+                    .into_iter()
+                    .filter(|ctor| !used.contains(ctor))
+                    .map(|ctor| Pat::Ctor(ctor.clone(), vec![Pat::Wild; ctor.arity()]))
+                    .collect(),
+                None => vec![Pat::Wild],
+            };
+            witnesses
+                .iter()
+                .flat_map(|witness| {
+                    heads.iter().map(move |head| {
+                        let mut row = vec![head.clone()];
+                        row.extend_from_slice(witness);
+                        row
+                    })
+                })
+                .collect()
+        }
+    }
+}
+
+// Checks the arms of a `match` against the enum they take apart. Reports
+// malformed patterns, arms that earlier arms already cover, and values that
+// no arm covers. `scrutinee` is the type of the matched value, if known.
+pub fn check_match<'a>(
+    checker: &TypeChecker<'a>,
+    scrutinee: Option<&Type>,
+    arms: &[MatchArm],
+    position: Position,
+    diagnostics: &mut Vec<TypeDiagnostic>,
+) {
+    let expected = match scrutinee.map(Type::dereference) {
+        Some(Type::Enum { name, .. }) => checker.enum_decl(name),
+        _ => arms
+            .iter()
+            .find_map(|arm| written_enum(checker, &arm.pattern)),
+    };
+
+    let errors = diagnostics.len();
+    let patterns: Vec<_> = arms
+        .iter()
+        .map(|arm| lower(checker, &arm.pattern, expected, diagnostics))
+        .collect();
+    // Coverage means little once a pattern is malformed
+    if diagnostics.len() > errors {
+        return;
+    }
+
+    let mut rows = Vec::new();
+    for (arm, pattern) in arms.iter().zip(patterns) {
+        if usefulness(&rows, std::slice::from_ref(&pattern)).is_empty() {
+            diagnostics.push(TypeDiagnostic {
+                error: TypeError::UnreachablePattern,
+                position: arm.pattern.position,
+            });
+        } else if let (Pat::Or(alternatives), PatternKind::Or(written)) =
+            (&pattern, &arm.pattern.kind)
+        {
+            // `A | A` repeats an alternative within one arm
+            let mut seen = rows.clone();
+            for (alternative, written) in alternatives.iter().zip(written) {
+                if usefulness(&seen, std::slice::from_ref(alternative)).is_empty() {
+                    diagnostics.push(TypeDiagnostic {
+                        error: TypeError::UnreachablePattern,
+                        position: written.position,
+                    });
+                }
+                seen.push(vec![alternative.clone()]);
+            }
+        }
+
+        // A guard may fail, so a guarded arm covers nothing for later arms
+        if arm.guard.is_none() {
+            rows.push(vec![pattern]);
+        }
+    }
+
+    let mut missing: Vec<String> = Vec::new();
+    for witness in usefulness(&rows, &[Pat::Wild]) {
+        let witness = witness[0].to_string();
+        if !missing.contains(&witness) {
+            missing.push(witness);
+        }
+    }
+    if !missing.is_empty() {
+        diagnostics.push(TypeDiagnostic {
+            error: TypeError::NonExhaustiveMatch(missing),
+            position,
+        });
+    }
+}
+
+// The enum named by the first variant path an arm spells out
+fn written_enum<'a>(checker: &TypeChecker<'a>, pattern: &Pattern) -> Option<&'a EnumDecl> {
+    match &pattern.kind {
+        PatternKind::Variant { path, .. } => {
+            resolve_variant(checker, path, None).map(|(decl, _)| decl)
+        }
+        PatternKind::Or(alternatives) => alternatives
+            .iter()
+            .find_map(|alternative| written_enum(checker, alternative)),
+        _ => None,
+    }
+}
+
+// `Enum::Variant`, or a bare variant name from the enum the pattern is
+// expected to match or else from the only enum that declares it
+fn resolve_variant<'a>(
+    checker: &TypeChecker<'a>,
+    path: &[String],
+    expected: Option<&'a EnumDecl>,
+) -> Option<(&'a EnumDecl, usize)> {
+    let index_in = |decl: &EnumDecl, name: &str| {
+        decl.variants
+            .iter()
+            .position(|variant| variant.name == name)
+    };
+
+    match path {
+        [enum_name, name] => {
+            let decl = checker.enum_decl(enum_name)?;
+            Some((decl, index_in(decl, name)?))
+        }
+        [name] => {
+            if let Some(decl) = expected
+                && let Some(index) = index_in(decl, name)
+            {
+                return Some((decl, index));
+            }
+            let mut declaring = checker
+                .enum_decls()
+                .filter_map(|decl| Some((decl, index_in(decl, name)?)));
+            match (declaring.next(), declaring.next()) {
+                (Some(found), None) => Some(found),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+fn lower<'a>(
+    checker: &TypeChecker<'a>,
+    pattern: &Pattern,
+    expected: Option<&'a EnumDecl>,
+    diagnostics: &mut Vec<TypeDiagnostic>,
+) -> Pat<'a> {
+    let mut error = |error| {
+        diagnostics.push(TypeDiagnostic {
+            error,
+            position: pattern.position,
+        });
+        Pat::Wild
+    };
+
+    match &pattern.kind {
+        PatternKind::Wildcard => Pat::Wild,
+        // A name that is also a variant means the variant, as it does in
+        // expressions
+        PatternKind::Binding(name) => {
+            match resolve_variant(checker, std::slice::from_ref(name), expected) {
+                Some(_) => lower_variant(
+                    checker,
+                    pattern,
+                    std::slice::from_ref(name),
+                    &PatternFields::Unit,
+                    expected,
+                    diagnostics,
+                ),
+                None => Pat::Wild,
+            }
+        }
+        PatternKind::Literal(literal) => {
+            if let Some(decl) = expected {
+                return error(TypeError::PatternTypeMismatch {
+                    expected: decl.name.clone(),
+                    found: literal_type(literal).to_string(),
+                });
+            }
+            match literal {
+                Literal::Bool(value) => Pat::Ctor(Ctor::Bool(*value), Vec::new()),
+                other => Pat::Ctor(Ctor::Literal(literal_text(other)), Vec::new()),
+            }
+        }
+        PatternKind::Variant { path, fields } => {
+            lower_variant(checker, pattern, path, fields, expected, diagnostics)
+        }
+        PatternKind::Or(alternatives) => Pat::Or(
+            alternatives
+                .iter()
+                .map(|alternative| lower(checker, alternative, expected, diagnostics))
+                .collect(),
+        ),
+    }
+}
+
+fn lower_variant<'a>(
+    checker: &TypeChecker<'a>,
+    pattern: &Pattern,
+    path: &[String],
+    fields: &PatternFields,
+    expected: Option<&'a EnumDecl>,
+    diagnostics: &mut Vec<TypeDiagnostic>,
+) -> Pat<'a> {
+    let mut error = |error| {
+        diagnostics.push(TypeDiagnostic {
+            error,
+            position: pattern.position,
+        });
+        Pat::Wild
+    };
+
+    let Some((decl, index)) = resolve_variant(checker, path, expected) else {
+        return error(TypeError::UnknownVariant(path.join("::")));
+    };
+    if let Some(expected) = expected
+        && !std::ptr::eq(expected, decl)
+    {
+        return error(TypeError::PatternTypeMismatch {
+            expected: expected.name.clone(),
+            found: decl.name.clone(),
+        });
+    }
+
+    let variant = &decl.variants[index];
+    let name = format!("{}::{}", decl.name, variant.name);
+    // Fields of enum type narrow the patterns inside them; type parameters
+    // could be anything
+    let field_enum = |ty: &TypeRef| {
+        if decl.generics.iter().any(|param| param.name == ty.name) {
+            None
+        } else {
+            checker.enum_decl(&ty.name)
+        }
+    };
+
+    let args = match (&variant.kind, fields) {
+        (VariantKind::Unit, PatternFields::Unit) => Vec::new(),
+        (VariantKind::Tuple(types), PatternFields::Tuple(patterns)) => {
+            if types.len() != patterns.len() {
+                return error(TypeError::PatternFieldCount {
+                    variant: name,
+                    expected: types.len(),
+                    found: patterns.len(),
+                });
+            }
+            types
+                .iter()
+                .zip(patterns)
+                .map(|(ty, pattern)| lower(checker, pattern, field_enum(ty), diagnostics))
+                .collect()
+        }
+        (VariantKind::Struct(declared), PatternFields::Struct { fields, rest }) => {
+            let mut args = vec![Pat::Wild; declared.len()];
+            let mut mentioned = vec![false; declared.len()];
+
+            for (field, pattern) in fields {
+                match declared.iter().position(|decl| decl.name == *field) {
+                    Some(index) => {
+                        let ty = &declared[index].ty;
+                        args[index] = lower(checker, pattern, field_enum(ty), diagnostics);
+                        mentioned[index] = true;
+                    }
+                    None => diagnostics.push(TypeDiagnostic {
+                        error: TypeError::UnknownMember {
+                            ty: name.clone(),
+                            member: field.clone(),
+                        },
+                        position: pattern.position,
+                    }),
+                }
+            }
+
+            let missing: Vec<String> = declared
+                .iter()
+                .zip(mentioned)
+                .filter(|(_, mentioned)| !mentioned)
+                .map(|(field, _)| field.name.clone())
+                .collect();
+            if !rest && !missing.is_empty() {
+                diagnostics.push(TypeDiagnostic {
+                    error: TypeError::MissingPatternFields {
+                        variant: name,
+                        fields: missing,
+                    },
+                    position: pattern.position,
+                });
+            }
+            args
+        }
+        (kind, _) => {
+            let kind = match kind {
+                VariantKind::Unit => "unit",
+                VariantKind::Tuple(_) => "tuple",
+                VariantKind::Struct(_) => "struct",
+            };
+            return error(TypeError::PatternShape {
+                variant: name,
+                kind,
+            });
+        }
+    };
+
+    Pat::Ctor(Ctor::Variant(decl, index), args)
+}
+
+fn literal_type(literal: &Literal) -> &'static str {
+    match literal {
+        Literal::Integer(_) => Primitive::Int.name(),
+        Literal::Float(_) => Primitive::Float.name(),
+        Literal::String(_) => Primitive::String.name(),
+        Literal::Char(_) => Primitive::Char.name(),
+        Literal::Bool(_) => Primitive::Bool.name(),
+        Literal::Null => "null",
+    }
+}
+
+// Spelling that tells two literals of the same type apart
+fn literal_text(literal: &Literal) -> String {
+    match literal {
+        Literal::Integer(value) => value.to_string(),
+        Literal::Float(value) => value.to_string(),
+        Literal::String(value) => format!("{:?}", value),
+        Literal::Char(value) => format!("{:?}", value),
+        Literal::Bool(value) => value.to_string(),
+        Literal::Null => "null".to_string(),
+    }
+}
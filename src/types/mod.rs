@@ -0,0 +1,12 @@
+#![allow(dead_code, unused, unused_imports)]
+
+pub mod checker;
+pub mod error;
+pub mod exhaustiveness;
+pub mod ty;
+
+// Re-export main types for easier access
+pub use checker::TypeChecker;
+pub use error::{TypeDiagnostic, TypeError};
+pub use exhaustiveness::check_match;
+pub use ty::{Primitive, Type};
@@ -0,0 +1,124 @@
+use std::fmt;
+
+use crate::parser::ast::GenericParam;
+
+// Built-in types, which take no type arguments
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Primitive {
+    Int,
+    Float,
+    I8,
+    I16,
+    I32,
+    I64,
+    U8,
+    U16,
+    U32,
+    U64,
+    F32,
+    F64,
+    Bool,
+    Char,
+    String,
+}
+
+const PRIMITIVES: &[(&str, Primitive)] = &[
+    ("int", Primitive::Int),
+    ("float", Primitive::Float),
+    ("i8", Primitive::I8),
+    ("i16", Primitive::I16),
+    ("i32", Primitive::I32),
+    ("i64", Primitive::I64),
+    ("u8", Primitive::U8),
+    ("u16", Primitive::U16),
+    ("u32", Primitive::U32),
+    ("u64", Primitive::U64),
+    ("f32", Primitive::F32),
+    ("f64", Primitive::F64),
+    ("bool", Primitive::Bool),
+    ("char", Primitive::Char),
+    ("string", Primitive::String),
+];
+
+impl Primitive {
+    pub fn from_name(name: &str) -> Option<Self> {
+        PRIMITIVES
+            .iter()
+            .find(|(spelling, _)| *spelling == name)
+            .map(|(_, primitive)| *primitive)
+    }
+
+    pub fn name(&self) -> &'static str {
+        PRIMITIVES
+            .iter()
+            .find(|(_, primitive)| primitive == self)
+            .map(|(spelling, _)| *spelling)
+            .expect("every primitive has a spelling")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Type {
+    Primitive(Primitive),
+    // A type parameter of the enclosing struct or enum
+    Param(String),
+    Struct { name: String, args: Vec<Type> },
+    Enum { name: String, args: Vec<Type> },
+    Reference(Box<Type>),
+}
+
+impl Type {
+    // Replaces each of `params` with the type at the same index in `args`
+    pub fn substitute(&self, params: &[GenericParam], args: &[Type]) -> Type {
+        match self {
+            Type::Param(name) => params
+                .iter()
+                .position(|param| param.name == *name)
+                .and_then(|index| args.get(index))
+                .cloned()
+                .unwrap_or_else(|| self.clone()),
+            Type::Struct { name, args: inner } => Type::Struct {
+                name: name.clone(),
+                args: inner
+                    .iter()
+                    .map(|arg| arg.substitute(params, args))
+                    .collect(),
+            },
+            Type::Enum { name, args: inner } => Type::Enum {
+                name: name.clone(),
+                args: inner
+                    .iter()
+                    .map(|arg| arg.substitute(params, args))
+                    .collect(),
+            },
+            Type::Reference(inner) => Type::Reference(Box::new(inner.substitute(params, args))),
+            Type::Primitive(_) => self.clone(),
+        }
+    }
+
+    // The type behind any number of `&`
+    pub fn dereference(&self) -> &Type {
+        match self {
+            Type::Reference(inner) => inner.dereference(),
+            _ => self,
+        }
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Type::Primitive(primitive) => f.write_str(primitive.name()),
+            Type::Param(name) => f.write_str(name),
+            Type::Struct { name, args } | Type::Enum { name, args } => {
+                f.write_str(name)?;
+                if !args.is_empty() {
+                    let args: Vec<_> = args.iter().map(Type::to_string).collect();
+                    write!(f, "<{}>", args.join(", "))?;
+                }
+                Ok(())
+            }
+            Type::Reference(inner) => write!(f, "&{}", inner),
+        }
+    }
+}